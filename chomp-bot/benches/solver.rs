@@ -0,0 +1,90 @@
+//! `cargo bench -p chomp-bot [--features parallel]`: how long a cold
+//! `PositionTable` build takes, and what a `best_reply` lookup costs over
+//! random reachable positions.
+//!
+//! Baseline on a single-core Linux sandbox (release profile, criterion's
+//! median estimate, one-byte book entries):
+//!
+//! | benchmark                         | default  | `--features parallel` |
+//! |-----------------------------------|----------|-----------------------|
+//! | `table/new 5x8`                   | 2.80 ms  | 2.14 ms               |
+//! | `table/new 12x8`                  | 711 ms   | 418 ms                |
+//! | `best_reply/1024 random skylines` | 42 µs    | 56 µs                 |
+//!
+//! With one core rayon has no second thread to hand a layer to, so the build
+//! speed-up under `parallel` comes from `new_parallel`'s layered sweep rather
+//! than from concurrency: it classifies each position once, in order, where
+//! `new` pushes every index onto its stack and pops it twice. Lookups run the
+//! same code under either feature, so the gap there is noise between runs. A
+//! build time well above these on similar hardware is a regression worth a
+//! look.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// The bin crate has no library to link, so the bench compiles the solver the
+// way build.rs does. Benches build with `cfg(test)` but no test harness, so
+// the file's test module is left with nothing to use its imports.
+#[allow(dead_code, unused_imports)]
+#[path = "../src/table.rs"]
+mod table;
+
+use table::{BoardShape, PositionTable, Rules};
+
+/// Lookups per `best_reply` iteration.
+const QUERIES: usize = 1024;
+
+/// A random reachable position on `shape`: eaten counts per row that never
+/// grow down the board.
+fn random_skyline(rng: &mut StdRng, shape: BoardShape) -> Vec<u8> {
+    let mut above = shape.cols();
+    (0..shape.rows())
+        .map(|_| {
+            above = rng.gen_range(0..=above);
+            above
+        })
+        .collect()
+}
+
+fn table_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("table");
+    group.bench_function("new 5x8", |b| {
+        b.iter(|| PositionTable::solve(black_box(BoardShape::STANDARD), Rules::PoisonLoses))
+    });
+    // 2^20 entries: big enough for the search to dominate, small enough to
+    // sample in seconds.
+    let big = BoardShape::new(12, 8).expect("12x8 fits the table limit");
+    group.sample_size(10);
+    group.bench_function("new 12x8", |b| {
+        b.iter(|| PositionTable::solve(black_box(big), Rules::PoisonLoses))
+    });
+    group.finish();
+}
+
+fn best_reply(c: &mut Criterion) {
+    let shape = BoardShape::STANDARD;
+    let table = PositionTable::solve(shape, Rules::PoisonLoses);
+    let mut rng = StdRng::seed_from_u64(0);
+    c.bench_function(&format!("best_reply/{} random skylines", QUERIES), |b| {
+        b.iter_batched(
+            || {
+                (0..QUERIES)
+                    .map(|_| random_skyline(&mut rng, shape))
+                    .collect::<Vec<_>>()
+            },
+            |positions| {
+                positions
+                    .iter()
+                    .filter_map(|counts| table.best_reply(black_box(counts)))
+                    .count()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, table_build, best_reply);
+criterion_main!(benches);
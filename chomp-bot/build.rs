@@ -0,0 +1,24 @@
+//! Under the `embed-table` feature, solves the position table once and writes
+//! it to `$OUT_DIR/table.bin` for `game.rs` to `include_bytes!`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[allow(dead_code)]
+#[path = "src/table.rs"]
+mod table;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/table.rs");
+    if env::var_os("CARGO_FEATURE_EMBED_TABLE").is_none() {
+        return;
+    }
+    let out = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set")).join("table.bin");
+    fs::write(
+        &out,
+        table::PositionTable::solve(table::BoardShape::STANDARD, table::Rules::PoisonLoses)
+            .to_bytes(),
+    )
+    .unwrap_or_else(|e| panic!("failed to write {}: {}", out.display(), e));
+}
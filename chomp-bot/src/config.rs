@@ -1,34 +1,455 @@
-use clap::Parser;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
+use std::ffi::OsString;
+
+use clap::{
+    parser::ValueSource, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
+use serde::Deserialize;
+use solana_sdk::{
+    bs58,
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
+};
+
+use crate::game::{validate_board, Rules, Skyline};
+use crate::render::{BoardFormat, ColorChoice, OutputFormat};
+use crate::solana::{AccountLayout, BoardLayout, PriorityFee};
+use crate::table::BoardShape;
+
+pub const DEFAULT_PROGRAM_ID: &str = "ChompZg47TcVy5fk2LxPEpW6SytFYBES5SHoqgrm8A4D";
+pub const DEFAULT_FEE_COLLECTOR: &str = "EGJnqcxVbhJFJ6Xnchtaw8jmPSvoLXfN2gWsY9Etz5SZ";
+pub const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+/// Where `--fork` looks for a local mainnet fork (Surfpool, or a validator with cloned accounts).
+pub const FORK_RPC_URL: &str = "http://127.0.0.1:8899";
+
+/// The Solana CLI's keypair under the home directory (`%USERPROFILE%` on
+/// Windows), or `./id.json` when there is none.
 pub fn default_keypair_path() -> String {
-    std::env::var("HOME")
-        .map(|h| format!("{h}/.config/solana/id.json"))
-        .unwrap_or_else(|_| "./id.json".to_string())
+    keypair_path_in(dirs_next::home_dir().as_deref())
 }
 
+fn keypair_path_in(home: Option<&Path>) -> String {
+    let Some(home) = home else {
+        return "./id.json".to_string();
+    };
+    let path = home.join(".config").join("solana").join("id.json");
+    path.display().to_string()
+}
+
+/// `p` with a leading `~` replaced by the home directory.
 pub fn expand_home(p: &str) -> String {
-    if let Ok(home) = std::env::var("HOME") {
-        if p.starts_with("~/") {
-            return format!("{home}/{}", &p[2..]);
+    expand_home_in(p, dirs_next::home_dir().as_deref())
+}
+
+fn expand_home_in(p: &str, home: Option<&Path>) -> String {
+    let Some(home) = home else {
+        return p.to_string();
+    };
+    if p == "~" {
+        return home.display().to_string();
+    }
+    match p
+        .strip_prefix("~/")
+        .or_else(|| p.strip_prefix("~\\").filter(|_| cfg!(windows)))
+    {
+        Some(rest) => home.join(rest).display().to_string(),
+        None => p.to_string(),
+    }
+}
+
+/// A secret given on the command line or through the environment. `Debug`
+/// leaves it out, so logging the `Cli` cannot leak it.
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+/// Never fails: a parse error would echo the value, so the secret is only
+/// checked by `load_keypair`.
+fn parse_secret(s: &str) -> Result<Secret, String> {
+    Ok(Secret(s.to_string()))
+}
+
+/// The payer: `--keypair-base58` or `KEYPAIR_SECRET` when set, else the
+/// `--keypair` file.
+pub fn load_keypair(cli: &Cli) -> Result<Keypair, String> {
+    match &cli.keypair_base58 {
+        Some(secret) => keypair_from_base58(&secret.0),
+        None => {
+            let path = expand_home(&cli.keypair_path);
+            read_keypair_file(&path).map_err(|e| format!("failed to read keypair at {}: {}", path, e))
         }
     }
-    p.to_string()
+}
+
+/// Errors name the source, never the secret.
+fn keypair_from_base58(secret: &str) -> Result<Keypair, String> {
+    let bytes = bs58::decode(secret.trim())
+        .into_vec()
+        .map_err(|_| "--keypair-base58 / KEYPAIR_SECRET is not valid base58".to_string())?;
+    Keypair::from_bytes(&bytes).map_err(|_| {
+        format!(
+            "--keypair-base58 / KEYPAIR_SECRET decodes to {} bytes, not a 64-byte keypair",
+            bytes.len()
+        )
+    })
+}
+
+/// The keypair files `--keypairs` names: every `*.json` in a directory, or the
+/// files whose name matches the `*` wildcards of the last path component.
+/// Sorted, so wallets always start in the same order.
+pub fn keypair_files(spec: &str) -> std::io::Result<Vec<PathBuf>> {
+    let spec = PathBuf::from(expand_home(spec));
+    let (dir, pattern) = if spec.is_dir() {
+        (spec, "*.json".to_string())
+    } else {
+        let name = spec
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !name.contains('*') {
+            return Ok(vec![spec]);
+        }
+        let dir = spec
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        (dir.to_path_buf(), name)
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let matched = path
+            .file_name()
+            .is_some_and(|n| wildcard_match(&pattern, &n.to_string_lossy()));
+        if matched && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// How the bot picks its move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StrategyKind {
+    /// Stored forced win, else `best_try`.
+    Optimal,
+    /// Winning (or legal) move leaving the opponent the fewest replies.
+    Constrain,
+    /// Biggest bite that is not the poison; no solver.
+    Greedy,
+    /// Random legal move, seeded by `--seed`; no solver.
+    Random,
+    /// First open square from the bottom row up, poison last; no solver.
+    AnyLegal,
+}
+
+/// How settled the state the bot reads and waits for must be.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Commitment {
+    /// Seen by the node; may still be rolled back.
+    Processed,
+    /// Voted on by a supermajority of the cluster.
+    Confirmed,
+    /// Rooted; cannot be rolled back.
+    Finalized,
+}
+
+impl Commitment {
+    pub fn config(self) -> CommitmentConfig {
+        match self {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+/// A public cluster `--network` points `--rpc` at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Network {
+    Devnet,
+    Testnet,
+    Mainnet,
+}
+
+impl Network {
+    /// The cluster's canonical public RPC endpoint.
+    pub fn url(self) -> &'static str {
+        match self {
+            Network::Devnet => "https://api.devnet.solana.com",
+            Network::Testnet => "https://api.testnet.solana.com",
+            Network::Mainnet => DEFAULT_RPC_URL,
+        }
+    }
+}
+
+/// Parses a move written as `r,c`.
+pub fn parse_move(s: &str) -> Result<(u8, u8), String> {
+    let (r, c) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected r,c but got `{s}`"))?;
+    let r = r.trim().parse().map_err(|_| format!("bad row in `{s}`"))?;
+    let c = c.trim().parse().map_err(|_| format!("bad column in `{s}`"))?;
+    Ok((r, c))
+}
+
+/// Parses a typed board for `--board`: five comma-separated rows, top first,
+/// each as eight binary digits (`1` for eaten, column 1 first) or two hex digits,
+/// or the whole board as ten hex digits. It must be a position the program can
+/// reach, eaten from the top-left in a staircase.
+pub fn parse_board(s: &str) -> Result<[u8; 5], String> {
+    let s = s.trim();
+    let specs: Vec<String> = if s.contains(',') {
+        s.split(',').map(|row| row.trim().to_string()).collect()
+    } else if s.len() == 10 && s.is_ascii() {
+        (0..5).map(|i| s[2 * i..2 * i + 2].to_string()).collect()
+    } else {
+        return Err(format!("expected five comma-separated rows or ten hex digits, got `{s}`"));
+    };
+    if specs.len() != 5 {
+        return Err(format!("expected 5 rows, got {} in `{s}`", specs.len()));
+    }
+    let mut board = [0u8; 5];
+    for (i, (row, spec)) in board.iter_mut().zip(&specs).enumerate() {
+        let hex = spec.strip_prefix("0x").unwrap_or(spec);
+        *row = match hex.len() {
+            8 if spec.bytes().all(|b| b == b'0' || b == b'1') => u8::from_str_radix(spec, 2),
+            2 => u8::from_str_radix(hex, 16),
+            _ => return Err(format!("row {} `{spec}` is not 8 binary or 2 hex digits", i + 1)),
+        }
+        .map_err(|_| format!("row {} `{spec}` is not 8 binary or 2 hex digits", i + 1))?;
+    }
+    validate_board(board)?;
+    Ok(board)
+}
+
+/// Parses a positive amount of SOL, for `airdrop --amount-sol`.
+fn parse_sol(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(sol) if sol.is_finite() && sol > 0.0 => Ok(sol),
+        _ => Err(format!("`{s}` is not a positive amount of SOL")),
+    }
+}
+
+/// Parses a base58 pubkey, for `--program`, `--collector` and their env vars.
+pub fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    s.trim()
+        .parse()
+        .map_err(|e| format!("`{s}` is not a base58 pubkey: {e}"))
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Serve the solver over HTTP: `POST /best-move` with `{"rows":[..5 bytes..]}`, `GET /health`.
+    Serve {
+        #[arg(long = "addr", default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+    /// Print the game PDA and bump for a player, and its board if the account exists.
+    Pda {
+        /// Player pubkey; defaults to the `--keypair` pubkey.
+        #[arg(long = "player")]
+        player: Option<String>,
+    },
+    /// Play the game by hand: the board, the solver's pick and every winning
+    /// reply are shown, then a move, `hint`, `auto` or `quit` is read from stdin.
+    Interactive,
+    /// List every open game of `--program`, with its board and stake. A game's
+    /// player is shown when its PDA derives from `--player` or the `--keypair` pubkey.
+    ListGames {
+        #[arg(long = "player", value_parser = parse_pubkey)]
+        players: Vec<Pubkey>,
+    },
+    /// Time blockhash, signing and simulation of our next move against `--rpc`. Never sends.
+    PingMove {
+        #[arg(long = "samples", default_value_t = 5)]
+        samples: u32,
+    },
+    /// Request devnet or testnet SOL for the payer from the cluster's faucet and
+    /// wait for it to confirm. Refuses any other cluster.
+    Airdrop {
+        #[arg(long = "amount-sol", default_value_t = 1.0, value_parser = parse_sol)]
+        amount_sol: f64,
+    },
+    /// Play one game in memory between two strategies from the empty board and
+    /// print the moves and the winner. Nothing touches the chain.
+    Simulate {
+        #[arg(long = "first", value_enum, default_value_t = StrategyKind::Optimal)]
+        first: StrategyKind,
+        #[arg(long = "second", value_enum, default_value_t = StrategyKind::AnyLegal)]
+        second: StrategyKind,
+    },
 }
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "chomp-strat-bot", author, version, about = "Baseline Chomp/Glass strat bot for Solana")]
 pub struct Cli {
-    #[arg(long = "rpc", default_value = "https://api.mainnet-beta.solana.com")]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML (or `.json`) file of defaults keyed by flag name, e.g. `rpc = "..."`;
+    /// flags given on the command line or through the environment win.
+    #[arg(long = "config")]
+    pub config: Option<String>,
+
+    #[arg(long = "rpc", default_value = DEFAULT_RPC_URL)]
     pub rpc_url: String,
 
+    /// Whether `--rpc` was given, by hand or through `--config`, rather than
+    /// left at its default; set by `load`.
+    #[arg(skip)]
+    rpc_given: bool,
+
+    /// Use this cluster's public RPC endpoint; an explicit `--rpc` still wins.
+    #[arg(long = "network", value_enum, conflicts_with = "fork")]
+    pub network: Option<Network>,
+
+    /// Play against a local fork of mainnet: `--rpc` defaults to 127.0.0.1:8899, reads use
+    /// `confirmed`, and the mainnet program and collector defaults are kept without the
+    /// mainnet new-game guard.
+    #[arg(long = "fork", default_value_t = false)]
+    pub fork: bool,
+
+    /// Commitment for reading the board and confirming transactions
+    /// [default: finalized, or confirmed with --fork].
+    #[arg(long = "commitment", value_enum)]
+    pub commitment: Option<Commitment>,
+
+    /// Game account layout to decode; `auto` picks by data length.
+    #[arg(long = "account-version", value_enum, default_value_t = AccountLayout::Auto)]
+    pub account_version: AccountLayout,
+
+    /// Where the board sits in the account data, e.g. `offset=8,rows=5` behind an
+    /// 8-byte discriminator. Overrides `--account-version`.
+    #[arg(long = "board-layout")]
+    pub board_layout: Option<BoardLayout>,
+
+    /// Second RPC endpoint, used when a call to `--rpc` fails with a transport error.
+    #[arg(long = "rpc-fallback")]
+    pub rpc_fallback: Option<String>,
+
+    /// WebSocket endpoint; autoplay then waits for the game account to change
+    /// instead of polling it every `--interval_ms`.
+    #[arg(long = "ws")]
+    pub ws: Option<String>,
+
     #[arg(long = "keypair", default_value_t = default_keypair_path())]
     pub keypair_path: String,
 
-    #[arg(long = "program", env = "PROGRAM_ID", default_value = "ChompZg47TcVy5fk2LxPEpW6SytFYBES5SHoqgrm8A4D")]
-    pub program_id: String,
+    /// The payer's 64-byte secret key in base58, as Phantom exports it, instead of a `--keypair` file.
+    #[arg(
+        long = "keypair-base58",
+        env = "KEYPAIR_SECRET",
+        hide_env_values = true,
+        conflicts_with = "keypair_path",
+        value_parser = parse_secret
+    )]
+    pub keypair_base58: Option<Secret>,
+
+    #[arg(long = "program", env = "PROGRAM_ID", default_value = DEFAULT_PROGRAM_ID, value_parser = parse_pubkey)]
+    pub program_id: Pubkey,
+
+    #[arg(long = "collector", env = "FEE_COLLECTOR", default_value = DEFAULT_FEE_COLLECTOR, value_parser = parse_pubkey)]
+    pub fee_collector: Pubkey,
+
+    /// Skip the RPC's preflight simulation when sending (faster, but errors surface later).
+    #[arg(long = "skip-preflight", default_value_t = false)]
+    pub skip_preflight: bool,
+
+    /// Commitment the RPC's preflight simulation reads at [default: --commitment].
+    #[arg(long = "preflight-commitment", value_enum)]
+    pub preflight_commitment: Option<Commitment>,
+
+    /// Times the RPC node may rebroadcast a send before dropping it [default: the node's own policy].
+    #[arg(long = "rpc-max-retries")]
+    pub rpc_max_retries: Option<usize>,
+
+    /// Simulate every transaction before sending it; a failing simulation logs the program output and sends nothing.
+    #[arg(long = "simulate-first", default_value_t = false)]
+    pub simulate_first: bool,
+
+    /// Fetch, decide, build and sign as usual, but log each transaction
+    /// instead of sending it. Nothing is spent.
+    #[arg(long = "dry-run", default_value_t = false)]
+    pub dry_run: bool,
 
-    #[arg(long = "collector", env = "FEE_COLLECTOR", default_value = "EGJnqcxVbhJFJ6Xnchtaw8jmPSvoLXfN2gWsY9Etz5SZ")]
-    pub fee_collector: String,
+    /// Times a transaction is resent after a transient failure (unreachable RPC, expired blockhash).
+    #[arg(long = "max-retries", default_value_t = 2)]
+    pub max_retries: u32,
+
+    /// Pause before the first retry, in ms; it doubles on each later one, with jitter.
+    #[arg(long = "retry-base-ms", default_value_t = 500)]
+    pub retry_base_ms: u64,
+
+    /// How long a sent transaction is watched for confirmation, in ms, before
+    /// it is reported as unconfirmed.
+    #[arg(long = "confirm-timeout-ms", default_value_t = 15_000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub confirm_timeout_ms: u64,
+
+    /// Pause between confirmation checks, in ms.
+    #[arg(long = "confirm-poll-ms", default_value_t = 500, value_parser = clap::value_parser!(u64).range(1..))]
+    pub confirm_poll_ms: u64,
+
+    /// Fixed compute-unit price, in micro-lamports, added to every transaction.
+    #[arg(long = "priority-fee-microlamports", conflicts_with = "auto_priority_fee")]
+    pub priority_fee_microlamports: Option<u64>,
+
+    /// Price each transaction at this percentile of recent prioritization fees
+    /// for its accounts (default 75), refreshed every 30 seconds.
+    #[arg(
+        long = "auto-priority-fee",
+        num_args = 0..=1,
+        default_missing_value = "75",
+        value_parser = clap::value_parser!(u32).range(0..=100)
+    )]
+    pub auto_priority_fee: Option<u32>,
+
+    /// Compute units to request per transaction; priority fees are paid on this many.
+    #[arg(long = "compute-unit-limit")]
+    pub compute_unit_limit: Option<u32>,
+
+    /// Ask the RPC for each transaction's fee before sending it, and send none costing more lamports than this.
+    #[arg(long = "max-fee-lamports")]
+    pub max_fee_lamports: Option<u64>,
+
+    /// Refuse to start, and pause autoplay before each move, while the payer holds fewer lamports than this.
+    /// Unset, only the start is checked, against one signature fee.
+    #[arg(long = "min-balance-lamports")]
+    pub min_balance_lamports: Option<u64>,
+
+    /// Play or watch this game account instead of the PDA derived from the keypair.
+    #[arg(long = "game-pda")]
+    pub game_pda: Option<String>,
 
     #[arg(long = "autoplay", default_value_t = false)]
     pub autoplay: bool,
@@ -39,6 +460,36 @@ pub struct Cli {
     #[arg(long = "max_moves", default_value_t = 200u32)]
     pub max_moves: u32,
 
+    /// Stop autoplay, after the move in flight, once this many seconds have passed.
+    #[arg(long = "time-budget-secs")]
+    pub time_budget_secs: Option<u64>,
+
+    /// When autoplay ends, write the last game's board, classification and result here as JSON.
+    #[arg(long = "final-board-file")]
+    pub final_board_file: Option<String>,
+
+    /// Also append every log line to this file, with timestamps, rotating it at 10 MiB.
+    #[arg(long = "log-file")]
+    pub log_file: Option<String>,
+
+    /// Write each move's latency in milliseconds, one per line, to this file at exit.
+    #[arg(long = "latency-samples")]
+    pub latency_samples: Option<String>,
+
+    /// Autoplay this many games back to back, resetting between them (0 = forever).
+    #[arg(long = "loop-games", default_value_t = 1u32)]
+    pub loop_games: u32,
+
+    /// Autoplay every wallet in a directory (its `*.json` files) or matching a
+    /// `*` pattern such as `keys/bot-*.json`, each on its own game PDA.
+    #[arg(long = "keypairs")]
+    pub keypairs: Option<String>,
+
+    /// How many `--keypairs` wallets play at the same time.
+    #[arg(long = "max-concurrent", default_value_t = 4u32, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_concurrent: u32,
+
+    /// Judge positions as if the final move wins the pot rather than the poison losing it.
     #[arg(long = "last_move_wins", default_value_t = false)]
     pub last_move_wins: bool,
 
@@ -48,6 +499,109 @@ pub struct Cli {
     #[arg(long = "init_if_missing", default_value_t = true)]
     pub init_if_missing: bool,
 
+    /// Skip the prompt before the first transaction on mainnet with the default
+    /// program and collector; required when stdin is not a terminal.
+    #[arg(long = "yes", default_value_t = false)]
+    pub yes: bool,
+
+    /// Allow opening a new game on mainnet with the default program and collector.
+    #[arg(long = "confirm-new-game", default_value_t = false)]
+    pub confirm_new_game: bool,
+
+    /// Write each board we read to this file, bincode-encoded, overwriting the last one.
+    #[arg(long = "save-board")]
+    pub save_board: Option<String>,
+
+    /// Load a board saved with `--save-board`, report its value and exit without touching the chain.
+    #[arg(long = "analyze")]
+    pub analyze: Option<String>,
+
+    /// Replay these moves (e.g. "5,1 3,2") from the empty board, report the position and exit.
+    #[arg(long = "moves", value_delimiter = ' ', value_parser = parse_move)]
+    pub moves: Vec<(u8, u8)>,
+
+    /// With an offline report, also list each opponent reply to our chosen move and
+    /// whether it leaves us winning or losing.
+    #[arg(long = "analyze-replies", default_value_t = false)]
+    pub analyze_replies: bool,
+
+    /// Take `--moves` from a game file in chomp notation, as `--export-notation` writes.
+    #[arg(long = "notation", conflicts_with = "moves")]
+    pub notation: Option<String>,
+
+    /// Write the game `simulate` or autoplay plays to this file in chomp notation.
+    #[arg(long = "export-notation")]
+    pub export_notation: Option<String>,
+
+    /// Analyze this board, typed as five rows top first, each eight binary digits
+    /// (`1` eaten) or two hex digits, e.g. `11111111,11110000,11000000,00,00`.
+    #[arg(long = "board", value_parser = parse_board, conflicts_with_all = ["analyze", "skyline", "moves", "notation"])]
+    pub board: Option<[u8; 5]>,
+
+    /// Analyze the position given as eaten-square counts per row, top first, e.g. `8,8,6,3,0`.
+    #[arg(long = "skyline")]
+    pub skyline: Option<Skyline>,
+
+    /// Solve a board with this many rows instead of the program's 5 and report
+    /// its opening; nothing is sent.
+    #[arg(long = "rows", default_value_t = 5u8)]
+    pub rows: u8,
+
+    /// Columns of the `--rows` board, at most 8.
+    #[arg(long = "cols", default_value_t = 8u8)]
+    pub cols: u8,
+
+    /// Write the perfect-play line from the offline position (default: empty board) as JSON.
+    #[arg(long = "export-pv")]
+    pub export_pv: Option<String>,
+
+    /// Play this many simulated games per strategy against a random opponent, from
+    /// `--moves` or the empty board, and report each strategy's win rate.
+    #[arg(long = "estimate-winrate")]
+    pub estimate_winrate: Option<u32>,
+
+    /// Before each autoplay move, pause for a random time between MIN_MS and MAX_MS,
+    /// on top of `--interval_ms`.
+    #[arg(long = "human-delay", num_args = 2, value_names = ["MIN_MS", "MAX_MS"])]
+    pub human_delay: Option<Vec<u64>>,
+
+    /// RNG seed for `--estimate-winrate` and `--human-delay`.
+    #[arg(long = "seed", default_value_t = 0)]
+    pub seed: u64,
+
+    /// Quiz mode: guess the best move each turn, see the solver's answer, then play on
+    /// against it. Starts from `--moves` if given, else the empty board.
+    #[arg(long = "teach", default_value_t = false)]
+    pub teach: bool,
+
+    /// Step through a line written by `--export-pv` and exit.
+    #[arg(long = "replay")]
+    pub replay: Option<String>,
+
+    /// Append each confirmed move, with the board it was played on and its signature, to this file.
+    #[arg(long = "history-file")]
+    pub history_file: Option<String>,
+
+    /// Send the moves of a `--history-file` again, in order, against a fresh game, then exit.
+    #[arg(long = "replay-history")]
+    pub replay_history: Option<String>,
+
+    /// Verify the solver against known Chomp facts before playing; abort on failure.
+    #[arg(long = "self-check", default_value_t = false)]
+    pub self_check: bool,
+
+    /// Load the solver table from this file, or solve it and save it there when
+    /// the file does not exist yet.
+    #[arg(long = "strategy-cache")]
+    pub strategy_cache: Option<String>,
+
+    /// Do not build the solver table; play any legal move instead.
+    #[arg(long = "no-solver", default_value_t = false)]
+    pub no_solver: bool,
+
+    #[arg(long = "strategy", value_enum, default_value_t = StrategyKind::Optimal)]
+    pub strategy: StrategyKind,
+
     #[arg(long = "r")]
     pub row: Option<u8>,
 
@@ -56,4 +610,706 @@ pub struct Cli {
 
     #[arg(long = "cash_out", default_value_t = false)]
     pub cash_out: bool,
+
+    /// Autoplay: before each move, cash out if the position is lost or the fees
+    /// still to pay exceed the pot.
+    #[arg(long = "ev-play", default_value_t = false)]
+    pub ev_play: bool,
+
+    /// Localnet self-play: send these moves (e.g. "5,1 3,2") in one transaction.
+    #[arg(long = "batch", value_delimiter = ' ', value_parser = parse_move)]
+    pub batch: Vec<(u8, u8)>,
+
+    /// Single-move only: exit 0 = move sent, 2 = game over, 3 = no legal move or
+    /// cashed out, 4 = RPC error or unconfirmed move.
+    #[arg(long = "exit-code", default_value_t = false)]
+    pub exit_code: bool,
+
+    /// Skip the board dump before each move; chosen/confirmed moves, game over and
+    /// the final board are still logged.
+    #[arg(long = "quiet", default_value_t = false)]
+    pub quiet: bool,
+
+    /// Show autoplay as a full-screen dashboard: board, chosen move, move count,
+    /// elapsed time, last signature and the log. `q` or Ctrl-C stops.
+    #[cfg(feature = "tui")]
+    #[arg(long = "tui", default_value_t = false, requires = "autoplay", conflicts_with_all = ["keypairs", "output"])]
+    pub tui: bool,
+
+    /// Colorize board and log output; `auto` checks for a tty and honors `NO_COLOR`.
+    #[arg(long = "color", value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Shorthand for `--color never`.
+    #[arg(long = "no-color", default_value_t = false, conflicts_with = "color")]
+    pub no_color: bool,
+
+    /// `json` writes each confirmed move and board snapshot to stdout as one
+    /// JSON object per line; logs stay on stderr.
+    #[arg(long = "output", value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// How boards are drawn: a numbered grid, or each row's bits as before.
+    #[arg(long = "board-format", value_enum, default_value_t = BoardFormat::Grid)]
+    pub board_format: BoardFormat,
+}
+
+impl Cli {
+    /// Parses the command line, then fills in whatever it left at the built-in
+    /// default from `--config`.
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(std::env::args_os())
+    }
+
+    /// The file's settings are written out as flags ahead of the command line
+    /// and everything is parsed again, so they meet the same value parsers,
+    /// ranges and conflicts as flags typed by hand.
+    fn load_from<I, T>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let matches = Cli::command().get_matches_from(&args);
+        let cli = Cli::from_matches(&matches)?;
+        let Some(path) = cli.config.clone() else {
+            return Ok(cli);
+        };
+        let file = FileConfig::read(Path::new(&path))?.into_flags(&matches)?;
+        let merged = args
+            .iter()
+            .take(1)
+            .cloned()
+            .chain(file.flags.into_iter().map(OsString::from))
+            .chain(args.iter().skip(1).cloned());
+        let matches = Cli::command()
+            .try_get_matches_from(merged)
+            .map_err(|e| format!("bad config {}: {}", path, clap_message(&e)))?;
+        let mut cli = Cli::from_matches(&matches)?;
+        if file.no_init_if_missing {
+            cli.init_if_missing = false;
+        }
+        Ok(cli)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let mut cli = Cli::from_arg_matches(matches).map_err(|e| e.to_string())?;
+        cli.rpc_given = matches.value_source("rpc_url") == Some(ValueSource::CommandLine);
+        Ok(cli)
+    }
+
+    pub fn time_budget(&self) -> Option<std::time::Duration> {
+        self.time_budget_secs.map(std::time::Duration::from_secs)
+    }
+
+    pub fn priority_fee(&self) -> PriorityFee {
+        match (self.priority_fee_microlamports, self.auto_priority_fee) {
+            (Some(price), _) => PriorityFee::Fixed(price),
+            (None, Some(percentile)) => PriorityFee::Auto { percentile },
+            (None, None) => PriorityFee::None,
+        }
+    }
+
+    /// `--rows` x `--cols`, checked to be solvable.
+    pub fn board_shape(&self) -> Result<BoardShape, String> {
+        BoardShape::new(self.rows, self.cols)
+    }
+
+    /// `--human-delay` as an ordered `(min, max)` range in milliseconds.
+    pub fn human_delay(&self) -> Option<(u64, u64)> {
+        match self.human_delay.as_deref()? {
+            &[a, b] => Some((a.min(b), a.max(b))),
+            _ => None,
+        }
+    }
+
+    /// The primary endpoint: `--rpc` when it was given, even as the mainnet
+    /// URL, else the local fork under `--fork` or the `--network` preset.
+    pub fn rpc_endpoint(&self) -> &str {
+        if self.rpc_given {
+            &self.rpc_url
+        } else if self.fork {
+            FORK_RPC_URL
+        } else {
+            self.network.map_or(DEFAULT_RPC_URL, Network::url)
+        }
+    }
+
+    /// `--commitment`, else `confirmed` on a fork, which confirms quickly but
+    /// finalizes slowly and has nothing at stake, else `finalized`.
+    pub fn commitment(&self) -> CommitmentConfig {
+        match self.commitment {
+            Some(commitment) => commitment.config(),
+            None if self.fork => CommitmentConfig::confirmed(),
+            None => CommitmentConfig::finalized(),
+        }
+    }
+
+    /// True when neither the program nor the fee collector was overridden.
+    pub fn uses_default_ids(&self) -> bool {
+        self.program_id.to_string() == DEFAULT_PROGRAM_ID
+            && self.fee_collector.to_string() == DEFAULT_FEE_COLLECTOR
+    }
+
+    pub fn rules(&self) -> Rules {
+        if self.last_move_wins {
+            Rules::LastMoveWins
+        } else {
+            Rules::PoisonLoses
+        }
+    }
+
+    pub fn color_choice(&self) -> ColorChoice {
+        if self.no_color {
+            ColorChoice::Never
+        } else {
+            self.color
+        }
+    }
+}
+
+/// The `--config` file: settings worth keeping between runs, keyed by their
+/// flag names. One-off actions such as `--moves` or `--cash_out` stay on the
+/// command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct FileConfig {
+    rpc: Option<String>,
+    fork: Option<bool>,
+    network: Option<String>,
+    commitment: Option<String>,
+    account_version: Option<String>,
+    rpc_fallback: Option<String>,
+    ws: Option<String>,
+    keypairs: Option<String>,
+    max_concurrent: Option<u32>,
+    keypair: Option<String>,
+    program: Option<String>,
+    collector: Option<String>,
+    skip_preflight: Option<bool>,
+    preflight_commitment: Option<String>,
+    rpc_max_retries: Option<usize>,
+    simulate_first: Option<bool>,
+    dry_run: Option<bool>,
+    max_retries: Option<u32>,
+    retry_base_ms: Option<u64>,
+    confirm_timeout_ms: Option<u64>,
+    confirm_poll_ms: Option<u64>,
+    priority_fee_microlamports: Option<u64>,
+    auto_priority_fee: Option<u32>,
+    compute_unit_limit: Option<u32>,
+    max_fee_lamports: Option<u64>,
+    min_balance_lamports: Option<u64>,
+    game_pda: Option<String>,
+    #[serde(rename = "interval_ms")]
+    interval_ms: Option<u64>,
+    #[serde(rename = "max_moves")]
+    max_moves: Option<u32>,
+    time_budget_secs: Option<u64>,
+    loop_games: Option<u32>,
+    #[serde(rename = "last_move_wins")]
+    last_move_wins: Option<bool>,
+    #[serde(rename = "init_if_missing")]
+    init_if_missing: Option<bool>,
+    strategy: Option<String>,
+    seed: Option<u64>,
+    ev_play: Option<bool>,
+    human_delay: Option<[u64; 2]>,
+    latency_samples: Option<String>,
+    final_board_file: Option<String>,
+    log_file: Option<String>,
+    history_file: Option<String>,
+    quiet: Option<bool>,
+    color: Option<String>,
+    output: Option<String>,
+    board_format: Option<String>,
+}
+
+impl FileConfig {
+    /// Reads `path` as JSON when it ends in `.json`, as TOML otherwise.
+    fn read(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read config {}: {}", path.display(), e))?;
+        let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|e| format!("bad config {}: {}", path.display(), e))
+    }
+
+    /// The file's settings as flags, each written the way it is typed, for
+    /// every argument the command line left alone. A setting that conflicts
+    /// with an explicit flag yields to it.
+    fn into_flags(self, matches: &ArgMatches) -> Result<FileFlags, String> {
+        let cmd = Cli::command();
+        let mut out = FileFlags::default();
+        // `field <- value`: the file value for the `Cli` field, whose clap id
+        // is the field name.
+        macro_rules! flags {
+            ($($field:ident <- $value:expr),* $(,)?) => {$(
+                if let Some(value) = $value {
+                    let id = stringify!($field);
+                    let arg = cmd
+                        .get_arguments()
+                        .find(|arg| arg.get_id() == id)
+                        .expect("every config key is a flag");
+                    let yields = explicit(matches, id)
+                        || cmd.get_arguments().any(|other| {
+                            explicit(matches, other.get_id().as_str())
+                                && conflict(&cmd, arg, other)
+                        });
+                    if !yields {
+                        let long = arg.get_long().expect("config flags are long flags");
+                        out.push(long, arg, FlagValue::values(&value));
+                    }
+                }
+            )*};
+        }
+        flags! {
+            rpc_url <- self.rpc,
+            fork <- self.fork,
+            network <- self.network,
+            commitment <- self.commitment,
+            account_version <- self.account_version,
+            rpc_fallback <- self.rpc_fallback,
+            ws <- self.ws,
+            keypairs <- self.keypairs,
+            max_concurrent <- self.max_concurrent,
+            keypair_path <- self.keypair,
+            program_id <- self.program,
+            fee_collector <- self.collector,
+            skip_preflight <- self.skip_preflight,
+            preflight_commitment <- self.preflight_commitment,
+            rpc_max_retries <- self.rpc_max_retries,
+            simulate_first <- self.simulate_first,
+            dry_run <- self.dry_run,
+            max_retries <- self.max_retries,
+            retry_base_ms <- self.retry_base_ms,
+            confirm_timeout_ms <- self.confirm_timeout_ms,
+            confirm_poll_ms <- self.confirm_poll_ms,
+            priority_fee_microlamports <- self.priority_fee_microlamports,
+            auto_priority_fee <- self.auto_priority_fee,
+            compute_unit_limit <- self.compute_unit_limit,
+            max_fee_lamports <- self.max_fee_lamports,
+            min_balance_lamports <- self.min_balance_lamports,
+            game_pda <- self.game_pda,
+            interval_ms <- self.interval_ms,
+            max_moves <- self.max_moves,
+            time_budget_secs <- self.time_budget_secs,
+            loop_games <- self.loop_games,
+            last_move_wins <- self.last_move_wins,
+            init_if_missing <- self.init_if_missing,
+            strategy <- self.strategy,
+            seed <- self.seed,
+            ev_play <- self.ev_play,
+            human_delay <- self.human_delay,
+            latency_samples <- self.latency_samples,
+            final_board_file <- self.final_board_file,
+            log_file <- self.log_file,
+            history_file <- self.history_file,
+            quiet <- self.quiet,
+            color <- self.color,
+            output <- self.output,
+            board_format <- self.board_format,
+        }
+        Ok(out)
+    }
+}
+
+/// Whether `a` and `b` may not be given together; clap records a conflict
+/// on whichever side declared it.
+fn conflict(cmd: &clap::Command, a: &clap::Arg, b: &clap::Arg) -> bool {
+    let declares = |x: &clap::Arg, y: &clap::Arg| {
+        cmd.get_arg_conflicts_with(x)
+            .iter()
+            .any(|other| other.get_id() == y.get_id())
+    };
+    declares(a, b) || declares(b, a)
+}
+
+/// `FileConfig` as command-line flags.
+#[derive(Debug, Default)]
+struct FileFlags {
+    flags: Vec<String>,
+    /// `init_if_missing = false`: an on-by-default switch has no flag to turn
+    /// it off, so it is applied after parsing.
+    no_init_if_missing: bool,
+}
+
+impl FileFlags {
+    fn push(&mut self, long: &str, arg: &clap::Arg, values: Vec<String>) {
+        if matches!(arg.get_action(), ArgAction::SetTrue) {
+            match values.first().map(String::as_str) {
+                Some("true") => self.flags.push(format!("--{}", long)),
+                _ if long == "init_if_missing" => self.no_init_if_missing = true,
+                // Off is already the default.
+                _ => {}
+            }
+        } else if let [value] = &values[..] {
+            self.flags.push(format!("--{}={}", long, value));
+        } else {
+            self.flags.push(format!("--{}", long));
+            self.flags.extend(values);
+        }
+    }
+}
+
+/// A config value as the strings clap would have been given for it.
+trait FlagValue {
+    fn values(&self) -> Vec<String>;
+}
+
+impl FlagValue for String {
+    fn values(&self) -> Vec<String> {
+        vec![self.clone()]
+    }
+}
+
+impl FlagValue for bool {
+    fn values(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+macro_rules! number_flag_values {
+    ($($ty:ty),*) => {$(
+        impl FlagValue for $ty {
+            fn values(&self) -> Vec<String> {
+                vec![self.to_string()]
+            }
+        }
+    )*};
+}
+number_flag_values!(u32, u64, usize);
+
+impl FlagValue for [u64; 2] {
+    fn values(&self) -> Vec<String> {
+        self.iter().map(u64::to_string).collect()
+    }
+}
+
+/// The first line of a clap error, without its `error: ` prefix or usage.
+fn clap_message(err: &clap::Error) -> String {
+    let text = err.to_string();
+    let line = text.lines().next().unwrap_or_default();
+    line.strip_prefix("error: ").unwrap_or(line).to_string()
+}
+
+/// True when the argument `id` came from the command line or its env var.
+fn explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_keypair_names() {
+        assert!(wildcard_match("*.json", "bot.json"));
+        assert!(wildcard_match("bot-*.json", "bot-1.json"));
+        assert!(wildcard_match("bot-*-*.json", "bot-a-b.json"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("*.json", "bot.json.bak"));
+        assert!(!wildcard_match("bot-*.json", "other-1.json"));
+        assert!(!wildcard_match("a*a", "a"));
+        assert!(!wildcard_match("id.json", "id.jsonx"));
+    }
+
+    #[test]
+    fn keypairs_come_from_a_directory_or_a_pattern() {
+        let dir = std::env::temp_dir().join(format!("chomp-keypairs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.json", "a.json", "bot-1.json", "notes.txt"] {
+            std::fs::write(dir.join(name), "[]").unwrap();
+        }
+        let names = |spec: &Path| -> Vec<String> {
+            keypair_files(&spec.display().to_string())
+                .unwrap()
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(names(&dir), ["a.json", "b.json", "bot-1.json"]);
+        assert_eq!(names(&dir.join("bot-*.json")), ["bot-1.json"]);
+        assert_eq!(names(&dir.join("notes.txt")), ["notes.txt"]);
+        assert!(names(&dir.join("z*")).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn home_paths_follow_the_home_directory() {
+        let home = Path::new("/home/chomper");
+        assert_eq!(
+            keypair_path_in(Some(home)),
+            home.join(".config/solana/id.json").display().to_string()
+        );
+        assert_eq!(keypair_path_in(None), "./id.json");
+        assert_eq!(
+            expand_home_in("~/keys/bot.json", Some(home)),
+            home.join("keys/bot.json").display().to_string()
+        );
+        assert_eq!(expand_home_in("~", Some(home)), "/home/chomper");
+        assert_eq!(expand_home_in("~/bot.json", None), "~/bot.json");
+        assert_eq!(expand_home_in("./~/bot.json", Some(home)), "./~/bot.json");
+        assert_eq!(
+            expand_home_in("~other/bot.json", Some(home)),
+            "~other/bot.json"
+        );
+    }
+
+    #[test]
+    fn parses_typed_boards() {
+        let board = [0xFF, 0xF0, 0xC0, 0, 0];
+        assert_eq!(parse_board("11111111,11110000,11000000,00000000,00000000"), Ok(board));
+        assert_eq!(parse_board("ff, f0, 0xc0, 00, 00"), Ok(board));
+        assert_eq!(parse_board("fff0c00000"), Ok(board));
+        assert_eq!(parse_board("FFF0C00000"), Ok(board));
+        assert_eq!(
+            parse_board("ff,f0,c0,00"),
+            Err("expected 5 rows, got 4 in `ff,f0,c0,00`".into())
+        );
+        assert_eq!(
+            parse_board("ff,f0,c0,00,12345678"),
+            Err("row 5 `12345678` is not 8 binary or 2 hex digits".into())
+        );
+        assert_eq!(
+            parse_board("f0,ff,00,00,00"),
+            Err("row 2 has more eaten than the row above it".into())
+        );
+        assert_eq!(
+            parse_board("10100000,0,0,0,0").unwrap_err(),
+            "row 2 `0` is not 8 binary or 2 hex digits"
+        );
+        assert!(parse_board("a0,00,00,00,00").unwrap_err().contains("gap"));
+        assert!(parse_board("ff").is_err());
+    }
+
+    #[test]
+    fn parses_moves() {
+        assert_eq!(parse_move("5,1"), Ok((5, 1)));
+        assert_eq!(parse_move(" 3 , 2 "), Ok((3, 2)));
+        assert!(parse_move("5").is_err());
+        assert!(parse_move("x,1").is_err());
+    }
+
+    #[test]
+    fn auto_priority_fee_defaults_to_p75() {
+        let cli = Cli::parse_from(["chomp-bot", "--auto-priority-fee"]);
+        assert_eq!(cli.priority_fee(), PriorityFee::Auto { percentile: 75 });
+        let cli = Cli::parse_from(["chomp-bot", "--auto-priority-fee", "90"]);
+        assert_eq!(cli.priority_fee(), PriorityFee::Auto { percentile: 90 });
+        assert!(Cli::try_parse_from(["chomp-bot", "--auto-priority-fee", "101"]).is_err());
+        assert!(Cli::try_parse_from([
+            "chomp-bot",
+            "--auto-priority-fee",
+            "--priority-fee-microlamports",
+            "5"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn fork_points_at_localhost_with_default_ids() {
+        let cli = Cli::parse_from(["chomp-bot", "--fork"]);
+        assert_eq!(cli.rpc_endpoint(), FORK_RPC_URL);
+        assert!(cli.uses_default_ids());
+        let cli = Cli::load_from(["chomp-bot", "--fork", "--rpc", "http://10.0.0.2:8899"]).unwrap();
+        assert_eq!(cli.rpc_endpoint(), "http://10.0.0.2:8899");
+        assert_eq!(Cli::parse_from(["chomp-bot"]).rpc_endpoint(), DEFAULT_RPC_URL);
+    }
+
+    #[test]
+    fn airdrop_amounts_are_positive_sol() {
+        let cli = Cli::parse_from(["chomp-bot", "airdrop", "--amount-sol", "0.5"]);
+        let Some(Command::Airdrop { amount_sol }) = cli.command else {
+            panic!("not an airdrop");
+        };
+        assert_eq!(amount_sol, 0.5);
+        for bad in ["0", "-1", "NaN", "lots"] {
+            assert!(parse_sol(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn network_presets_fill_in_the_rpc() {
+        use crate::solana::Cluster;
+
+        let on = |network: &str| Cli::parse_from(["chomp-bot", "--network", network]);
+        assert_eq!(on("devnet").rpc_endpoint(), "https://api.devnet.solana.com");
+        assert_eq!(on("mainnet").rpc_endpoint(), DEFAULT_RPC_URL);
+        for (network, cluster) in [
+            (Network::Devnet, Cluster::Devnet),
+            (Network::Testnet, Cluster::Testnet),
+            (Network::Mainnet, Cluster::Mainnet),
+        ] {
+            assert_eq!(Cluster::infer(network.url()), cluster);
+        }
+        let cli = Cli::load_from(["chomp-bot", "--network", "devnet", "--rpc", "http://10.0.0.2:8899"]).unwrap();
+        assert_eq!(cli.rpc_endpoint(), "http://10.0.0.2:8899");
+        // Naming the mainnet URL is still an explicit choice over the preset.
+        let cli = Cli::load_from(["chomp-bot", "--network", "devnet", "--rpc", DEFAULT_RPC_URL]).unwrap();
+        assert_eq!(cli.rpc_endpoint(), DEFAULT_RPC_URL);
+        assert!(Cli::try_parse_from(["chomp-bot", "--network", "testnet", "--fork"]).is_err());
+    }
+
+    #[test]
+    fn config_file_fills_in_under_explicit_flags() {
+        let path = std::env::temp_dir().join(format!("chomp-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+rpc = "http://10.0.0.9:8899"
+collector = "11111111111111111111111111111111"
+interval_ms = 250
+max_moves = 12
+strategy = "greedy"
+commitment = "confirmed"
+auto-priority-fee = 50
+human-delay = [100, 400]
+"#,
+        )
+        .unwrap();
+        let config = path.to_str().unwrap();
+        let cli = Cli::load_from([
+            "chomp-bot",
+            "--config",
+            config,
+            "--max_moves",
+            "3",
+            "--priority-fee-microlamports",
+            "7",
+        ])
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(cli.rpc_url, "http://10.0.0.9:8899");
+        assert_eq!(cli.fee_collector, Pubkey::default());
+        assert_eq!(cli.interval_ms, 250);
+        assert_eq!(cli.strategy, StrategyKind::Greedy);
+        assert_eq!(cli.commitment(), CommitmentConfig::confirmed());
+        assert_eq!(cli.human_delay(), Some((100, 400)));
+        // The command line wins, and its fee flag replaces the file's other one.
+        assert_eq!(cli.max_moves, 3);
+        assert_eq!(cli.priority_fee(), PriorityFee::Fixed(7));
+        // Untouched by either, so still the built-in default.
+        assert_eq!(cli.retry_base_ms, 500);
+        assert_eq!(cli.program_id.to_string(), DEFAULT_PROGRAM_ID);
+    }
+
+    #[test]
+    fn config_file_rejects_unknown_keys_and_values() {
+        let dir = std::env::temp_dir();
+        let typo = dir.join(format!("chomp-typo-{}.json", std::process::id()));
+        std::fs::write(&typo, r#"{"intervalms": 5}"#).unwrap();
+        let err = FileConfig::read(&typo).unwrap_err();
+        std::fs::remove_file(&typo).unwrap();
+        assert!(err.contains("intervalms"), "{}", err);
+
+        // File values meet the flags' own parsers, ranges and conflicts.
+        let load = |text: &str, flags: &[&str]| {
+            let path = dir.join(format!("chomp-bad-{}.toml", std::process::id()));
+            std::fs::write(&path, text).unwrap();
+            let config = path.to_str().unwrap().to_string();
+            let args = ["chomp-bot", "--config", &config]
+                .into_iter()
+                .chain(flags.iter().copied());
+            let cli = Cli::load_from(args);
+            std::fs::remove_file(&path).unwrap();
+            cli
+        };
+        for bad in [
+            r#"strategy = "best""#,
+            "max-concurrent = 0",
+            "confirm-timeout-ms = 0",
+            "auto-priority-fee = 101",
+            "fork = true\nnetwork = \"devnet\"",
+            "priority-fee-microlamports = 5\nauto-priority-fee = 50",
+        ] {
+            let err = load(bad, &[]).unwrap_err();
+            assert!(err.starts_with("bad config"), "{}: {}", bad, err);
+        }
+        // A file setting that conflicts with an explicit flag gives way to it.
+        let cli = load(r#"network = "devnet""#, &["--fork"]).unwrap();
+        assert_eq!((cli.fork, cli.network), (true, None));
+        let cli = load("init_if_missing = false\nfork = true", &[]).unwrap();
+        assert!(!cli.init_if_missing && cli.fork);
+    }
+
+    #[test]
+    fn commitment_defaults_by_cluster() {
+        let commitment = |args: &[&str]| {
+            Cli::parse_from(["chomp-bot"].iter().chain(args)).commitment()
+        };
+        assert_eq!(commitment(&[]), CommitmentConfig::finalized());
+        assert_eq!(commitment(&["--fork"]), CommitmentConfig::confirmed());
+        assert_eq!(
+            commitment(&["--fork", "--commitment", "finalized"]),
+            CommitmentConfig::finalized()
+        );
+        assert_eq!(
+            commitment(&["--commitment", "processed"]),
+            CommitmentConfig::processed()
+        );
+        assert!(Cli::try_parse_from(["chomp-bot", "--commitment", "recent"]).is_err());
+    }
+
+    #[test]
+    fn human_delay_takes_a_range() {
+        assert_eq!(Cli::parse_from(["chomp-bot"]).human_delay(), None);
+        let cli = Cli::parse_from(["chomp-bot", "--human-delay", "1500", "400"]);
+        assert_eq!(cli.human_delay(), Some((400, 1500)));
+        assert!(Cli::try_parse_from(["chomp-bot", "--human-delay", "400"]).is_err());
+    }
+
+    #[test]
+    fn overriding_an_id_leaves_the_defaults() {
+        assert!(Cli::parse_from(["chomp-bot"]).uses_default_ids());
+        let custom = Cli::parse_from(["chomp-bot", "--program", "11111111111111111111111111111111"]);
+        assert!(!custom.uses_default_ids());
+        let collector = Cli::parse_from(["chomp-bot", "--collector", "11111111111111111111111111111111"]);
+        assert!(!collector.uses_default_ids());
+    }
+
+    #[test]
+    fn pubkeys_are_checked_when_parsing() {
+        let err = Cli::try_parse_from(["chomp-bot", "--program", "not-a-key"]).unwrap_err();
+        assert!(err.to_string().contains("`not-a-key` is not a base58 pubkey"), "{}", err);
+        assert!(Cli::try_parse_from(["chomp-bot", "--collector", "0OIl"]).is_err());
+
+        // Env values go through the same parser; a private variable stands in
+        // for `PROGRAM_ID` so other tests never see it.
+        let command = || {
+            Cli::command().mut_arg("program_id", |arg| arg.env("CHOMP_TEST_PROGRAM_ID"))
+        };
+        std::env::set_var("CHOMP_TEST_PROGRAM_ID", "11111111111111111111111111111111");
+        let ok = command().try_get_matches_from(["chomp-bot"]).unwrap();
+        assert_eq!(ok.get_one::<Pubkey>("program_id"), Some(&Pubkey::default()));
+        std::env::set_var("CHOMP_TEST_PROGRAM_ID", "bogus");
+        assert!(command().try_get_matches_from(["chomp-bot"]).is_err());
+        std::env::remove_var("CHOMP_TEST_PROGRAM_ID");
+    }
+
+    #[test]
+    fn base58_secrets_load_and_stay_out_of_debug() {
+        use solana_sdk::signer::Signer;
+
+        let payer = Keypair::new();
+        let secret = payer.to_base58_string();
+        let cli = Cli::parse_from(["chomp-bot", "--keypair-base58", &secret]);
+        assert_eq!(load_keypair(&cli).unwrap().pubkey(), payer.pubkey());
+        assert!(!format!("{:?}", cli).contains(&secret));
+
+        assert!(
+            Cli::try_parse_from(["chomp-bot", "--keypair", "id.json", "--keypair-base58", &secret]).is_err()
+        );
+        let short = bs58::encode(&payer.to_bytes()[..32]).into_string();
+        let err = keypair_from_base58(&short).unwrap_err();
+        assert!(err.contains("32 bytes") && !err.contains(&short), "{}", err);
+        assert!(keypair_from_base58("0OIl").unwrap_err().contains("not valid base58"));
+    }
 }
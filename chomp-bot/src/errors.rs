@@ -0,0 +1,82 @@
+//! What a rejection from the chomp program means. The program only fails with
+//! built-in `ProgramError`s and reuses each one for several checks, but it logs
+//! a `msg!` line naming the failed check first, so the logs tell them apart
+//! whenever the RPC hands them back.
+
+use solana_sdk::program_error::ProgramError;
+
+/// The line each check logs before failing, and what it means for the bot.
+const CHECKS: [(&str, &str); 5] = [
+    (
+        "Invalid system program",
+        "the system program account is not the real one",
+    ),
+    (
+        "Player must be a signer and writable",
+        "the player account must sign and be writable",
+    ),
+    (
+        "Invalid game account",
+        "the game account is not the signer's PDA",
+    ),
+    (
+        "Invalid fee collector account",
+        "the fee collector is not the one the program pays (see --collector)",
+    ),
+    (
+        "Invalid move",
+        "the square is already eaten or off the board",
+    ),
+];
+
+/// Why the program rejected a transaction with `code`, the `u64` form of its
+/// `ProgramError`: the check named in `logs` when there is one, else the usual
+/// cause of that code. `None` for codes this program does not return.
+pub fn describe(code: u64, logs: &[String]) -> Option<&'static str> {
+    let logged = CHECKS
+        .iter()
+        .find(|(check, _)| logs.iter().any(|line| line.contains(check)));
+    if let Some((_, reason)) = logged {
+        return Some(reason);
+    }
+    let reason = match ProgramError::from(code) {
+        ProgramError::InvalidArgument => {
+            "one of the system program, player, game or fee collector accounts is wrong"
+        }
+        ProgramError::InvalidInstructionData => "the move is malformed or its square is not open",
+        ProgramError::InsufficientFunds => "the payer cannot fund the game account",
+        _ => return None,
+    };
+    Some(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logged_checks_beat_the_bare_code() {
+        let code = u64::from(ProgramError::InvalidArgument);
+        let logs = [
+            "Program ChompZg47TcVy5fk2LxPEpW6SytFYBES5SHoqgrm8A4D invoke [1]".to_string(),
+            "Program log: Invalid fee collector account. \nsrc/lib.rs:317:5".to_string(),
+        ];
+        assert_eq!(
+            describe(code, &logs),
+            Some("the fee collector is not the one the program pays (see --collector)")
+        );
+        assert_eq!(
+            describe(code, &[]),
+            Some("one of the system program, player, game or fee collector accounts is wrong")
+        );
+        let moved = ["Program log: Invalid move".to_string()];
+        assert_eq!(
+            describe(u64::from(ProgramError::InvalidInstructionData), &moved),
+            Some("the square is already eaten or off the board")
+        );
+        assert_eq!(
+            describe(u64::from(ProgramError::AccountBorrowFailed), &[]),
+            None
+        );
+    }
+}
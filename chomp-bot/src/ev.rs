@@ -0,0 +1,129 @@
+//! `--ev-play`: before each autoplay move, weigh the pot against the fees
+//! still to pay. A win pays the pot back to us, while a loss or a cash-out
+//! hands it to the fee collector, so cashing out can only save the fees of
+//! the moves we would otherwise send.
+
+use std::fmt;
+
+use crate::game::{self, Outcome};
+
+/// What the EV model sees of a turn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// The forced result of the game with perfect play from here.
+    pub outcome: Outcome,
+    /// Lamports held by the game account.
+    pub pot: u64,
+    /// Estimated lamports per move transaction.
+    pub fee_per_move: u64,
+    /// Transactions we still send under perfect play, the last one included.
+    pub moves_left: u32,
+}
+
+impl Position {
+    pub fn read(board: [u8; 5], pot: u64, fee_per_move: u64) -> Self {
+        Position {
+            outcome: game::classify(board),
+            pot,
+            fee_per_move,
+            // The program's reply lands in the same transaction as our move.
+            moves_left: u32::from(game::depth(board)).div_ceil(2),
+        }
+    }
+
+    /// Expected lamports from playing on: the pot when the game is a forced
+    /// win, less the fees of every move left.
+    pub fn play_value(&self) -> i128 {
+        let pot = match self.outcome {
+            Outcome::Winning => i128::from(self.pot),
+            Outcome::Losing => 0,
+        };
+        pot - i128::from(self.moves_left) * i128::from(self.fee_per_move)
+    }
+
+    /// Expected lamports from cashing out now: the pot is forfeited and the
+    /// cash-out transaction is paid for.
+    pub fn cash_out_value(&self) -> i128 {
+        -i128::from(self.fee_per_move)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Call {
+    Play,
+    CashOut(CashOutReason),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CashOutReason {
+    /// Perfect play from the other side wins the pot.
+    Losing,
+    /// The fees left cost more than the pot pays back.
+    FeesExceedPot,
+}
+
+impl fmt::Display for CashOutReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CashOutReason::Losing => write!(f, "the position is lost"),
+            CashOutReason::FeesExceedPot => write!(f, "the fees left exceed the pot"),
+        }
+    }
+}
+
+pub fn decide(position: &Position) -> Call {
+    if position.outcome == Outcome::Losing {
+        Call::CashOut(CashOutReason::Losing)
+    } else if position.play_value() <= position.cash_out_value() {
+        Call::CashOut(CashOutReason::FeesExceedPot)
+    } else {
+        Call::Play
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn winning(pot: u64, fee_per_move: u64, moves_left: u32) -> Position {
+        Position {
+            outcome: Outcome::Winning,
+            pot,
+            fee_per_move,
+            moves_left,
+        }
+    }
+
+    #[test]
+    fn plays_a_win_while_the_pot_covers_the_fees() {
+        let cheap = winning(1_000_000, 5_000, 6);
+        assert_eq!(cheap.play_value(), 970_000);
+        assert_eq!(decide(&cheap), Call::Play);
+
+        // Six moves at 250k cost more than the pot plus the cash-out fee saved.
+        let pricey = winning(1_000_000, 250_000, 6);
+        assert_eq!(decide(&pricey), Call::CashOut(CashOutReason::FeesExceedPot));
+        // The final move of a win always beats paying to cash out.
+        assert_eq!(decide(&winning(1, 250_000, 1)), Call::Play);
+    }
+
+    #[test]
+    fn cashes_out_of_lost_positions() {
+        let lost = Position {
+            outcome: Outcome::Losing,
+            ..winning(1_000_000, 5_000, 1)
+        };
+        assert_eq!(decide(&lost), Call::CashOut(CashOutReason::Losing));
+    }
+
+    #[test]
+    fn reads_moves_left_from_the_board() {
+        let empty = Position::read([0u8; 5], 1_000_000, 5_000);
+        assert_eq!(empty.outcome, Outcome::Winning);
+        assert_eq!(empty.moves_left, u32::from(game::depth([0u8; 5])) / 2);
+
+        let lost = Position::read([0xFF, 0xFF, 0xFF, 0xFE, 0xFC], 1_000_000, 5_000);
+        assert_eq!(lost.outcome, Outcome::Losing);
+        assert_eq!(decide(&lost), Call::CashOut(CashOutReason::Losing));
+    }
+}
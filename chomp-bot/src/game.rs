@@ -1,24 +1,183 @@
 use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub use crate::table::{Rules, Skyline};
+#[cfg(test)]
+use crate::table::{max_encoded_index, TABLE_SIZE};
+use crate::table::{BoardShape, PositionTable, COLS_U8, COL_COUNT, ROW_COUNT};
 
-const ROW_COUNT: usize = 5;
-const COL_COUNT: usize = 8;
 const ROWS_U8: u8 = ROW_COUNT as u8;
-const COLS_U8: u8 = COL_COUNT as u8;
 const POISON_ROW: u8 = ROWS_U8;
 const POISON_COL: u8 = COLS_U8;
-const TABLE_SIZE: usize = 1 << 16;
 const BIT_TEST: [u8; COL_COUNT] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+const PREFIX_MASK: [u8; COL_COUNT] = [0x80, 0xC0, 0xE0, 0xF0, 0xF8, 0xFC, 0xFE, 0xFF];
+
+#[cfg(feature = "embed-table")]
+static EMBEDDED_TABLE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/table.bin"));
+
+static STRATEGY: Lazy<PositionTable> = Lazy::new(load_table);
+/// A table read or built by `load_strategy_cache`, taken by the first use of
+/// `STRATEGY`.
+static PRELOADED: Mutex<Option<PositionTable>> = Mutex::new(None);
+static SOLVER_DISABLED: AtomicBool = AtomicBool::new(false);
+static LAST_MOVE_WINS: AtomicBool = AtomicBool::new(false);
+static SOLVER_BUILT: Lazy<bool> = Lazy::new(|| {
+    std::panic::catch_unwind(|| {
+        Lazy::force(&STRATEGY);
+    })
+    .is_ok()
+});
+
+/// The solver table: the one `load_strategy_cache` left, else the blob
+/// `build.rs` embeds under the `embed-table` feature, else solved on first use.
+/// The embedded table is always solved under `Rules::PoisonLoses`.
+fn load_table() -> PositionTable {
+    if let Some(table) = PRELOADED.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        return table;
+    }
+    #[cfg(feature = "embed-table")]
+    if rules() == Rules::PoisonLoses {
+        return PositionTable::from_bytes(BoardShape::STANDARD, Rules::PoisonLoses, EMBEDDED_TABLE)
+            .expect("embedded solver table is valid");
+    }
+    PositionTable::solve(BoardShape::STANDARD, rules())
+}
+
+/// Solves for `rules` from now on (`--last_move_wins`). Must run before the
+/// solver is first used.
+pub fn set_rules(rules: Rules) {
+    LAST_MOVE_WINS.store(rules == Rules::LastMoveWins, Ordering::Relaxed);
+}
+
+pub fn rules() -> Rules {
+    if LAST_MOVE_WINS.load(Ordering::Relaxed) {
+        Rules::LastMoveWins
+    } else {
+        Rules::PoisonLoses
+    }
+}
+
+/// Where `load_strategy_cache` got the table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CacheUse {
+    Loaded,
+    /// The file was missing, so the table was solved and written there.
+    Saved,
+}
+
+/// `--strategy-cache`: reads the table from `path`, or solves it and saves it
+/// there when the file does not exist yet. A file for another board shape or
+/// other rules, or one that fails `verify`, is an error rather than a source
+/// of wrong moves.
+/// Must run before the solver is first used.
+pub fn load_strategy_cache(path: &Path) -> io::Result<CacheUse> {
+    let (table, used) = match File::open(path) {
+        Ok(file) => {
+            let table = PositionTable::load_from(BufReader::new(file))?;
+            if table.shape() != BoardShape::STANDARD {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("cached table is for a {} board, not {}", table.shape(), BoardShape::STANDARD),
+                ));
+            }
+            if table.rules() != rules() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("cached table is for {:?}, not {:?}", table.rules(), rules()),
+                ));
+            }
+            if !table.verify() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "cached table fails verification"));
+            }
+            (table, CacheUse::Loaded)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let table = PositionTable::solve(BoardShape::STANDARD, rules());
+            table.save_to(BufWriter::new(File::create(path)?))?;
+            (table, CacheUse::Saved)
+        }
+        Err(e) => return Err(e),
+    };
+    *PRELOADED.lock().unwrap_or_else(|e| e.into_inner()) = Some(table);
+    Ok(used)
+}
+
+/// Turns the solver off for the rest of the run (`--no-solver`).
+pub fn disable_solver() {
+    SOLVER_DISABLED.store(true, Ordering::Relaxed);
+}
 
-static STRATEGY: Lazy<PositionTable> = Lazy::new(PositionTable::new);
+/// Whether the solver table may be used: it was not disabled and it built
+/// without panicking. The first call builds it; after a failure only
+/// `pick_any_legal` is safe to call.
+pub fn solver_ready() -> bool {
+    !SOLVER_DISABLED.load(Ordering::Relaxed) && *SOLVER_BUILT
+}
 
 pub fn is_glass_only(board: [u8; ROW_COUNT]) -> bool {
     board.iter().take(ROW_COUNT - 1).all(|row| *row == 0xFF) && board[ROW_COUNT - 1] == 0xFE
 }
 
-fn move_is_open(board: [u8; ROW_COUNT], r: u8, c: u8) -> bool {
+/// Checks that `board` is a position the program can produce: every row is
+/// eaten from column 1 rightwards, and no row has more eaten than the row
+/// above it, since a move eats every row up to its own.
+pub fn validate_board(board: [u8; ROW_COUNT]) -> Result<(), String> {
+    let mut above = COLS_U8;
+    for (i, &row) in board.iter().enumerate() {
+        let eaten = row.leading_ones() as u8;
+        if row.count_ones() as u8 != eaten {
+            return Err(format!("row {} ({:#04x}) has a gap in its eaten squares", i + 1, row));
+        }
+        if eaten > above {
+            return Err(format!("row {} has more eaten than the row above it", i + 1));
+        }
+        above = eaten;
+    }
+    Ok(())
+}
+
+/// Whether square `(r, c)` is still uneaten. Anything off the 1-based board,
+/// the `(0, 0)` cash-out sentinel included, is reported as not open.
+pub fn move_is_open(board: [u8; ROW_COUNT], r: u8, c: u8) -> bool {
+    if !(1..=ROWS_U8).contains(&r) || !(1..=COLS_U8).contains(&c) {
+        return false;
+    }
     board[(r - 1) as usize] & BIT_TEST[(c - 1) as usize] == 0
 }
 
+/// Whether the program would accept `(r, c)` on `board`: any open square,
+/// the poison included (taking it is legal, it just loses), or the `(0, 0)`
+/// cash-out once a square has been eaten, as the program only pays out a game
+/// that has moves on it. Off-board coordinates never are.
+pub fn is_legal_move(board: [u8; ROW_COUNT], r: u8, c: u8) -> bool {
+    if (r, c) == (0, 0) {
+        return board.iter().any(|row| *row != 0);
+    }
+    move_is_open(board, r, c)
+}
+
+/// Board after we play `(r, c)`: the square and everything above and to the
+/// left of it is eaten, exactly like the program's own move handling. A move
+/// off the board, such as the `(0, 0)` cash-out, leaves the board unchanged.
+pub fn apply_move(board: [u8; ROW_COUNT], r: u8, c: u8) -> [u8; ROW_COUNT] {
+    if !(1..=COLS_U8).contains(&c) {
+        return board;
+    }
+    let mut next = board;
+    for row in next.iter_mut().take(r as usize) {
+        *row |= PREFIX_MASK[(c - 1) as usize];
+    }
+    next
+}
+
 pub fn pick_any_legal(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
     for r in (1..=ROWS_U8).rev() {
         for c in 1..=COLS_U8 {
@@ -33,124 +192,484 @@ pub fn pick_any_legal(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
     move_is_open(board, POISON_ROW, POISON_COL).then_some((POISON_ROW, POISON_COL))
 }
 
-pub fn pick_forced_victory(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+/// What to do on a live board. The wire uses `(0, 0)` for the cash-out, so a
+/// missing move must never be sent as that square.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Move(u8, u8),
+    /// Send the `(0, 0)` cash-out, closing the game.
+    CashOut,
+    /// Nothing legal is left to play.
+    NoMove,
+}
+
+impl Decision {
+    /// The square played, if this is a move.
+    pub fn square(self) -> Option<(u8, u8)> {
+        match self {
+            Decision::Move(r, c) => Some((r, c)),
+            Decision::CashOut | Decision::NoMove => None,
+        }
+    }
+}
+
+/// A cash-out when one was asked for, else the move `choose` offers. Nothing
+/// offered, or a square that is not open, the `(0, 0)` sentinel included, is
+/// `NoMove`: only an explicit request cashes out.
+pub fn decide(board: [u8; ROW_COUNT], cash_out: bool, choose: impl FnOnce() -> Option<(u8, u8)>) -> Decision {
+    if cash_out {
+        return Decision::CashOut;
+    }
+    match choose() {
+        Some((r, c)) if move_is_open(board, r, c) => Decision::Move(r, c),
+        _ => Decision::NoMove,
+    }
+}
+
+/// A uniformly random open square. The poison is only taken when nothing
+/// else is left, so this models a weak player rather than a suicidal one.
+pub fn pick_random_legal(board: [u8; ROW_COUNT], rng: &mut impl Rng) -> Option<(u8, u8)> {
+    let mut moves = legal_moves(board);
+    if moves.len() > 1 {
+        moves.retain(|&mv| mv != (POISON_ROW, POISON_COL));
+    }
+    moves.choose(rng).copied()
+}
+
+/// Which side of the game the bot is sitting on.
+///
+/// The program answers every move inside the same transaction, so whenever we
+/// read the PDA it is our turn and the solver always evaluates the position from
+/// the mover's point of view. The seat only records who opened: `First` when we
+/// sent the opening move from an empty board (always a forced win), `Second`
+/// when we picked up a game that already had moves on it, which may be lost.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Seat {
+    First,
+    Second,
+}
+
+impl Seat {
+    pub fn from_board(board: [u8; ROW_COUNT]) -> Self {
+        if board.iter().all(|row| *row == 0) {
+            Seat::First
+        } else {
+            Seat::Second
+        }
+    }
+}
+
+/// Number of still-uneaten squares the move `(r, c)` would remove.
+fn squares_taken(board: [u8; ROW_COUNT], r: u8, c: u8) -> u32 {
+    board
+        .iter()
+        .take(r as usize)
+        .map(|row| (PREFIX_MASK[(c - 1) as usize] & !row).count_ones())
+        .sum()
+}
+
+/// Move for a position with no forced win: the reply after which the winner
+/// needs the most plies, so the opponent has the most chances to slip. Only
+/// touches the poison square when nothing else is left.
+pub fn best_try(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
     STRATEGY
-        .best_reply(&bitmask_to_skyline(board))
+        .best_defensive_reply(&bitmask_to_skyline(board).rows())
         .map(|(row, col)| ((row as u8) + 1, col as u8))
+        .or_else(|| pick_any_legal(board))
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Classified {
-    Unexplored,
-    Winning(u8, u8),
-    Losing,
+/// The opposite of `best_try`: eat as much as possible in one bite, still
+/// leaving the poison for last.
+pub fn pick_greedy(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+    legal_moves(board)
+        .into_iter()
+        .filter(|&mv| mv != (POISON_ROW, POISON_COL))
+        .max_by_key(|&(r, c)| squares_taken(board, r, c))
+        .or_else(|| pick_any_legal(board))
 }
 
-/// Tracks how many squares are already eaten from each row.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Skyline(pub [u8; ROW_COUNT]);
+/// Plays `moves` from the empty board and returns the position reached, or a
+/// message naming the first move that was not legal on the running board.
+pub fn replay(moves: &[(u8, u8)]) -> Result<[u8; ROW_COUNT], String> {
+    let mut board = [0u8; ROW_COUNT];
+    for (i, &(r, c)) in moves.iter().enumerate() {
+        if !legal_moves(board).contains(&(r, c)) {
+            return Err(format!(
+                "move #{} ({},{}) is not legal on {:02X?}",
+                i + 1,
+                r,
+                c,
+                board
+            ));
+        }
+        board = apply_move(board, r, c);
+    }
+    Ok(board)
+}
 
-impl Skyline {
-    pub fn encode(&self) -> usize {
-        let mut idx = 0usize;
-        let mut trailing = COL_COUNT as u8;
-        self.0.iter().for_each(|&val| {
-            if trailing > val {
-                idx <<= (trailing - val) as usize;
-                trailing = val;
+/// Squares still on `board` apart from the poison, a rough progress measure:
+/// 39 on the empty board, 0 once only glass is left.
+pub fn moves_remaining(board: [u8; ROW_COUNT]) -> usize {
+    let uneaten: usize = bitmask_to_skyline(board)
+        .0
+        .iter()
+        .map(|&eaten| (COLS_U8 - eaten) as usize)
+        .sum();
+    uneaten.saturating_sub(usize::from(move_is_open(board, POISON_ROW, POISON_COL)))
+}
+
+/// Every open square on `board`, poison included, as 1-based `(row, col)`.
+pub fn legal_moves(board: [u8; ROW_COUNT]) -> Vec<(u8, u8)> {
+    let mut moves = Vec::new();
+    for r in 1..=ROWS_U8 {
+        for c in 1..=COLS_U8 {
+            if move_is_open(board, r, c) {
+                moves.push((r, c));
             }
-            idx = (idx << 1) | 1;
-        });
-        idx << trailing as usize
+        }
     }
+    moves
+}
+
+/// The one move that turns `before` into `after`, e.g. the program's reply
+/// between two reads of its board; `None` when no single move does.
+pub fn move_between(before: [u8; ROW_COUNT], after: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+    legal_moves(before)
+        .into_iter()
+        .find(|&(r, c)| apply_move(before, r, c) == after)
+}
+
+/// Every legal reply on `board`, where the opponent is to move, with the
+/// position it hands back classified for us. When `board` is losing for the
+/// opponent, every entry is `Winning`.
+pub fn opponent_reply_analysis(board: [u8; ROW_COUNT]) -> Vec<((u8, u8), Outcome)> {
+    legal_moves(board)
+        .into_iter()
+        .map(|(r, c)| ((r, c), classify(apply_move(board, r, c))))
+        .collect()
+}
 
-    pub fn decode(mut encoded: usize) -> Self {
-        let mut rows = [0u8; ROW_COUNT];
-        rows[ROW_COUNT - 1] = encoded.trailing_zeros() as u8;
-        encoded >>= (rows[ROW_COUNT - 1] + 1) as usize;
+/// Among the winning moves (or every non-poison legal move when there is no
+/// win) picks the one that leaves the opponent the fewest legal replies.
+pub fn pick_most_constraining(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+    let mut candidates = winning_moves(board);
+    if candidates.is_empty() {
+        candidates = legal_moves(board);
+        candidates.retain(|&mv| mv != (POISON_ROW, POISON_COL));
+    }
+    candidates
+        .into_iter()
+        .min_by_key(|&(r, c)| legal_moves(apply_move(board, r, c)).len())
+        .or_else(|| pick_any_legal(board))
+}
 
-        let mut zeros_seen = 0u8;
-        let mut cursor = ROW_COUNT - 1;
+/// Forced win when one exists, otherwise the best try from a lost position.
+pub fn choose_move(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+    pick_forced_victory(board).or_else(|| best_try(board))
+}
 
-        while encoded != 0 {
-            if encoded & 1 == 1 {
-                cursor -= 1;
-                rows[cursor] = rows[cursor + 1] + zeros_seen;
-                zeros_seen = 0;
-                encoded >>= 1;
+/// Game-theoretic value of a position for the player about to move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Winning,
+    Losing,
+}
+
+pub fn classify(board: [u8; ROW_COUNT]) -> Outcome {
+    if STRATEGY.is_winning(&bitmask_to_skyline(board).rows()) {
+        Outcome::Winning
+    } else {
+        Outcome::Losing
+    }
+}
+
+/// True when the player who just moved, leaving `board`, has a forced win under
+/// `Rules::PoisonLoses`. This is the complement of `classify`, which answers for
+/// the player about to move.
+pub fn mover_is_winning(board: [u8; ROW_COUNT]) -> bool {
+    classify(board) == Outcome::Losing
+}
+
+/// `mover_is_winning` for either pot rule. Under `LastMoveWins` the player to
+/// move can always take (5,8) and eat everything, so the mover is only winning
+/// once the board is fully eaten.
+pub fn mover_is_winning_under(board: [u8; ROW_COUNT], rules: Rules) -> bool {
+    match rules {
+        Rules::PoisonLoses => mover_is_winning(board),
+        Rules::LastMoveWins => board.iter().all(|row| *row == 0xFF),
+    }
+}
+
+/// Startup sanity checks of the solver against known Chomp facts, as
+/// `(description, passed)` pairs.
+pub fn self_check() -> Vec<(&'static str, bool)> {
+    let empty = [0u8; ROW_COUNT];
+    let glass = [0xFF, 0xFF, 0xFF, 0xFF, 0xFE];
+    // Strategy stealing: if taking the far corner (1,1) is not already a win,
+    // the opponent's winning reply also eats (1,1), so we could have played it
+    // straight away and it must show up among our opening wins.
+    let corner = apply_move(empty, 1, 1);
+    let stealing = classify(corner) == Outcome::Losing
+        || winning_moves(corner)
+            .iter()
+            .any(|mv| winning_moves(empty).contains(mv));
+    vec![
+        (
+            "empty 5x8 board is a first-player win",
+            classify(empty) == Outcome::Winning,
+        ),
+        match rules() {
+            Rules::PoisonLoses => (
+                "glass-only board is lost for the mover",
+                classify(glass) == Outcome::Losing,
+            ),
+            Rules::LastMoveWins => (
+                "glass-only board is won by taking the poison",
+                winning_moves(glass) == [(POISON_ROW, POISON_COL)],
+            ),
+        },
+        ("strategy stealing holds for the corner move", stealing),
+        ("every entry agrees with its children", STRATEGY.verify()),
+    ]
+}
+
+/// Plies left until someone eats the poison when both sides play perfectly:
+/// the winner hurries, the loser stalls. Even for winning positions, odd for
+/// losing ones, and 0 once the board is fully eaten.
+pub fn depth(board: [u8; ROW_COUNT]) -> u8 {
+    STRATEGY.depths()[bitmask_to_skyline(board).encode()]
+}
+
+/// The perfect-play line from `board` until the poison is eaten, as 1-based
+/// moves: the side that is winning takes the quickest win, the side that is
+/// losing takes the longest loss, matching `depth`.
+pub fn principal_variation(board: [u8; ROW_COUNT]) -> Vec<(u8, u8)> {
+    let mut line = Vec::new();
+    let mut board = board;
+    while board.iter().any(|row| *row != 0xFF) {
+        let candidates = match classify(board) {
+            Outcome::Winning => winning_moves(board),
+            Outcome::Losing => legal_moves(board),
+        };
+        let stalling = classify(board) == Outcome::Losing;
+        let Some(&mv) = candidates.iter().min_by_key(|&&(r, c)| {
+            let d = depth(apply_move(board, r, c)) as i16;
+            if stalling {
+                -d
             } else {
-                zeros_seen += 1;
-                encoded >>= 1;
+                d
             }
+        }) else {
+            break;
+        };
+        line.push(mv);
+        board = apply_move(board, mv.0, mv.1);
+    }
+    line
+}
+
+/// A game played out in memory by `self_play`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfPlay {
+    /// Every move, the first player's first; the last one took the poison.
+    pub moves: Vec<(u8, u8)>,
+    pub first_won: bool,
+}
+
+/// Plays `first` against `second` from `board` to the end, applying each
+/// move to a local board under the program's rule: whoever takes the poison
+/// loses.
+pub fn self_play(
+    board: [u8; ROW_COUNT],
+    mut first: impl FnMut([u8; ROW_COUNT]) -> Option<(u8, u8)>,
+    mut second: impl FnMut([u8; ROW_COUNT]) -> Option<(u8, u8)>,
+) -> SelfPlay {
+    let mut position = board;
+    let mut moves = Vec::new();
+    let mut firsts_turn = true;
+    let first_won = loop {
+        let mv = if firsts_turn { first(position) } else { second(position) };
+        // No open square at all: the last move took the poison.
+        let Some((r, c)) = mv else { break firsts_turn };
+        moves.push((r, c));
+        if (r, c) == (POISON_ROW, POISON_COL) {
+            break !firsts_turn;
         }
+        position = apply_move(position, r, c);
+        firsts_turn = !firsts_turn;
+    };
+    SelfPlay { moves, first_won }
+}
 
-        Self(rows)
+/// Fraction of `trials` games won by `strategy`, moving first from `board`,
+/// against `opponent`. Each trial is a `self_play` game. The same `seed`
+/// gives the same estimate.
+pub fn estimate_win_rate(
+    board: [u8; ROW_COUNT],
+    strategy: impl Fn([u8; ROW_COUNT]) -> Option<(u8, u8)>,
+    opponent: impl Fn([u8; ROW_COUNT], &mut StdRng) -> Option<(u8, u8)>,
+    trials: u32,
+    seed: u64,
+) -> f64 {
+    if trials == 0 {
+        return 0.0;
     }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut wins = 0u32;
+    for _ in 0..trials {
+        let game = self_play(board, &strategy, |b| opponent(b, &mut rng));
+        wins += u32::from(game.first_won);
+    }
+    f64::from(wins) / f64::from(trials)
 }
 
-pub struct PositionTable {
-    book: [Classified; TABLE_SIZE],
+/// One ply of an exported line: who was winning before `mv`, and the board after it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PvPly {
+    #[serde(rename = "move")]
+    pub mv: (u8, u8),
+    pub classification: Outcome,
+    pub board: Board,
 }
 
-impl PositionTable {
-    pub fn new() -> Self {
-        let mut book = [Classified::Unexplored; TABLE_SIZE];
-        // Base cases: completely eaten and glass-only endings.
-        book[0b1111100000000] = Classified::Winning(0xFF, 0xFF);
-        book[0b1111010000000] = Classified::Losing;
-
-        fn dfs(idx: usize, book: &mut [Classified]) {
-            if !matches!(book[idx], Classified::Unexplored) {
-                return;
-            }
+/// A shareable replay of `principal_variation` from `start`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PvLine {
+    pub start: Board,
+    pub plies: Vec<PvPly>,
+}
 
-            let snapshot = Skyline::decode(idx);
-            let mut found_response = false;
-
-            for r in 0..ROW_COUNT as u8 {
-                let current = snapshot.0[r as usize];
-                for c in (current + 1)..=COLS_U8 {
-                    let mut next = snapshot;
-                    for fill_row in 0..=r {
-                        let slot = fill_row as usize;
-                        next.0[slot] = next.0[slot].max(c);
-                    }
-                    let next_idx = next.encode();
-                    if book[next_idx] == Classified::Unexplored {
-                        dfs(next_idx, book);
-                    }
-                    if book[next_idx] == Classified::Losing {
-                        book[idx] = Classified::Winning(r, c);
-                        found_response = true;
-                    }
+impl PvLine {
+    pub fn from_board(start: [u8; ROW_COUNT]) -> Self {
+        let mut board = start;
+        let plies = principal_variation(start)
+            .into_iter()
+            .map(|(r, c)| {
+                let classification = classify(board);
+                board = apply_move(board, r, c);
+                PvPly {
+                    mv: (r, c),
+                    classification,
+                    board: Board::from_rows(board),
                 }
-            }
+            })
+            .collect();
+        PvLine {
+            start: Board::from_rows(start),
+            plies,
+        }
+    }
 
-            if !found_response {
-                book[idx] = Classified::Losing;
+    /// Re-applies every move from `start` and checks it is legal and lands on
+    /// the recorded board, so a hand-edited file is caught before it is shown.
+    pub fn verify(&self) -> Result<(), String> {
+        let mut board = self.start.rows();
+        for (i, ply) in self.plies.iter().enumerate() {
+            let (r, c) = ply.mv;
+            if !is_legal_move(board, r, c) || (r, c) == (0, 0) {
+                return Err(format!("ply {} ({},{}) is not legal", i + 1, r, c));
+            }
+            board = apply_move(board, r, c);
+            if board != ply.board.rows() {
+                return Err(format!("ply {} does not match its recorded board", i + 1));
             }
         }
+        Ok(())
+    }
+}
 
-        dfs(0b11111, &mut book);
+/// The winning move for `board` under the active `rules` that ends the game
+/// soonest, so a won game costs as few transactions as possible.
+pub fn pick_forced_victory(board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+    forced_victory(&STRATEGY, board)
+}
+
+fn forced_victory(table: &PositionTable, board: [u8; ROW_COUNT]) -> Option<(u8, u8)> {
+    table
+        .best_reply_fastest(&bitmask_to_skyline(board).rows())
+        .map(|(row, col)| ((row as u8) + 1, col as u8))
+}
 
-        Self { book }
+
+
+impl Skyline {
+    /// The row bitmasks the program stores for this skyline.
+    pub fn to_board(self) -> [u8; ROW_COUNT] {
+        self.rows()
+            .map(|eaten| eaten.checked_sub(1).map_or(0, |i| PREFIX_MASK[i as usize]))
     }
+}
 
-    pub fn best_reply(&self, skyline: &Skyline) -> Option<(usize, usize)> {
-        match self.book[skyline.encode()] {
-            Classified::Winning(0xFF, 0xFF) => None,
-            Classified::Winning(r, c) => Some((r as usize, c as usize)),
-            _ => None,
-        }
+/// Eaten counts from the top row down, e.g. `8,8,6,3,0`.
+impl fmt::Display for Skyline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self.0.iter().map(u8::to_string).collect();
+        write!(f, "{}", counts.join(","))
     }
 }
 
-fn bitmask_to_skyline(board: [u8; ROW_COUNT]) -> Skyline {
-    let mut rows = [0u8; ROW_COUNT];
-    for (i, &mask) in board.iter().enumerate() {
-        rows[i] = mask.leading_ones() as u8;
+impl FromStr for Skyline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let counts = s
+            .split(',')
+            .map(|part| part.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("expected {} comma-separated counts, got `{}`", ROW_COUNT, s))?;
+        let rows: [u8; ROW_COUNT] = counts
+            .try_into()
+            .map_err(|_| format!("expected {} comma-separated counts, got `{}`", ROW_COUNT, s))?;
+        Skyline::new(rows).ok_or_else(|| {
+            format!(
+                "`{}` is not a skyline: counts must be at most {} and never grow down the rows",
+                s, COL_COUNT
+            )
+        })
     }
-    Skyline(rows)
+}
+
+
+/// All winning moves for `board` as 1-based `(row, col)` pairs.
+pub fn winning_moves(board: [u8; ROW_COUNT]) -> Vec<(u8, u8)> {
+    STRATEGY
+        .all_winning_replies(&bitmask_to_skyline(board).rows())
+        .into_iter()
+        .map(|(row, col)| ((row as u8) + 1, col as u8))
+        .collect()
+}
+
+/// A board as stored on chain: one row mask per row, bit `0x80` is column 1 and
+/// a set bit means eaten. Serializes as the five raw row bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Board([u8; ROW_COUNT]);
+
+impl Board {
+    pub fn from_rows(rows: [u8; ROW_COUNT]) -> Self {
+        Board(rows)
+    }
+
+    pub fn rows(&self) -> [u8; ROW_COUNT] {
+        self.0
+    }
+
+    pub fn skyline(&self) -> Skyline {
+        bitmask_to_skyline(self.0)
+    }
+
+    pub fn to_bincode(self) -> bincode::Result<Vec<u8>> {
+        bincode::serialize(&self)
+    }
+
+    pub fn from_bincode(bytes: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(bytes)
+    }
+}
+
+fn bitmask_to_skyline(board: [u8; ROW_COUNT]) -> Skyline {
+    let counts = BoardShape::STANDARD.counts_from_masks(&board);
+    Skyline(counts.try_into().expect("one count per row"))
 }
 
 #[cfg(test)]
@@ -163,6 +682,28 @@ mod tests {
         assert!(mv.is_some());
     }
 
+    #[test]
+    fn solver_builds() {
+        assert!(solver_ready());
+    }
+
+    #[test]
+    fn strategy_cache_refuses_other_shapes() {
+        let path = std::env::temp_dir().join(format!("chomp-cache-{}.bin", std::process::id()));
+        let narrow = PositionTable::new(BoardShape::new(5, 7).unwrap(), Rules::PoisonLoses);
+        narrow.save_to(File::create(&path).unwrap()).unwrap();
+        let err = load_strategy_cache(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("5x7"), "{}", err);
+    }
+
+    #[cfg(feature = "embed-table")]
+    #[test]
+    fn embedded_table_matches_a_fresh_solve() {
+        assert_eq!(EMBEDDED_TABLE, PositionTable::new(BoardShape::STANDARD, Rules::PoisonLoses).to_bytes().as_slice());
+    }
+
     #[test]
     fn terminal_is_losing() {
         let s = [0xFF, 0xFF, 0xFF, 0xFF, 0xFE];
@@ -176,6 +717,367 @@ mod tests {
         assert_eq!(mv.1, 8);
     }
 
+    #[test]
+    fn seat_follows_opening_board() {
+        assert_eq!(Seat::from_board([0u8; ROW_COUNT]), Seat::First);
+        assert_eq!(Seat::from_board([0x80, 0, 0, 0, 0]), Seat::Second);
+    }
+
+    #[test]
+    fn best_try_plays_on_from_losing_position() {
+        // Equal L-shaped arms around the poison: every reply loses for the mover.
+        let board = [0xFF, 0xFF, 0xFF, 0xFE, 0xFC];
+        assert!(pick_forced_victory(board).is_none());
+        let (r, c) = best_try(board).expect("move");
+        assert_ne!((r, c), (POISON_ROW, POISON_COL));
+        assert_eq!(squares_taken(board, r, c), 1);
+        assert_eq!(choose_move(board), Some((r, c)));
+    }
+
+    #[test]
+    fn greedy_takes_the_biggest_bite_but_not_the_poison() {
+        assert_eq!(pick_greedy([0u8; ROW_COUNT]), Some((5, 7)));
+        let glass = [0xFF, 0xFF, 0xFF, 0xFF, 0xFE];
+        assert_eq!(pick_greedy(glass), Some((POISON_ROW, POISON_COL)));
+        assert_eq!(pick_greedy([0xFF; ROW_COUNT]), None);
+    }
+
+    #[test]
+    fn best_try_takes_poison_only_when_forced() {
+        let board = [0xFF, 0xFF, 0xFF, 0xFF, 0xFE];
+        assert_eq!(best_try(board), Some((POISON_ROW, POISON_COL)));
+    }
+
+    #[test]
+    fn empty_board_winning_moves_include_corner_opening() {
+        let moves = winning_moves([0u8; ROW_COUNT]);
+        assert!(moves.contains(&(1, 2)));
+        assert!(moves.contains(&pick_forced_victory([0u8; ROW_COUNT]).unwrap()));
+    }
+
+    #[test]
+    fn staircase_has_several_winning_moves() {
+        let board = [0xFF, 0xFE, 0xFC, 0xF8, 0xF0];
+        assert_eq!(winning_moves(board), vec![(3, 8), (4, 7), (5, 6)]);
+        let skyline = bitmask_to_skyline(board);
+        for (r, c) in STRATEGY.all_winning_replies(&skyline.rows()) {
+            let next = skyline.after(r as u8, c as u8);
+            assert!(STRATEGY.best_reply(&next.rows()).is_none());
+        }
+    }
+
+    #[test]
+    fn fully_eaten_skyline_hits_max_index() {
+        let full = Skyline([COLS_U8; ROW_COUNT]);
+        assert_eq!(full.encode(), max_encoded_index(ROW_COUNT, COL_COUNT));
+        assert!(full.encode() < TABLE_SIZE);
+    }
+
+    #[test]
+    fn encode_is_injective_and_in_bounds() {
+        // Every valid skyline: eaten counts never grow from one row to the next.
+        fn fill(row: usize, cap: u8, rows: &mut [u8; ROW_COUNT], out: &mut Vec<Skyline>) {
+            if row == ROW_COUNT {
+                out.push(Skyline(*rows));
+                return;
+            }
+            for eaten in 0..=cap {
+                rows[row] = eaten;
+                fill(row + 1, eaten, rows, out);
+            }
+        }
+        let mut skylines = Vec::new();
+        fill(0, COLS_U8, &mut [0; ROW_COUNT], &mut skylines);
+        // C(ROW_COUNT + COL_COUNT, ROW_COUNT) monotone skylines.
+        assert_eq!(skylines.len(), 1287);
+
+        let mut seen = vec![false; TABLE_SIZE];
+        for skyline in &skylines {
+            let idx = skyline.encode();
+            assert!(idx < TABLE_SIZE, "{:?} encodes to {}", skyline, idx);
+            assert!(!seen[idx], "{:?} collides at {}", skyline, idx);
+            seen[idx] = true;
+            assert_eq!(Skyline::decode(idx), *skyline);
+        }
+        // One skyline per walk of 5 ones and 8 zeros, so C(13, 5) of the slots.
+        assert_eq!(skylines.len(), 1287);
+    }
+
+    #[test]
+    fn forced_victory_beats_any_legal_from_empty() {
+        let game = self_play([0u8; ROW_COUNT], choose_move, pick_any_legal);
+        assert!(game.first_won, "{:?}", game.moves);
+        assert_eq!(game.moves.len() % 2, 0, "the second player takes the poison");
+        let (last, played) = game.moves.split_last().unwrap();
+        assert_eq!(*last, (POISON_ROW, POISON_COL));
+        let before_poison = played.iter().fold([0u8; ROW_COUNT], |b, &(r, c)| {
+            assert!(move_is_open(b, r, c), "({},{}) on {:02x?}", r, c, b);
+            apply_move(b, r, c)
+        });
+        assert!(is_glass_only(before_poison));
+
+        assert!(!self_play([0u8; ROW_COUNT], pick_any_legal, choose_move).first_won);
+    }
+
+    #[test]
+    fn win_rate_against_random_and_perfect_play() {
+        let empty = [0u8; ROW_COUNT];
+        assert_eq!(
+            estimate_win_rate(empty, choose_move, pick_random_legal, 200, 7),
+            1.0
+        );
+        let greedy = |b| pick_any_legal(b);
+        let rate = estimate_win_rate(empty, greedy, pick_random_legal, 200, 7);
+        assert!(rate < 1.0);
+        assert_eq!(
+            rate,
+            estimate_win_rate(empty, greedy, pick_random_legal, 200, 7)
+        );
+
+        let lost = [0xFF, 0xFF, 0xFF, 0xFE, 0xFC];
+        let perfect = |b, _: &mut StdRng| choose_move(b);
+        assert_eq!(estimate_win_rate(lost, choose_move, perfect, 10, 0), 0.0);
+    }
+
+    #[test]
+    fn replies_to_a_winning_move_all_leave_us_winning() {
+        let after_opening = apply_move([0u8; ROW_COUNT], 1, 2);
+        let replies = opponent_reply_analysis(after_opening);
+        assert_eq!(replies.len(), legal_moves(after_opening).len());
+        assert!(replies.iter().all(|&(_, us)| us == Outcome::Winning));
+
+        // After a bad move of ours the opponent has at least one reply that beats us.
+        let after_blunder = apply_move([0u8; ROW_COUNT], 5, 1);
+        assert!(opponent_reply_analysis(after_blunder)
+            .iter()
+            .any(|&(_, us)| us == Outcome::Losing));
+    }
+
+    #[test]
+    fn apply_move_eats_up_and_left() {
+        let board = apply_move([0u8; ROW_COUNT], 3, 2);
+        assert_eq!(board, [0xC0, 0xC0, 0xC0, 0, 0]);
+        assert_eq!(apply_move(board, 1, 4), [0xF0, 0xC0, 0xC0, 0, 0]);
+    }
+
+    #[test]
+    fn the_move_between_two_boards_is_recovered() {
+        let empty = [0u8; ROW_COUNT];
+        let board = apply_move(empty, 3, 2);
+        assert_eq!(move_between(empty, board), Some((3, 2)));
+        assert_eq!(move_between(board, apply_move(board, 1, 4)), Some((1, 4)));
+        // Two bites at once, or no change at all, is not one move.
+        assert_eq!(move_between(empty, apply_move(board, 1, 4)), None);
+        assert_eq!(move_between(board, board), None);
+    }
+
+    #[test]
+    fn only_a_request_cashes_out() {
+        let empty = [0u8; ROW_COUNT];
+        assert_eq!(decide(empty, false, || Some((2, 3))), Decision::Move(2, 3));
+        assert_eq!(decide(empty, true, || unreachable!("no move is picked when cashing out")), Decision::CashOut);
+        // A strategy with nothing to offer, or offering the sentinel, does not cash out.
+        assert_eq!(decide([0xFF; ROW_COUNT], false, || None), Decision::NoMove);
+        assert_eq!(decide(empty, false, || Some((0, 0))), Decision::NoMove);
+        assert_eq!(decide([0x80, 0, 0, 0, 0], false, || Some((1, 1))), Decision::NoMove);
+        assert_eq!(Decision::Move(5, 1).square(), Some((5, 1)));
+        assert_eq!(Decision::CashOut.square(), None);
+    }
+
+    #[test]
+    fn apply_move_corners_rows_and_eaten_squares() {
+        let empty = [0u8; ROW_COUNT];
+        // Corners: the top-left takes one square, the poison takes the rest.
+        assert_eq!(apply_move(empty, 1, 1), [0x80, 0, 0, 0, 0]);
+        assert_eq!(apply_move(empty, 5, 8), [0xFF; ROW_COUNT]);
+        assert_eq!(apply_move(empty, 5, 1), [0x80; ROW_COUNT]);
+        // A move in the last column eats every row above it whole.
+        assert_eq!(apply_move(empty, 2, 8), [0xFF, 0xFF, 0, 0, 0]);
+
+        // Replaying a move, or one inside what is already eaten, changes nothing.
+        let board = apply_move(empty, 4, 5);
+        assert_eq!(apply_move(board, 4, 5), board);
+        assert_eq!(apply_move(board, 2, 3), board);
+        assert_eq!(apply_move(apply_move(board, 1, 7), 1, 7), apply_move(board, 1, 7));
+    }
+
+    #[test]
+    fn most_constraining_keeps_forced_win() {
+        let empty = [0u8; ROW_COUNT];
+        assert_eq!(pick_most_constraining(empty), pick_forced_victory(empty));
+
+        let board = [0xFF, 0xFE, 0xFC, 0xF8, 0xF0];
+        let mv = pick_most_constraining(board).expect("move");
+        assert!(winning_moves(board).contains(&mv));
+        let replies = |(r, c): (u8, u8)| legal_moves(apply_move(board, r, c)).len();
+        assert!(winning_moves(board).into_iter().all(|other| replies(mv) <= replies(other)));
+    }
+
+    #[test]
+    fn most_constraining_avoids_poison_when_losing() {
+        let board = [0xFF, 0xFF, 0xFF, 0xFE, 0xFC];
+        let mv = pick_most_constraining(board).expect("move");
+        assert_ne!(mv, (POISON_ROW, POISON_COL));
+        assert!(winning_moves(board).is_empty());
+        let replies = |(r, c): (u8, u8)| legal_moves(apply_move(board, r, c)).len();
+        assert!(legal_moves(board)
+            .into_iter()
+            .filter(|&other| other != (POISON_ROW, POISON_COL))
+            .all(|other| replies(mv) <= replies(other)));
+    }
+
+    #[test]
+    fn opening_depends_on_the_convention() {
+        let empty = [0u8; ROW_COUNT];
+        let normal = PositionTable::new(BoardShape::STANDARD, Rules::LastMoveWins);
+        assert_eq!(forced_victory(&normal, empty), Some((POISON_ROW, POISON_COL)));
+        let misere = pick_forced_victory(empty).expect("first-player win");
+        assert_ne!(misere, (POISON_ROW, POISON_COL));
+    }
+
+    #[test]
+    fn mover_outcome_depends_on_rules() {
+        let glass = [0xFF, 0xFF, 0xFF, 0xFF, 0xFE];
+        let eaten = [0xFF; 5];
+        assert!(mover_is_winning(glass));
+        assert!(!mover_is_winning(eaten));
+        assert!(!mover_is_winning([0u8; 5]));
+        assert!(!mover_is_winning_under(glass, Rules::LastMoveWins));
+        assert!(mover_is_winning_under(eaten, Rules::LastMoveWins));
+    }
+
+    #[test]
+    fn board_bincode_round_trip() {
+        let board = Board::from_rows([0xFF, 0xFE, 0xC0, 0x80, 0]);
+        let bytes = board.to_bincode().unwrap();
+        assert_eq!(bytes, board.rows());
+        assert_eq!(Board::from_bincode(&bytes).unwrap(), board);
+        assert_eq!(board.skyline(), Skyline([8, 7, 2, 1, 0]));
+        assert!(Board::from_bincode(&bytes[..3]).is_err());
+
+        let sky = Skyline([3, 2, 2, 0, 0]);
+        let decoded: Skyline = bincode::deserialize(&bincode::serialize(&sky).unwrap()).unwrap();
+        assert_eq!(decoded, sky);
+    }
+
+    #[test]
+    fn skyline_text_and_validation() {
+        let sky: Skyline = "8,8,6,3,0".parse().unwrap();
+        assert_eq!(sky.rows(), [8, 8, 6, 3, 0]);
+        assert_eq!(sky.to_string(), "8,8,6,3,0");
+        assert_eq!(" 2, 1,0,0 ,0".parse::<Skyline>(), Ok(Skyline([2, 1, 0, 0, 0])));
+        assert!("1,2,0,0,0".parse::<Skyline>().is_err());
+        assert!("9,0,0,0,0".parse::<Skyline>().is_err());
+        assert!("8,8,6,3".parse::<Skyline>().is_err());
+        assert!("8,8,x,3,0".parse::<Skyline>().is_err());
+
+        let board = [0xFF, 0xFE, 0xC0, 0x80, 0];
+        assert_eq!(bitmask_to_skyline(board).to_board(), board);
+        assert_eq!(Skyline::new([0; ROW_COUNT]).unwrap().to_board(), [0; ROW_COUNT]);
+    }
+
+    #[test]
+    fn replay_reports_first_illegal_move() {
+        assert_eq!(replay(&[(5, 1), (3, 2)]), Ok([0xC0, 0xC0, 0xC0, 0x80, 0x80]));
+        let err = replay(&[(5, 1), (3, 1), (1, 3)]).unwrap_err();
+        assert!(err.starts_with("move #2 (3,1)"), "{}", err);
+        assert!(replay(&[(6, 1)]).is_err());
+    }
+
+    #[test]
+    fn principal_variation_runs_to_the_poison() {
+        let empty = [0u8; 5];
+        let line = principal_variation(empty);
+        assert_eq!(line.len(), depth(empty) as usize);
+        assert_eq!(line.last(), Some(&(POISON_ROW, POISON_COL)));
+        assert_eq!(line[0], (1, 2));
+
+        let pv = PvLine::from_board(empty);
+        assert!(pv.verify().is_ok());
+        let json = serde_json::to_string(&pv).unwrap();
+        let back: PvLine = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, pv);
+
+        let mut tampered = pv;
+        tampered.plies[0].mv = (5, 8);
+        assert!(tampered.verify().is_err());
+    }
+
+    #[test]
+    fn moves_remaining_counts_non_poison_squares() {
+        assert_eq!(moves_remaining([0u8; 5]), 39);
+        assert_eq!(moves_remaining([0xFF, 0xFF, 0xFF, 0xFF, 0xFE]), 0);
+        assert_eq!(moves_remaining([0xFF; 5]), 0);
+        assert_eq!(moves_remaining([0xFF, 0xF0, 0, 0, 0]), 27);
+    }
+
+    #[test]
+    fn cash_out_sentinel_does_not_underflow() {
+        let board = [0u8; 5];
+        assert!(!move_is_open(board, 0, 0));
+        assert!(!move_is_open(board, 0, 3));
+        assert!(!move_is_open(board, 2, 0));
+        assert!(!move_is_open(board, 6, 9));
+        assert_eq!(apply_move(board, 0, 0), board);
+    }
+
+    #[test]
+    fn legal_move_cases() {
+        let board = [0xFF, 0xF0, 0, 0, 0];
+        assert!(is_legal_move(board, 2, 5));
+        assert!(!is_legal_move(board, 2, 4));
+        assert!(!is_legal_move(board, 6, 1));
+        assert!(!is_legal_move(board, 1, 0));
+        assert!(is_legal_move(board, POISON_ROW, POISON_COL));
+        assert!(is_legal_move(board, 0, 0));
+        assert!(!is_legal_move([0; 5], 0, 0));
+    }
+
+    #[test]
+    fn validates_board_shapes() {
+        assert!(validate_board([0u8; 5]).is_ok());
+        assert!(validate_board([0xFF, 0xFE, 0xC0, 0x80, 0]).is_ok());
+        assert!(validate_board([0xA0, 0, 0, 0, 0]).is_err());
+        assert!(validate_board([0x80, 0xC0, 0, 0, 0]).is_err());
+
+        // Holes, rows eaten from the wrong end, and lower rows sticking out.
+        for bad in [
+            [0xFF, 0xEF, 0, 0, 0],
+            [0x7F, 0, 0, 0, 0],
+            [0x01, 0, 0, 0, 0],
+            [0xFF, 0xFF, 0xF0, 0xF0, 0xF8],
+            [0, 0, 0, 0, 0x80],
+            [0xFF, 0xFF, 0xFF, 0xFF, 0x7F],
+        ] {
+            assert!(validate_board(bad).is_err(), "{:02x?}", bad);
+        }
+        assert_eq!(
+            validate_board([0xFF, 0xEF, 0, 0, 0]).unwrap_err(),
+            "row 2 (0xef) has a gap in its eaten squares"
+        );
+        assert_eq!(
+            validate_board([0xF0, 0xF0, 0xF8, 0, 0]).unwrap_err(),
+            "row 3 has more eaten than the row above it"
+        );
+    }
+
+    #[test]
+    fn depth_parity_matches_outcome() {
+        assert_eq!(depth([0xFF; 5]), 0);
+        assert_eq!(depth([0xFF, 0xFF, 0xFF, 0xFF, 0xFE]), 1);
+        for board in [[0u8; 5], [0xFF, 0xFE, 0xFC, 0xF8, 0xF0], [0xFF, 0xFF, 0xFF, 0xFE, 0xFC]] {
+            let even = depth(board).is_multiple_of(2);
+            assert_eq!(even, classify(board) == Outcome::Winning, "{:?}", board);
+        }
+    }
+
+    #[test]
+    fn self_check_passes() {
+        for (name, passed) in self_check() {
+            assert!(passed, "{name}");
+        }
+    }
+
     #[test]
     fn skyline_round_trip_cases() {
         let cases = [
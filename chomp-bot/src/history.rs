@@ -0,0 +1,163 @@
+//! `--history-file`: a line per move the bot got confirmed, which
+//! `--replay-history` reads back to send the same moves again.
+//!
+//! A line is `<time> <board> <row>,<col> <signature>`: the RFC 3339 UTC time
+//! the move confirmed, the board it was played on as ten hex digits (row 1
+//! first), the move, and its transaction signature. Blank lines and lines
+//! starting with `#` are skipped.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use solana_sdk::signature::Signature;
+
+use crate::config::parse_move;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub at: SystemTime,
+    /// The board before the move.
+    pub board: [u8; 5],
+    pub mv: (u8, u8),
+    pub signature: Signature,
+}
+
+impl Entry {
+    pub fn now(board: [u8; 5], mv: (u8, u8), signature: Signature) -> Self {
+        Entry {
+            at: SystemTime::now(),
+            board,
+            mv,
+            signature,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", humantime::format_rfc3339_millis(self.at))?;
+        for row in self.board {
+            write!(f, "{:02x}", row)?;
+        }
+        write!(f, " {},{} {}", self.mv.0, self.mv.1, self.signature)
+    }
+}
+
+impl FromStr for Entry {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [at, board, mv, signature] = fields[..] else {
+            return Err(format!(
+                "expected `<time> <board> <row>,<col> <signature>`, got `{}`",
+                line
+            ));
+        };
+        let at = humantime::parse_rfc3339(at).map_err(|e| format!("bad time `{}`: {}", at, e))?;
+        Ok(Entry {
+            at,
+            board: parse_board(board)?,
+            mv: parse_move(mv)?,
+            signature: signature
+                .parse()
+                .map_err(|_| format!("`{}` is not a signature", signature))?,
+        })
+    }
+}
+
+fn parse_board(hex: &str) -> Result<[u8; 5], String> {
+    let bad = || format!("board `{}` is not ten hex digits", hex);
+    if hex.len() != 10 || !hex.is_ascii() {
+        return Err(bad());
+    }
+    let mut board = [0u8; 5];
+    for (i, row) in board.iter_mut().enumerate() {
+        *row = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| bad())?;
+    }
+    Ok(board)
+}
+
+/// Every entry of a history file, in order.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| line.parse().map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// Adds `entry` to the end of `path`, creating the file if needed.
+pub fn append(path: &Path, entry: &Entry) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(board: [u8; 5], mv: (u8, u8), ms: u64) -> Entry {
+        Entry {
+            at: UNIX_EPOCH + Duration::from_millis(ms),
+            board,
+            mv,
+            signature: Signature::new(&[7; 64]),
+        }
+    }
+
+    #[test]
+    fn entries_round_trip_through_lines() {
+        let played = [
+            entry([0; 5], (2, 3), 1_700_000_000_123),
+            entry([0xE0, 0xE0, 0x80, 0x80, 0], (1, 8), 1_700_000_004_500),
+        ];
+        let line = played[1].to_string();
+        assert!(
+            line.starts_with("2023-11-14T22:13:24.500Z e0e0808000 1,8 "),
+            "{}",
+            line
+        );
+
+        let text = format!("# chomp history\n{}\n\n{}\n", played[0], played[1]);
+        assert_eq!(parse(&text).unwrap(), played);
+    }
+
+    #[test]
+    fn bad_lines_name_their_number() {
+        let good = entry([0; 5], (2, 3), 0).to_string();
+        let text = format!("{}\n{}\n", good, good.replace("0000000000", "00000000zz"));
+        assert_eq!(
+            parse(&text).unwrap_err(),
+            "line 2: board `00000000zz` is not ten hex digits"
+        );
+        assert!(parse("2023-11-14T22:13:24Z 0000000000 2,3")
+            .unwrap_err()
+            .starts_with("line 1: expected"));
+        assert!("2023-11-14T22:13:24Z 0000000000 9 sig"
+            .parse::<Entry>()
+            .is_err());
+    }
+
+    #[test]
+    fn appends_one_line_per_entry() {
+        let path = std::env::temp_dir().join(format!("chomp-history-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let played = [
+            entry([0; 5], (2, 3), 1_000),
+            entry([0xC0, 0, 0, 0, 0], (3, 1), 2_000),
+        ];
+        for e in &played {
+            append(&path, e).unwrap();
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(parse(&text).unwrap(), played);
+    }
+}
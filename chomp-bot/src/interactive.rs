@@ -0,0 +1,304 @@
+//! `interactive`: play the on-chain game by hand. Each turn the board is
+//! fetched and drawn with the solver's pick and every winning reply, the
+//! player types a move, `hint`, `auto` or `quit`, and the move is sent. The
+//! loop ends when only the poison is left or the game account closes.
+//! Without the solver table the pick comes from `--strategy` instead, and
+//! the winning replies and hints are left out.
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::Result;
+
+use crate::config::parse_move;
+use crate::game::{self, Outcome};
+use crate::render::format_board;
+
+/// What the player typed at the prompt.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    Move(u8, u8),
+    /// Explain the position: who is winning and how long perfect play lasts.
+    Hint,
+    /// Send the solver's pick.
+    Auto,
+    Quit,
+}
+
+/// Parses a prompt line: `hint`, `auto`, `quit` (or `q`), or a move as
+/// `row col`, `row,col` or either in parentheses.
+pub fn parse_input(line: &str) -> Result<Input, String> {
+    let line = line.trim();
+    match line.to_ascii_lowercase().as_str() {
+        "hint" | "h" => return Ok(Input::Hint),
+        "auto" | "a" => return Ok(Input::Auto),
+        "quit" | "q" => return Ok(Input::Quit),
+        _ => {}
+    }
+    let inner = line
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(line);
+    let fields: Vec<&str> = inner
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|field| !field.is_empty())
+        .collect();
+    match fields[..] {
+        [r, c] => parse_move(&format!("{},{}", r, c)).map(|(r, c)| Input::Move(r, c)),
+        _ => Err(format!(
+            "expected `row col`, hint, auto or quit, got `{}`",
+            line
+        )),
+    }
+}
+
+/// The game the prompt plays: the chain in `main`, a local board in tests.
+pub trait Table {
+    /// The board now, or `None` once the game account is gone.
+    fn board(&mut self) -> Result<Option<[u8; 5]>>;
+    /// Sends `mv`, played on `board`; `false` ends the session, as after a dry run.
+    fn play(&mut self, board: [u8; 5], mv: (u8, u8)) -> Result<bool>;
+}
+
+/// Runs the prompt until the game ends, the input runs out or the player
+/// quits. `open` is whether a missing game account may be opened by the first
+/// move; otherwise there is nothing to play. `solver` is whether the solver
+/// table may be used; without it `fallback` picks the suggested move.
+pub fn run(
+    table: &mut impl Table,
+    open: bool,
+    solver: bool,
+    fallback: impl Fn([u8; 5]) -> Option<(u8, u8)>,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> Result<()> {
+    let mut lines = input.lines();
+    let mut started = false;
+    loop {
+        let board = match table.board()? {
+            Some(board) => board,
+            None if !started && open => {
+                writeln!(out, "No game in progress; your move opens one.")?;
+                [0u8; 5]
+            }
+            None if started => {
+                writeln!(out, "The game account closed — game over.")?;
+                break;
+            }
+            None => {
+                writeln!(out, "No game in progress, and --init-if-missing is off.")?;
+                break;
+            }
+        };
+        started = true;
+        if game::is_glass_only(board) {
+            write!(out, "{}", format_board(&board, None))?;
+            writeln!(out, "Only the poison is left — game over.")?;
+            break;
+        }
+        let pick = if solver {
+            game::choose_move(board)
+        } else {
+            fallback(board)
+        };
+        let pick = pick.expect("an open square besides the poison");
+        let winners = if solver {
+            game::winning_moves(board)
+        } else {
+            vec![]
+        };
+        write!(out, "{}", format_board(&board, Some(pick)))?;
+        if solver {
+            writeln!(
+                out,
+                "solver suggests ({},{}); winning moves: {:?}",
+                pick.0, pick.1, winners
+            )?;
+        } else {
+            writeln!(out, "suggested ({},{}); no solver table", pick.0, pick.1)?;
+        }
+
+        let mv = loop {
+            write!(out, "move (row col), hint, auto or quit: ")?;
+            out.flush()?;
+            let Some(line) = lines.next().transpose()? else {
+                return Ok(());
+            };
+            match parse_input(&line) {
+                Ok(Input::Move(r, c)) if game::move_is_open(board, r, c) => break (r, c),
+                Ok(Input::Move(r, c)) => {
+                    writeln!(out, "({},{}) is not an open square; try again.", r, c)?
+                }
+                Ok(Input::Hint) if solver => hint(&mut out, board, pick)?,
+                Ok(Input::Hint) => writeln!(out, "No hints without the solver table.")?,
+                Ok(Input::Auto) => break pick,
+                Ok(Input::Quit) => return Ok(()),
+                Err(e) => writeln!(out, "{}; try again.", e)?,
+            }
+        };
+        if !winners.is_empty() && !winners.contains(&mv) {
+            writeln!(
+                out,
+                "({},{}) gives up the forced win; sending it anyway.",
+                mv.0, mv.1
+            )?;
+        }
+        if !table.play(board, mv)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn hint(out: &mut impl Write, board: [u8; 5], pick: (u8, u8)) -> io::Result<()> {
+    let plies = game::depth(board);
+    match game::classify(board) {
+        Outcome::Winning => writeln!(
+            out,
+            "You are winning: ({},{}) leaves a lost position, and perfect play ends within {} plies.",
+            pick.0, pick.1, plies
+        ),
+        Outcome::Losing => writeln!(
+            out,
+            "You are losing against perfect play; ({},{}) drags the game out to {} plies.",
+            pick.0, pick.1, plies
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A local board where the solver answers every move, as the program does.
+    struct Local {
+        board: Option<[u8; 5]>,
+        sent: Vec<(u8, u8)>,
+    }
+
+    impl Table for Local {
+        fn board(&mut self) -> Result<Option<[u8; 5]>> {
+            Ok(self.board)
+        }
+
+        fn play(&mut self, board: [u8; 5], mv: (u8, u8)) -> Result<bool> {
+            self.sent.push(mv);
+            let after = game::apply_move(board, mv.0, mv.1);
+            self.board = match game::choose_move(after) {
+                Some(reply) if !game::is_glass_only(after) => {
+                    Some(game::apply_move(after, reply.0, reply.1))
+                }
+                _ => Some(after),
+            };
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn parses_moves_and_commands() {
+        assert_eq!(parse_input("2 3"), Ok(Input::Move(2, 3)));
+        assert_eq!(parse_input(" (2, 3) "), Ok(Input::Move(2, 3)));
+        assert_eq!(parse_input("(4 1)"), Ok(Input::Move(4, 1)));
+        assert_eq!(parse_input("HINT"), Ok(Input::Hint));
+        assert_eq!(parse_input("auto"), Ok(Input::Auto));
+        assert_eq!(parse_input("q"), Ok(Input::Quit));
+        assert!(parse_input("2").is_err());
+        assert!(parse_input("x y").is_err());
+    }
+
+    #[test]
+    fn scripted_session_sends_only_open_squares() {
+        let mut table = Local {
+            board: None,
+            sent: vec![],
+        };
+        let mut out = Vec::new();
+        let script = b"hint\n9 9\nnonsense\n(1 2)\nauto\nquit\n" as &[u8];
+        run(
+            &mut table,
+            true,
+            true,
+            game::pick_any_legal,
+            script,
+            &mut out,
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("your move opens one"), "{}", text);
+        assert!(text.contains("You are winning"), "{}", text);
+        assert!(text.contains("(9,9) is not an open square"), "{}", text);
+        assert!(text.contains("try again"), "{}", text);
+        assert_eq!(table.sent.len(), 2);
+        assert_eq!(table.sent[0], (1, 2));
+
+        let mut closed = Local {
+            board: None,
+            sent: vec![],
+        };
+        let mut out = Vec::new();
+        run(
+            &mut closed,
+            false,
+            true,
+            game::pick_any_legal,
+            b"auto\n" as &[u8],
+            &mut out,
+        )
+        .unwrap();
+        assert!(closed.sent.is_empty());
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("--init-if-missing"));
+    }
+
+    #[test]
+    fn auto_plays_the_game_out() {
+        let mut table = Local {
+            board: Some([0u8; 5]),
+            sent: vec![],
+        };
+        let mut out = Vec::new();
+        run(
+            &mut table,
+            false,
+            true,
+            game::pick_any_legal,
+            "auto\n".repeat(40).as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.ends_with("Only the poison is left — game over.\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn without_the_solver_the_fallback_suggests() {
+        let mut table = Local {
+            board: Some([0u8; 5]),
+            sent: vec![],
+        };
+        let mut out = Vec::new();
+        let script = b"hint\nauto\nquit\n" as &[u8];
+        run(
+            &mut table,
+            false,
+            false,
+            game::pick_any_legal,
+            script,
+            &mut out,
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(table.sent, vec![game::pick_any_legal([0u8; 5]).unwrap()]);
+        assert!(
+            text.contains("No hints without the solver table."),
+            "{}",
+            text
+        );
+        assert!(!text.contains("winning moves"), "{}", text);
+        assert!(!text.contains("You are"), "{}", text);
+    }
+}
@@ -0,0 +1,172 @@
+//! End-to-end checks of the RPC layer against a throwaway `solana-test-validator`.
+//!
+//! Build with `--features localnet-tests`. The test loads the Chomping Glass
+//! program from `CHOMP_PROGRAM_SO` (default `target/deploy/chomping_glass.so`,
+//! produced by `cargo build-sbf` in the repo root) and is skipped, not failed,
+//! when the validator binary or the program build is missing.
+
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::{thread, time::Duration};
+
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use crate::game::choose_move;
+use crate::solana::{
+    fetch_board, get_game_pda, ping_move, reset_game_pda, send_move, MultiRpc, SendOutcome,
+    SolanaError,
+};
+
+const RPC_PORT: u16 = 18899;
+const FEE_COLLECTOR: &str = "EGJnqcxVbhJFJ6Xnchtaw8jmPSvoLXfN2gWsY9Etz5SZ";
+
+/// Kills the validator and removes its ledger when the test finishes.
+struct Validator {
+    child: Child,
+    ledger: PathBuf,
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
+
+fn program_so() -> PathBuf {
+    std::env::var_os("CHOMP_PROGRAM_SO")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../target/deploy/chomping_glass.so")
+        })
+}
+
+fn start_validator(program_id: &Pubkey) -> Option<Validator> {
+    let installed = Command::new("solana-test-validator")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !installed {
+        eprintln!("skipping: solana-test-validator is not installed");
+        return None;
+    }
+    let so = program_so();
+    if !so.exists() {
+        eprintln!("skipping: program build not found at {}", so.display());
+        return None;
+    }
+
+    let ledger = std::env::temp_dir().join(format!("chomp-ledger-{}", std::process::id()));
+    let child = Command::new("solana-test-validator")
+        .args(["--reset", "--quiet", "--rpc-port", &RPC_PORT.to_string(), "--ledger"])
+        .arg(&ledger)
+        .arg("--bpf-program")
+        .arg(program_id.to_string())
+        .arg(&so)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn solana-test-validator");
+    Some(Validator { child, ledger })
+}
+
+fn wait_healthy(rpc: &RpcClient) {
+    for _ in 0..60 {
+        if rpc.get_health().is_ok() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    panic!("validator did not become healthy");
+}
+
+fn airdrop(rpc: &RpcClient, to: &Pubkey, sol: u64) {
+    let sig = rpc.request_airdrop(to, sol * LAMPORTS_PER_SOL).expect("airdrop");
+    for _ in 0..60 {
+        if rpc.confirm_transaction(&sig).unwrap_or(false) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    panic!("airdrop to {} never confirmed", to);
+}
+
+#[test]
+fn reset_open_play_cycle() {
+    let program_id = Keypair::new().pubkey();
+    let Some(_validator) = start_validator(&program_id) else {
+        return;
+    };
+    let url = format!("http://127.0.0.1:{RPC_PORT}");
+    let admin = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+    wait_healthy(&admin);
+    let rpc = MultiRpc::new(
+        RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        None,
+    );
+
+    let payer = Keypair::new();
+    let fee_collector: Pubkey = FEE_COLLECTOR.parse().unwrap();
+    airdrop(&admin, &payer.pubkey(), 10);
+    airdrop(&admin, &fee_collector, 1);
+    let (game_pda, _) = get_game_pda(&program_id, &payer.pubkey());
+
+    reset_game_pda(&rpc, &program_id, &fee_collector, &payer, &game_pda).expect("reset");
+    assert_eq!(fetch_board(&rpc, &program_id, &game_pda).unwrap(), None);
+
+    let (r, c) = choose_move([0u8; 5]).unwrap();
+    let outcome = send_move(&rpc, &program_id, &fee_collector, &payer, &game_pda, r, c).unwrap();
+    assert!(matches!(outcome, SendOutcome::Confirmed(_)));
+    let opened = fetch_board(&rpc, &program_id, &game_pda).unwrap().expect("game account");
+    assert!(opened.iter().any(|row| *row != 0));
+    assert_eq!(opened[0] & 0xC0, 0xC0, "opening move missing from {:?}", opened);
+
+    let (r, c) = choose_move(opened).expect("move to time");
+    ping_move(&rpc, &program_id, &fee_collector, &payer, &game_pda, r, c).expect("ping-move");
+    assert_eq!(
+        fetch_board(&rpc, &program_id, &game_pda).unwrap(),
+        Some(opened),
+        "ping-move must not change the board"
+    );
+
+    let simulating = MultiRpc::new(
+        RpcClient::new_with_commitment(
+            format!("http://127.0.0.1:{RPC_PORT}"),
+            CommitmentConfig::confirmed(),
+        ),
+        None,
+    )
+    .with_simulate_first(true);
+    let balance = admin.get_balance(&payer.pubkey()).unwrap();
+    let outcome = send_move(&simulating, &program_id, &fee_collector, &payer, &game_pda, 1, 1);
+    assert!(matches!(outcome, Ok(SendOutcome::BoardAdvanced)), "{:?}", outcome);
+    assert_eq!(
+        admin.get_balance(&payer.pubkey()).unwrap(),
+        balance,
+        "a failed simulation must not be sent"
+    );
+
+    let stranger = Keypair::new().pubkey();
+    assert!(matches!(
+        fetch_board(&rpc, &stranger, &game_pda),
+        Err(SolanaError::WrongOwner { owner, .. }) if owner == program_id
+    ));
+
+    let (r, c) = choose_move(opened).expect("second move");
+    let outcome = send_move(&rpc, &program_id, &fee_collector, &payer, &game_pda, r, c).unwrap();
+    assert!(matches!(outcome, SendOutcome::Confirmed(_)));
+    if let Some(next) = fetch_board(&rpc, &program_id, &game_pda).unwrap() {
+        assert_ne!(next, opened);
+        reset_game_pda(&rpc, &program_id, &fee_collector, &payer, &game_pda).expect("cash out");
+    }
+    assert_eq!(fetch_board(&rpc, &program_id, &game_pda).unwrap(), None);
+}
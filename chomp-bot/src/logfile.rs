@@ -0,0 +1,207 @@
+//! `--log-file`: every line the console logger prints is also appended to a
+//! file, stamped with the time and, in `--keypairs` runs, the wallet whose
+//! thread logged it. The file is rotated by size so that an unattended run
+//! cannot fill the disk.
+
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use log::{Log, Metadata, Record};
+
+/// The file is rotated before a line would take it past this size.
+const MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// Rotated files kept, `<path>.1` being the newest.
+const KEEP: u32 = 3;
+
+thread_local! {
+    static CONTEXT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Tags the file lines logged by this thread with `label`, e.g. the wallet it
+/// plays for.
+// Async `--keypairs` games share runtime threads, so only the threaded one tags.
+#[cfg_attr(feature = "async", allow(dead_code))]
+pub fn set_context(label: Option<String>) {
+    CONTEXT.with(|context| *context.borrow_mut() = label);
+}
+
+/// Installs `console` as the logger, teed into `path` when one is given.
+pub fn init(console: env_logger::Logger, path: Option<&Path>) -> io::Result<()> {
+    let max_level = console.filter();
+    let logger: Box<dyn Log> = match path {
+        None => Box::new(console),
+        Some(path) => Box::new(Tee {
+            console,
+            file: Mutex::new(Rotating::open(path, MAX_BYTES)?),
+        }),
+    };
+    log::set_boxed_logger(logger).expect("the logger is installed once");
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Flushes the logger when dropped, so every way out of `main`, a Ctrl-C
+/// included, leaves the log file complete. `process::exit` skips it, so
+/// callers of that flush first.
+pub struct FlushOnExit;
+
+impl Drop for FlushOnExit {
+    fn drop(&mut self) {
+        log::logger().flush();
+    }
+}
+
+/// The console logger, with each line it accepts written to the file too.
+struct Tee {
+    console: env_logger::Logger,
+    file: Mutex<Rotating>,
+}
+
+impl Log for Tee {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.console.matches(record) {
+            return;
+        }
+        self.console.log(record);
+        let line = CONTEXT
+            .with(|context| format_line(SystemTime::now(), context.borrow().as_deref(), record));
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        // A full disk must not stop the bot; the console still has the line.
+        let _ = file.write_line(&line);
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = file.out.flush();
+    }
+}
+
+fn format_line(at: SystemTime, context: Option<&str>, record: &Record) -> String {
+    let context = context
+        .map(|label| format!(" [{}]", label))
+        .unwrap_or_default();
+    format!(
+        "{} {:<5} {}{}: {}\n",
+        humantime::format_rfc3339_millis(at),
+        record.level(),
+        record.target(),
+        context,
+        record.args()
+    )
+}
+
+/// An append-only file that moves itself to `<path>.1` once full, shifting
+/// older ones up to `<path>.KEEP` and dropping the oldest.
+struct Rotating {
+    path: PathBuf,
+    out: BufWriter<File>,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl Rotating {
+    fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Rotating {
+            path: path.to_path_buf(),
+            written: file.metadata()?.len(),
+            out: BufWriter::new(file),
+            max_bytes,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64;
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.rotate()?;
+        }
+        self.out.write_all(line.as_bytes())?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.out.flush()?;
+        for n in (1..KEEP).rev() {
+            let older = numbered(&self.path, n);
+            if older.exists() {
+                fs::rename(&older, numbered(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, numbered(&self.path, 1))?;
+        *self = Rotating::open(&self.path, self.max_bytes)?;
+        Ok(())
+    }
+}
+
+fn numbered(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn lines_carry_the_time_and_the_wallet() {
+        let at = UNIX_EPOCH + Duration::from_millis(1_500);
+        let args = format_args!("chosen: (2,3)");
+        let record = Record::builder()
+            .args(args)
+            .level(log::Level::Info)
+            .target("chomp::game")
+            .build();
+        assert_eq!(
+            format_line(at, Some("Wallet1"), &record),
+            "1970-01-01T00:00:01.500Z INFO  chomp::game [Wallet1]: chosen: (2,3)\n"
+        );
+        assert_eq!(
+            format_line(at, None, &record),
+            "1970-01-01T00:00:01.500Z INFO  chomp::game: chosen: (2,3)\n"
+        );
+    }
+
+    #[test]
+    fn full_files_rotate_and_the_oldest_is_dropped() {
+        let dir = std::env::temp_dir().join(format!("chomp-logfile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bot.log");
+        let mut file = Rotating::open(&path, 10).unwrap();
+        for line in [
+            "aaaa\n",
+            "bbbb\n",
+            "cccccccc\n",
+            "dd\n",
+            "eeee\n",
+            "ffffffff\n",
+        ] {
+            file.write_line(line).unwrap();
+        }
+        file.out.flush().unwrap();
+        let read = |n| match n {
+            0 => fs::read_to_string(&path).unwrap(),
+            n => fs::read_to_string(numbered(&path, n)).unwrap(),
+        };
+        assert_eq!(read(0), "ffffffff\n");
+        assert_eq!(read(1), "dd\neeee\n");
+        assert_eq!(read(2), "cccccccc\n");
+        assert_eq!(read(3), "aaaa\nbbbb\n");
+
+        file.write_line("gg\n").unwrap();
+        assert_eq!(read(3), "cccccccc\n");
+        assert!(!numbered(&path, 4).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
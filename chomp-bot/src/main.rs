@@ -1,171 +1,1464 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use env_logger::Env;
-use log::{info, warn};
+use log::{debug, info, warn};
 
 mod config;
+mod errors;
+mod ev;
 mod solana;
 mod game;
+mod history;
+mod interactive;
+mod logfile;
+#[cfg(feature = "async")]
+mod multiplay;
+mod notation;
+mod render;
+mod server;
+mod shutdown;
+mod teach;
+#[cfg(feature = "tui")]
+mod tui;
+mod stats;
+mod strategy;
+mod table;
+#[cfg(all(test, feature = "localnet-tests"))]
+mod localnet_tests;
 
-use crate::config::Cli;
-use crate::solana::{fetch_board, get_game_pda, reset_game_pda, send_move};
-use crate::game::{pick_any_legal, pick_forced_victory, is_glass_only};
+use crate::config::{Cli, Command, Commitment, StrategyKind};
+use crate::solana::{BoardWatch, ConfirmPolicy, Push, estimate_move_fee, fetch_board, fetch_boards, fetch_game_state, fetch_pot, get_game_pda, is_mainnet, Cluster, MultiRpc, reset_game_pda, send_move, RetryPolicy, send_moves, SendOutcome, SolanaError};
+use crate::render::OutputFormat;
+use crate::game::{
+    choose_move, is_glass_only, is_legal_move, mover_is_winning_under, winning_moves, Board, Decision, Outcome, PvLine, Rules, Seat,
+};
+use crate::stats::{Prediction, RunStats};
+use crate::strategy::Strategy;
+use crate::table::{BoardShape, PositionTable};
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::{pubkey::Pubkey, signature::{read_keypair_file, Keypair}, signer::Signer};
-use std::{thread, time::Duration};
-use clap::Parser;
+use solana_sdk::{native_token::{lamports_to_sol, sol_to_lamports}, pubkey::Pubkey, signature::{read_keypair_file, Keypair, Signature}, signer::Signer};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+/// Log target for game flow: boards, chosen moves, game ends.
+const LOG_TARGET: &str = "chomp::game";
+
+/// The RPC client every command shares, configured from the flags.
+fn build_rpc(cli: &Cli) -> MultiRpc {
+    let commitment = cli.commitment();
+    MultiRpc::new(
+        RpcClient::new_with_commitment(cli.rpc_endpoint().to_string(), commitment),
+        cli.rpc_fallback.clone().map(|url| RpcClient::new_with_commitment(url, commitment)),
+    )
+    .with_fork(cli.fork)
+    .with_account_layout(cli.account_version)
+    .with_board_layout(cli.board_layout)
+    .with_skip_preflight(cli.skip_preflight)
+    .with_preflight_commitment(cli.preflight_commitment.map(Commitment::config))
+    .with_rpc_max_retries(cli.rpc_max_retries)
+    .with_simulate_first(cli.simulate_first)
+    .with_dry_run(cli.dry_run)
+    .with_retry(RetryPolicy {
+        max_retries: cli.max_retries,
+        base: Duration::from_millis(cli.retry_base_ms),
+    })
+    .with_confirm(ConfirmPolicy {
+        timeout: Duration::from_millis(cli.confirm_timeout_ms),
+        poll: Duration::from_millis(cli.confirm_poll_ms),
+    })
+    .with_priority_fee(cli.priority_fee())
+    .with_compute_unit_limit(cli.compute_unit_limit)
+    .with_max_fee(cli.max_fee_lamports)
+}
 
 fn main() -> Result<()> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-    let cli = Cli::parse();
-    info!("starting chomp-strat-bot; autoplay={}, single-move={}", cli.autoplay, !cli.autoplay);
-
-    let program_id: Pubkey = cli.program_id.parse().context("Invalid PROGRAM_ID pubkey")?;
-    let fee_collector: Pubkey = cli.fee_collector.parse().context("Invalid FEE_COLLECTOR pubkey")?;
-    let payer_path = config::expand_home(&cli.keypair_path);
-let payer: Keypair = read_keypair_file(&payer_path)
-    .map_err(|e| anyhow::anyhow!("failed to read keypair at {}: {}", payer_path, e))?;
-    let rpc = RpcClient::new(cli.rpc_url.clone());
-    let (game_pda, _bump) = get_game_pda(&program_id, &payer.pubkey());
+    let mut cli = Cli::load().map_err(anyhow::Error::msg)?;
+    let color = cli.color_choice();
+    // The dashboard draws plain text; escape codes would show up as garbage.
+    #[cfg(feature = "tui")]
+    let color = if cli.tui { render::ColorChoice::Never } else { color };
+    render::init_color(color);
+    render::init_output(cli.output);
+    render::init_board_format(cli.board_format);
+    let mut console = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    console.write_style(color.write_style());
+    #[cfg(feature = "tui")]
+    if cli.tui {
+        console.target(env_logger::Target::Pipe(Box::new(tui::LogPipe)));
+    }
+    let console = console.build();
+    let log_file = cli.log_file.as_deref().map(config::expand_home);
+    logfile::init(console, log_file.as_deref().map(Path::new))
+        .with_context(|| format!("failed to open log file {}", log_file.as_deref().unwrap_or_default()))?;
+    let _flush = logfile::FlushOnExit;
+    info!(target: LOG_TARGET, "starting chomp-strat-bot; autoplay={}, single-move={}", cli.autoplay, !cli.autoplay);
+
+    game::set_rules(cli.rules());
+    if cli.last_move_wins {
+        warn!(target: LOG_TARGET, "--last_move_wins: solving for normal play, but the program still makes whoever eats the poison lose");
+    }
+    // The cache and the self-check cover the program's board only, so a variant is checked first.
+    let shape = cli.board_shape().map_err(anyhow::Error::msg)?;
+    if shape != BoardShape::STANDARD {
+        if cli.strategy_cache.is_some() {
+            bail!("--strategy-cache holds the {} table; it cannot be used with a {} board", BoardShape::STANDARD, shape);
+        }
+        if cli.self_check {
+            bail!("--self-check checks the {} table; it cannot be used with a {} board", BoardShape::STANDARD, shape);
+        }
+        return run_variant(shape, cli.rules());
+    }
+    if cli.no_solver {
+        game::disable_solver();
+        warn!(target: LOG_TARGET, "--no-solver: playing any legal move");
+    } else if let Some(path) = &cli.strategy_cache {
+        match game::load_strategy_cache(std::path::Path::new(path))
+            .with_context(|| format!("strategy cache {} is unusable; delete it to rebuild", path))?
+        {
+            game::CacheUse::Loaded => info!(target: LOG_TARGET, "loaded the solver table from {}", path),
+            game::CacheUse::Saved => info!(target: LOG_TARGET, "solved the table and saved it to {}", path),
+        }
+    }
+    if cli.self_check {
+        run_self_check()?;
+    }
+    if let Some(Command::Serve { addr }) = &cli.command {
+        return server::serve(addr);
+    }
+    if let Some(path) = &cli.notation {
+        let text = std::fs::read_to_string(config::expand_home(path)).with_context(|| format!("failed to read {}", path))?;
+        let game: notation::Game = text.parse().map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+        if game.shape != BoardShape::STANDARD {
+            bail!("{} is a {} game; only the program's {} board can be replayed", path, game.shape, BoardShape::STANDARD);
+        }
+        info!(target: LOG_TARGET, "read {} moves from {} (winner: {:?})", game.moves.len(), path, game.winner);
+        cli.moves = game.moves;
+    }
+    if let Some(Command::Simulate { first, second }) = &cli.command {
+        return run_simulation(&cli, *first, *second);
+    }
+    if let Some(path) = &cli.replay {
+        return run_replay(path);
+    }
+    if let Some(trials) = cli.estimate_winrate {
+        let start = game::replay(&cli.moves).map_err(anyhow::Error::msg)?;
+        for &strategy in StrategyKind::value_variants() {
+            let player = strategy::build(strategy, cli.seed);
+            let rate = game::estimate_win_rate(start, |b| player.choose(b), game::pick_random_legal, trials, cli.seed);
+            info!(target: LOG_TARGET, "{:?} vs random: {:.1}% over {} games", strategy, 100.0 * rate, trials);
+        }
+        return Ok(());
+    }
+    if cli.teach {
+        let start = game::replay(&cli.moves).map_err(anyhow::Error::msg)?;
+        teach::run(start, std::io::stdin().lock(), std::io::stdout())?;
+        return Ok(());
+    }
+    if let Some((tag, board)) = offline_position(&cli)? {
+        report_position(tag, board);
+        if cli.analyze_replies {
+            report_replies(board);
+        }
+        if let Some(path) = &cli.export_pv {
+            export_pv(path, board)?;
+        }
+        return Ok(());
+    }
+
+    let (program_id, fee_collector) = (cli.program_id, cli.fee_collector);
+    let rpc = build_rpc(&cli);
+    match rpc.cluster() {
+        Cluster::Mainnet if !cli.dry_run => {
+            let rule = "!".repeat(72);
+            warn!(target: solana::LOG_TARGET, "{}", rule);
+            warn!(target: solana::LOG_TARGET, "!! MAINNET ({}): moves and new games spend real SOL", cli.rpc_endpoint());
+            warn!(target: solana::LOG_TARGET, "!! use --network devnet or --fork to practise, or --dry-run to send nothing");
+            warn!(target: solana::LOG_TARGET, "{}", rule);
+        }
+        Cluster::Unknown => warn!(target: solana::LOG_TARGET, "cannot tell the cluster from {}; treating it as possibly mainnet", cli.rpc_endpoint()),
+        cluster => info!(target: solana::LOG_TARGET, "cluster: {:?}", cluster),
+    }
+    if let Some(Command::Pda { player: Some(player) }) = &cli.command {
+        let player: Pubkey = player.parse().context("Invalid --player pubkey")?;
+        return run_pda(&rpc, &program_id, &player);
+    }
+    if let Some(Command::ListGames { players }) = &cli.command {
+        // The fee collector may list games without a keypair of its own.
+        let payer = config::load_keypair(&cli).ok().map(|payer| payer.pubkey());
+        let players: Vec<Pubkey> = players.iter().copied().chain(payer).collect();
+        return run_list_games(&rpc, &program_id, &players);
+    }
+    if let Some(spec) = &cli.keypairs {
+        return run_wallets(&rpc, &cli, spec);
+    }
+    let payer = config::load_keypair(&cli).map_err(anyhow::Error::msg)?;
+    if let Some(Command::Pda { player: None }) = &cli.command {
+        return run_pda(&rpc, &program_id, &payer.pubkey());
+    }
+    let (derived_pda, _bump) = get_game_pda(&program_id, &payer.pubkey());
+    let game_pda: Pubkey = match &cli.game_pda {
+        Some(key) => key.parse().context("Invalid --game-pda pubkey")?,
+        None => derived_pda,
+    };
+    if game_pda != derived_pda {
+        warn!(target: LOG_TARGET,
+            "using game PDA {} (derived would be {}); the program only accepts moves on the signer's own PDA",
+            game_pda, derived_pda
+        );
+    }
+
+    if let Some(Command::PingMove { samples }) = &cli.command {
+        return run_ping_move(&rpc, &program_id, &fee_collector, &payer, &game_pda, *samples);
+    }
+    if let Some(Command::Airdrop { amount_sol }) = &cli.command {
+        return run_airdrop(&rpc, &payer.pubkey(), *amount_sol);
+    }
+
+    confirm_mainnet(&rpc, &cli)?;
+    check_start_balance(&rpc, &cli, &payer.pubkey(), &game_pda)?;
 
     if cli.reset {
         reset_game_pda(&rpc, &program_id, &fee_collector, &payer, &game_pda)?;
     }
+    if !cli.no_solver && !game::solver_ready() {
+        warn!(target: LOG_TARGET, "solver table failed to build; falling back to any legal move");
+    }
+    if let Some(Command::Interactive) = &cli.command {
+        let mut table = OnChain { rpc: &rpc, program_id: &program_id, fee_collector: &fee_collector, payer: &payer, game_pda: &game_pda, cli: &cli };
+        let strategy = strategy::build(cli.strategy, cli.seed);
+        let open = may_open_game(&rpc, &cli)?;
+        return interactive::run(&mut table, open, game::solver_ready(), |board| strategy.choose(board), std::io::stdin().lock(), std::io::stdout());
+    }
+    if let Some(path) = &cli.replay_history {
+        shutdown::install().context("failed to install the Ctrl-C handler")?;
+        return run_history_replay(&rpc, &program_id, &fee_collector, &payer, &game_pda, &cli, path);
+    }
 
     if cli.autoplay {
-        run_autoplay(&rpc, &program_id, &fee_collector, &payer, &game_pda, &cli)?;
+        shutdown::install().context("failed to install the Ctrl-C handler")?;
+        #[cfg(feature = "tui")]
+        let _dashboard = if cli.tui { Some(tui::start().context("failed to start the --tui dashboard")?) } else { None };
+        run_games(&rpc, &program_id, &fee_collector, &payer, &game_pda, &cli)?;
     } else {
-        run_single_move(&rpc, &program_id, &fee_collector, &payer, &game_pda, &cli)?;
+        let outcome = run_single_move(&rpc, &program_id, &fee_collector, &payer, &game_pda, &cli);
+        if cli.exit_code {
+            let code = match &outcome {
+                Ok(outcome) => outcome.exit_code(),
+                Err(e) if is_rpc_error(e) => {
+                    log::error!(target: LOG_TARGET, "{:#}", e);
+                    EXIT_RPC_ERROR
+                }
+                Err(_) => return outcome.map(|_| ()),
+            };
+            // `process::exit` skips `_flush`, so flush the log file by hand.
+            log::logger().flush();
+            std::process::exit(code);
+        }
+        outcome?;
     }
     Ok(())
 }
 
-fn run_autoplay(
-    rpc: &RpcClient,
+const EXIT_RPC_ERROR: i32 = 4;
+
+/// Result of a single-move invocation, reported as the exit code under `--exit-code`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SingleOutcome {
+    /// Our move (or the opening move) was confirmed.
+    MoveSent,
+    /// `--cash_out` closed the game.
+    CashedOut,
+    /// Nothing left to play: glass only, or the PDA is missing and may not be created.
+    GameOver,
+    /// No legal move was found.
+    NoMove,
+    /// The move was dropped or could not be confirmed.
+    NotConfirmed,
+}
+
+impl SingleOutcome {
+    fn exit_code(self) -> i32 {
+        match self {
+            SingleOutcome::MoveSent => 0,
+            SingleOutcome::GameOver => 2,
+            SingleOutcome::NoMove | SingleOutcome::CashedOut => 3,
+            SingleOutcome::NotConfirmed => EXIT_RPC_ERROR,
+        }
+    }
+}
+
+fn is_rpc_error(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|cause| cause.downcast_ref::<SolanaError>().is_some_and(SolanaError::is_rpc))
+}
+
+fn run_self_check() -> Result<()> {
+    let mut failed = 0;
+    for (name, passed) in game::self_check() {
+        if passed {
+            info!(target: LOG_TARGET, "self-check PASS: {}", name);
+        } else {
+            log::error!(target: LOG_TARGET, "self-check FAIL: {}", name);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("solver self-check failed ({} check(s))", failed);
+    }
+    Ok(())
+}
+
+/// `airdrop`: faucet SOL for the payer, on devnet or testnet only, where it is
+/// worth nothing.
+fn run_airdrop(rpc: &MultiRpc, payer: &Pubkey, amount_sol: f64) -> Result<()> {
+    let cluster = rpc.cluster();
+    if !matches!(cluster, Cluster::Devnet | Cluster::Testnet) {
+        bail!("airdrop only runs on devnet or testnet, not {:?}; pass --network devnet", cluster);
+    }
+    let sig = solana::airdrop(rpc, payer, sol_to_lamports(amount_sol))?;
+    info!(target: LOG_TARGET, "airdrop confirmed: {}", sig);
+    let balance = solana::check_balance(rpc, payer, 0)?;
+    info!(target: LOG_TARGET, "{} now holds {} lamports ({} SOL)", payer, balance, lamports_to_sol(balance));
+    Ok(())
+}
+
+/// `simulate`: one game between two strategies on a local board, from the
+/// empty board, under the program's rule that whoever takes the poison loses.
+fn run_simulation(cli: &Cli, first: StrategyKind, second: StrategyKind) -> Result<()> {
+    let players = [strategy::build(first, cli.seed), strategy::build(second, cli.seed.wrapping_add(1))];
+    let game = game::self_play([0u8; 5], |b| players[0].choose(b), |b| players[1].choose(b));
+    let mut board = [0u8; 5];
+    for (i, &(r, c)) in game.moves.iter().enumerate() {
+        let (seat, kind) = if i % 2 == 0 { ("first", first) } else { ("second", second) };
+        info!(target: LOG_TARGET, "ply {}: {} ({:?}) plays ({},{})", i + 1, seat, kind, r, c);
+        board = game::apply_move(board, r, c);
+    }
+    print_board("final", &board, None);
+    let (seat, kind) = if game.first_won { ("first", first) } else { ("second", second) };
+    info!(target: LOG_TARGET, "{} player ({:?}) wins after {} plies", seat, kind, game.moves.len());
+    if let Some(path) = &cli.export_notation {
+        export_notation(cli, path, game.moves)?;
+    }
+    Ok(())
+}
+
+/// `--export-notation`: `moves` from the empty board as a chomp-notation file.
+fn export_notation(cli: &Cli, path: &str, moves: Vec<(u8, u8)>) -> Result<()> {
+    let exported = notation::Game::new(BoardShape::STANDARD, Some(cli.program_id), moves);
+    std::fs::write(config::expand_home(path), exported.to_string()).with_context(|| format!("failed to write {}", path))?;
+    info!(target: LOG_TARGET, "wrote the game to {}", path);
+    Ok(())
+}
+
+/// `--rows`/`--cols` other than 5x8: solve that board and report its opening.
+/// The program only plays 5x8, so this is all such a run does.
+fn run_variant(shape: BoardShape, rules: Rules) -> Result<()> {
+    let started = Instant::now();
+    let table = PositionTable::solve(shape, rules);
+    info!(target: LOG_TARGET, "solved the {} board in {:?}", shape, started.elapsed());
+    let empty = vec![0u8; usize::from(shape.rows())];
+    let outcome = if table.is_winning(&empty) { Outcome::Winning } else { Outcome::Losing };
+    let openings: Vec<(u8, u8)> = table
+        .all_winning_replies(&empty)
+        .into_iter()
+        .map(|(r, c)| (r as u8 + 1, c as u8))
+        .collect();
+    info!(target: LOG_TARGET, "poison at ({},{}); player to move on the empty board is {:?}", shape.rows(), shape.cols(), outcome);
+    info!(target: LOG_TARGET, "winning openings: {:?}", openings);
+    info!(target: LOG_TARGET, "perfect play lasts {} plies", table.depths()[shape.encode(&empty)]);
+    Ok(())
+}
+
+/// The position for the offline report, if one was asked for: a saved board
+/// (`--analyze`), a typed board (`--board`), a skyline (`--skyline`), a replayed move list (`--moves`),
+/// or the empty board when only `--export-pv` is given.
+fn offline_position(cli: &Cli) -> Result<Option<(&'static str, Board)>> {
+    if let Some(path) = &cli.analyze {
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read board file {}", path))?;
+        let board = Board::from_bincode(&bytes).with_context(|| format!("{} is not a saved board", path))?;
+        return Ok(Some(("saved", board)));
+    }
+    if let Some(rows) = cli.board {
+        return Ok(Some(("typed", Board::from_rows(rows))));
+    }
+    if let Some(skyline) = cli.skyline {
+        return Ok(Some(("skyline", Board::from_rows(skyline.to_board()))));
+    }
+    if !cli.moves.is_empty() {
+        let board = game::replay(&cli.moves).map_err(anyhow::Error::msg)?;
+        info!(target: LOG_TARGET, "replayed {} moves", cli.moves.len());
+        return Ok(Some(("replayed", Board::from_rows(board))));
+    }
+    if cli.export_pv.is_some() {
+        return Ok(Some(("start", Board::from_rows([0u8; 5]))));
+    }
+    Ok(None)
+}
+
+fn export_pv(path: &str, board: Board) -> Result<()> {
+    let line = PvLine::from_board(board.rows());
+    let json = serde_json::to_string_pretty(&line)?;
+    std::fs::write(path, json).with_context(|| format!("failed to write {}", path))?;
+    info!(target: LOG_TARGET, "wrote {}-ply principal variation to {}", line.plies.len(), path);
+    Ok(())
+}
+
+/// Steps through a line written by `--export-pv`, one board per ply.
+fn run_replay(path: &str) -> Result<()> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+    let line: PvLine = serde_json::from_str(&text).with_context(|| format!("{} is not an exported line", path))?;
+    line.verify().map_err(anyhow::Error::msg)?;
+    print_board("start", &line.start.rows(), None);
+    for (i, ply) in line.plies.iter().enumerate() {
+        info!(target: LOG_TARGET, "ply {}: ({},{}) by the {:?} side", i + 1, ply.mv.0, ply.mv.1, ply.classification);
+        print_board(&format!("after ply {}", i + 1), &ply.board.rows(), None);
+    }
+    Ok(())
+}
+
+/// `--replay-history`: sends the moves of a `--history-file` again, in order,
+/// from a fresh game, e.g. to reproduce a sequence on devnet. The program's
+/// replies can differ from the recorded ones, so each move is checked against
+/// the board read now, and the replay stops at the first one that no longer
+/// fits. Under `--dry-run` nothing lands, so the recorded boards are used.
+fn run_history_replay(
+    rpc: &MultiRpc,
     program_id: &Pubkey,
     fee_collector: &Pubkey,
     payer: &Keypair,
     game_pda: &Pubkey,
     cli: &Cli,
+    path: &str,
+) -> Result<()> {
+    let text = std::fs::read_to_string(config::expand_home(path)).with_context(|| format!("failed to read {}", path))?;
+    let entries = history::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    if !cli.dry_run && fetch_board(rpc, program_id, game_pda)?.is_some() {
+        bail!("{} already holds a game; pass --reset to cash it out before replaying", game_pda);
+    }
+    info!(target: LOG_TARGET, "replaying {} moves from {}", entries.len(), path);
+    for (i, entry) in entries.iter().enumerate() {
+        if shutdown::requested() {
+            info!(target: LOG_TARGET, "Ctrl-C — stopping the replay after {} moves.", i);
+            break;
+        }
+        let board = if cli.dry_run { entry.board } else { fetch_board(rpc, program_id, game_pda)?.unwrap_or([0u8; 5]) };
+        if board != entry.board {
+            warn!(target: LOG_TARGET, "move {}: the board is {:02x?}, the history had {:02x?}", i + 1, board, entry.board);
+        }
+        let (r, c) = entry.mv;
+        if !is_legal_move(board, r, c) {
+            bail!("move {} ({},{}) is not legal on the current board; the replay diverged from {}", i + 1, r, c, entry.signature);
+        }
+        info!(target: LOG_TARGET, "move {}/{}: ({},{}), first sent as {}", i + 1, entries.len(), r, c, entry.signature);
+        match send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)? {
+            SendOutcome::Confirmed(sig) => record_move(cli, r, c, &sig, &board)?,
+            SendOutcome::DryRun(_) => {}
+            SendOutcome::Dropped | SendOutcome::BoardAdvanced => bail!("move {} ({},{}) did not land", i + 1, r, c),
+            SendOutcome::Unknown(sig) => bail!("cannot tell whether move {} ({}) landed — stopping", i + 1, sig),
+        }
+        shutdown::sleep_ms(cli.interval_ms);
+    }
+    Ok(())
+}
+
+/// `interactive` against the chain: the board is the game PDA's, and each
+/// move is sent and recorded as a single move would be.
+struct OnChain<'a> {
+    rpc: &'a MultiRpc,
+    program_id: &'a Pubkey,
+    fee_collector: &'a Pubkey,
+    payer: &'a Keypair,
+    game_pda: &'a Pubkey,
+    cli: &'a Cli,
+}
+
+impl interactive::Table for OnChain<'_> {
+    fn board(&mut self) -> Result<Option<[u8; 5]>> {
+        Ok(fetch_board(self.rpc, self.program_id, self.game_pda)?)
+    }
+
+    fn play(&mut self, board: [u8; 5], (r, c): (u8, u8)) -> Result<bool> {
+        info!(target: LOG_TARGET, "sending ({},{})", r, c);
+        match send_move(self.rpc, self.program_id, self.fee_collector, self.payer, self.game_pda, r, c)? {
+            SendOutcome::Confirmed(sig) => record_move(self.cli, r, c, &sig, &board)?,
+            SendOutcome::DryRun(_) => {
+                info!(target: LOG_TARGET, "dry run: the board cannot change, so the session ends here");
+                return Ok(false);
+            }
+            SendOutcome::Dropped | SendOutcome::BoardAdvanced => {
+                warn!(target: LOG_TARGET, "({},{}) did not land; reading the board again", r, c)
+            }
+            SendOutcome::Unknown(sig) => bail!("cannot tell whether ({},{}) landed ({}) — stopping", r, c, sig),
+        }
+        Ok(true)
+    }
+}
+
+/// Offline report for `--analyze`, `--board`, `--moves` and `--export-pv`: the board with our
+/// chosen move, who is winning, how long perfect play lasts and every winning move.
+fn report_position(tag: &str, board: Board) {
+    let rows = board.rows();
+    print_board(tag, &rows, choose_move(rows));
+    debug!(target: LOG_TARGET, "skyline: {}", board.skyline());
+    info!(target: LOG_TARGET, "player to move is {:?}", game::classify(rows));
+    match game::classify(rows) {
+        Outcome::Winning => info!(target: LOG_TARGET, "forced win within {} plies", game::depth(rows)),
+        Outcome::Losing => info!(target: LOG_TARGET, "lost against perfect play; the longest defence lasts {} plies", game::depth(rows)),
+    }
+    info!(target: LOG_TARGET, "winning moves: {:?}", winning_moves(rows));
+    info!(target: LOG_TARGET, "squares left (poison aside): {}", game::moves_remaining(rows));
+}
+
+/// `pda`: the game PDA and bump for `player`, and the board if the account exists.
+fn run_pda(rpc: &MultiRpc, program_id: &Pubkey, player: &Pubkey) -> Result<()> {
+    let (pda, bump) = get_game_pda(program_id, player);
+    println!("player:   {}", player);
+    println!("game PDA: {} (bump {})", pda, bump);
+    match fetch_board(rpc, program_id, &pda)? {
+        Some(board) => {
+            println!("account:  exists");
+            print!("{}", render::format_board(&board, None));
+        }
+        None => println!("account:  missing (no game in progress)"),
+    }
+    Ok(())
+}
+
+/// `list-games`: every open game of `program_id`. `getProgramAccounts` only
+/// returns the PDAs, which cannot be inverted, so a player is named only when
+/// one of `players` derives the PDA.
+fn run_list_games(rpc: &MultiRpc, program_id: &Pubkey, players: &[Pubkey]) -> Result<()> {
+    let games = solana::list_games(rpc, program_id)?;
+    for (pda, state) in &games {
+        let player = players.iter().find(|player| get_game_pda(program_id, player).0 == *pda);
+        match render::output_format() {
+            OutputFormat::Text => {
+                println!("game PDA: {}", pda);
+                match player {
+                    Some(player) => println!("player:   {}", player),
+                    None => println!("player:   unknown"),
+                }
+                println!("stake:    {} lamports", state.stake);
+                print!("{}", render::format_board(&state.board, None));
+            }
+            OutputFormat::Json => {
+                let player = player.map(ToString::to_string);
+                println!("{}", render::game_record(&pda.to_string(), player.as_deref(), &state.board, state.stake));
+            }
+        }
+    }
+    info!(target: LOG_TARGET, "{} open games under {}", games.len(), program_id);
+    Ok(())
+}
+
+/// `ping-move`: simulates our next move `samples` times and reports how long
+/// each phase took. Nothing is sent, so the board is left as it is.
+fn run_ping_move(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    game_pda: &Pubkey,
+    samples: u32,
 ) -> Result<()> {
-    info!(
-        "Autoplay ON (interval={}ms, max_moves={}, last_move_wins={}, reset={}, init_if_missing={})",
-        cli.interval_ms, cli.max_moves, cli.last_move_wins, cli.reset, cli.init_if_missing
+    let board = fetch_board(rpc, program_id, game_pda)?.unwrap_or([0u8; 5]);
+    let (r, c) = choose_move(board).unwrap_or((0, 0));
+    info!(target: LOG_TARGET, "timing move ({},{}) over {} samples (simulate only)", r, c, samples);
+    let mut phases: [(&str, Vec<Duration>); 3] = [("get_latest_blockhash", vec![]), ("build+sign", vec![]), ("simulate", vec![])];
+    for _ in 0..samples {
+        let t = solana::ping_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
+        phases[0].1.push(t.blockhash);
+        phases[1].1.push(t.sign);
+        phases[2].1.push(t.simulate);
+    }
+    for (name, times) in &phases {
+        let ms = |p| stats::nearest_rank(times, p).unwrap_or_default().as_secs_f64() * 1000.0;
+        info!(target: LOG_TARGET, "{:<20} min {:>7.1}ms  p50 {:>7.1}ms  max {:>7.1}ms", name, ms(0), ms(50), ms(100));
+    }
+    Ok(())
+}
+
+/// `--analyze-replies`: after our chosen move, what each opponent reply leaves us with.
+fn report_replies(board: Board) {
+    let rows = board.rows();
+    let Some((r, c)) = choose_move(rows) else {
+        return;
+    };
+    let after = game::apply_move(rows, r, c);
+    let replies = game::opponent_reply_analysis(after);
+    info!(target: LOG_TARGET, "after our ({},{}) the opponent has {} replies:", r, c, replies.len());
+    for ((r, c), us) in replies {
+        info!(target: LOG_TARGET, "  ({},{}) leaves us {:?}", r, c, us);
+    }
+}
+
+fn save_board(cli: &Cli, board: [u8; 5]) -> Result<()> {
+    if let Some(path) = &cli.save_board {
+        let bytes = Board::from_rows(board).to_bincode()?;
+        std::fs::write(path, bytes).with_context(|| format!("failed to save board to {}", path))?;
+    }
+    Ok(())
+}
+
+/// Why a single autoplay game stopped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum GameEnd {
+    /// The program closed the PDA after one of our moves: somebody won.
+    Closed,
+    /// The PDA we had been playing on closed without a move from us in
+    /// between, so someone else cashed out or claimed the pot.
+    EndedByOpponent,
+    GlassOnly,
+    MaxMoves,
+    TimeBudget,
+    NoMove,
+    /// The PDA is missing and `init_if_missing` is off.
+    Missing,
+    /// A move could not be confirmed either way.
+    Unconfirmed,
+    /// Ctrl-C was pressed; the move in flight, if any, was finished first.
+    Interrupted,
+    /// `--ev-play` found cashing out worth more than playing on.
+    CashedOut,
+    /// `--dry-run` built our move without sending it, so the board cannot move on.
+    DryRun,
+    /// The program kept refusing our move on a board that had not changed.
+    Refused,
+}
+
+/// How many `BoardAdvanced` refusals in a row, each on an unchanged board,
+/// end a game instead of spending more fees on the same move.
+const MAX_REFUSED_MOVES: u32 = 3;
+
+impl GameEnd {
+    /// Whether another game may safely be started after this one.
+    fn can_continue(self) -> bool {
+        !matches!(
+            self,
+            GameEnd::Missing
+                | GameEnd::Unconfirmed
+                | GameEnd::TimeBudget
+                | GameEnd::Interrupted
+                | GameEnd::DryRun
+                | GameEnd::Refused
+        )
+    }
+}
+
+/// How one autoplay game ended and the board left behind.
+struct GameResult {
+    end: GameEnd,
+    /// `None` once the program has closed the account.
+    board: Option<[u8; 5]>,
+    /// Known only when the game was decided: the program closed the account
+    /// after a move of ours that was not the poison, or we were left with glass.
+    won: Option<bool>,
+    /// Every move from the empty board, ours and the program's replies, for
+    /// `--export-notation`. `None` when the game was joined part-way through
+    /// or a reply could not be read off the board.
+    moves: Option<Vec<(u8, u8)>>,
+}
+
+/// `--human-delay`: a random pause before each autoplay move, drawn from the
+/// `--seed` RNG. Each game gets its own stream so a run is reproducible.
+struct HumanDelay {
+    min_ms: u64,
+    max_ms: u64,
+    rng: StdRng,
+}
+
+impl HumanDelay {
+    fn for_game(cli: &Cli, game: u32) -> Option<Self> {
+        let (min_ms, max_ms) = cli.human_delay()?;
+        Some(HumanDelay { min_ms, max_ms, rng: StdRng::seed_from_u64(cli.seed.wrapping_add(u64::from(game))) })
+    }
+
+    /// Sleeps for a random time in range (cut short by Ctrl-C) and returns it.
+    fn pause(&mut self) -> Duration {
+        let ms = self.rng.gen_range(self.min_ms..=self.max_ms);
+        debug!(target: LOG_TARGET, "human delay: {}ms", ms);
+        let started = Instant::now();
+        shutdown::sleep_ms(ms);
+        started.elapsed()
+    }
+}
+
+/// Contents of `--final-board-file`.
+#[derive(Serialize)]
+struct FinalReport {
+    end: String,
+    board: Option<Board>,
+    classification: Option<Outcome>,
+    glass_only: bool,
+    won: Option<bool>,
+}
+
+impl FinalReport {
+    fn new(result: &GameResult) -> Self {
+        FinalReport {
+            end: format!("{:?}", result.end),
+            board: result.board.map(Board::from_rows),
+            classification: result.board.filter(|_| game::solver_ready()).map(game::classify),
+            glass_only: result.board.is_some_and(is_glass_only),
+            won: result.won,
+        }
+    }
+}
+
+/// Plays `--loop-games` games back to back (0 = until interrupted), resetting
+/// any leftover PDA between games so each one starts from an empty board.
+fn run_games(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    game_pda: &Pubkey,
+    cli: &Cli,
+) -> Result<RunStats> {
+    info!(target: LOG_TARGET,
+        "Autoplay ON (interval={}ms, max_moves={}, last_move_wins={}, reset={}, init_if_missing={}, loop_games={})",
+        cli.interval_ms, cli.max_moves, cli.last_move_wins, cli.reset, cli.init_if_missing, cli.loop_games
     );
 
+    let mut watch = cli.ws.as_deref().and_then(|url| match BoardWatch::subscribe(url, rpc, program_id, game_pda) {
+        Ok(watch) => Some(watch),
+        Err(e) => {
+            warn!(target: LOG_TARGET, "cannot subscribe over {}: {}; polling every {}ms instead", url, e, cli.interval_ms);
+            None
+        }
+    });
+    let mut stats = RunStats::default();
+    let spent_before = rpc.lamports_spent();
+    let last = loop {
+        if cli.loop_games != 1 {
+            info!(target: LOG_TARGET, "=== game {} ===", stats.games + 1);
+        }
+        let result = run_autoplay(rpc, program_id, fee_collector, payer, game_pda, cli, &mut watch, &mut stats)?;
+        let end = result.end;
+        stats.lamports_spent = rpc.lamports_spent() - spent_before;
+        stats.record_game(matches!(
+            end,
+            GameEnd::Closed | GameEnd::EndedByOpponent | GameEnd::GlassOnly | GameEnd::CashedOut
+        ));
+        info!(target: LOG_TARGET, "game ended ({:?}) for {}; totals: {}", end, payer.pubkey(), stats);
+        if let Some(path) = &cli.export_notation {
+            match &result.moves {
+                Some(moves) => export_notation(cli, path, moves.clone())?,
+                None => warn!(target: LOG_TARGET, "not writing {}: the game was not watched from its first move", path),
+            }
+        }
+
+        if !end.can_continue() || (cli.loop_games != 0 && stats.games >= cli.loop_games) {
+            break result;
+        }
+        if stats.over_budget(cli.time_budget()) {
+            info!(target: LOG_TARGET, "time budget reached.");
+            break result;
+        }
+        if fetch_board(rpc, program_id, game_pda)?.is_some() {
+            reset_game_pda(rpc, program_id, fee_collector, payer, game_pda)?;
+        }
+        shutdown::sleep_ms(cli.interval_ms);
+        if shutdown::requested() {
+            info!(target: LOG_TARGET, "Ctrl-C — not starting another game.");
+            break result;
+        }
+    };
+    stats.lamports_spent = rpc.lamports_spent() - spent_before;
+    info!(target: LOG_TARGET, "run summary: {}", stats);
+    info!(target: LOG_TARGET, "{}", stats.latency_report());
+    if render::output_format() == OutputFormat::Json {
+        println!("{}", render::summary_record(&stats));
+    }
+    if let Some(path) = &cli.latency_samples {
+        stats
+            .write_latencies(std::path::Path::new(path))
+            .with_context(|| format!("failed to write latency samples to {}", path))?;
+        info!(target: LOG_TARGET, "wrote {} latency samples to {}", stats.move_latencies.len(), path);
+    }
+    if let Some(path) = &cli.final_board_file {
+        let json = serde_json::to_string_pretty(&FinalReport::new(&last))?;
+        std::fs::write(path, json).with_context(|| format!("failed to write final board to {}", path))?;
+        info!(target: LOG_TARGET, "wrote final board to {}", path);
+    }
+    Ok(stats)
+}
+
+/// `--keypairs`: an autoplay run per wallet, each on its own derived PDA, at
+/// most `--max-concurrent` at a time: on a thread and RPC client per wallet,
+/// or as tasks on the nonblocking client under the `async` feature. The
+/// solver table is global, so every game reads the same one.
+fn run_wallets(rpc: &MultiRpc, cli: &Cli, spec: &str) -> Result<()> {
+    if cli.game_pda.is_some() || cli.save_board.is_some() || cli.final_board_file.is_some() || cli.latency_samples.is_some() || cli.history_file.is_some() || cli.export_notation.is_some() {
+        bail!("--keypairs plays one PDA per wallet, so it cannot be combined with --game-pda, --save-board, --final-board-file, --latency-samples, --history-file or --export-notation");
+    }
+    let paths = config::keypair_files(spec).with_context(|| format!("failed to list keypairs in {}", spec))?;
+    if paths.is_empty() {
+        bail!("no keypair files match {}", spec);
+    }
+    let payers = paths
+        .iter()
+        .map(|path| read_keypair_file(path).map_err(|e| anyhow::anyhow!("failed to read keypair at {}: {}", path.display(), e)))
+        .collect::<Result<Vec<Keypair>>>()?;
+    confirm_mainnet(rpc, cli)?;
+    shutdown::install().context("failed to install the Ctrl-C handler")?;
+    let workers = payers.len().min(cli.max_concurrent as usize);
+    info!(target: LOG_TARGET, "playing {} wallets, {} at a time", payers.len(), workers);
+
+    #[cfg(feature = "async")]
+    let results = play_wallets_async(rpc, cli, payers, workers)?;
+    #[cfg(not(feature = "async"))]
+    let results = play_wallets(cli, &payers, workers);
+
+    let mut combined = RunStats::default();
+    let mut failed = 0;
+    // Every final board in one round trip rather than a fetch per wallet.
+    let pdas: Vec<Pubkey> = results.iter().map(|(wallet, _)| get_game_pda(&cli.program_id, wallet).0).collect();
+    match fetch_boards(rpc, &cli.program_id, &pdas) {
+        Ok(boards) => {
+            for ((wallet, _), board) in results.iter().zip(boards) {
+                match board {
+                    Some(board) => info!(target: LOG_TARGET, "{}: final board {:02x?}", wallet, board),
+                    None => info!(target: LOG_TARGET, "{}: no game account left", wallet),
+                }
+            }
+        }
+        Err(e) => warn!(target: LOG_TARGET, "cannot read the final boards: {}", e),
+    }
+    for (wallet, outcome) in &results {
+        match outcome {
+            Ok(stats) => {
+                info!(target: LOG_TARGET, "{}: {}", wallet, stats);
+                combined.absorb(stats);
+            }
+            Err(e) => {
+                warn!(target: LOG_TARGET, "{}: stopped on error: {:#}", wallet, e);
+                failed += 1;
+            }
+        }
+    }
+    info!(target: LOG_TARGET, "combined over {} wallets: {}", results.len(), combined);
+    info!(target: LOG_TARGET, "{}", combined.latency_report());
+    if failed > 0 {
+        bail!("{} of {} wallets stopped on an error", failed, results.len());
+    }
+    Ok(())
+}
+
+/// `--keypairs` on `workers` threads, each taking the next wallet in turn.
+#[cfg(not(feature = "async"))]
+fn play_wallets(cli: &Cli, payers: &[Keypair], workers: usize) -> Vec<(Pubkey, Result<RunStats>)> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(payers.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(payer) = payers.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if shutdown::requested() {
+                        break;
+                    }
+                    logfile::set_context(Some(payer.pubkey().to_string()));
+                    let outcome = play_wallet(cli, payer);
+                    results.lock().unwrap().push((payer.pubkey(), outcome));
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// `--keypairs` as `multiplay` tasks on a tokio runtime. Each wallet plays one
+/// game with the move loop's basic flags, so the autoplay extras are refused.
+#[cfg(feature = "async")]
+fn play_wallets_async(rpc: &MultiRpc, cli: &Cli, payers: Vec<Keypair>, workers: usize) -> Result<Vec<(Pubkey, Result<RunStats>)>> {
+    if cli.reset || cli.cash_out || cli.ev_play || cli.loop_games != 1 || cli.ws.is_some() || cli.human_delay.is_some() {
+        bail!("under the async feature --keypairs plays one plain game per wallet, so it cannot be combined with --reset, --cash_out, --ev-play, --loop-games, --ws or --human-delay");
+    }
+    let limits = multiplay::Limits {
+        interval: Duration::from_millis(cli.interval_ms),
+        max_moves: cli.max_moves,
+        open_games: may_open_game(rpc, cli)?,
+        max_concurrent: workers,
+        strategy: cli.strategy,
+        seed: cli.seed,
+    };
+    // A wallet that cannot pay is reported as failed, and the rest still play.
+    let mut results = Vec::with_capacity(payers.len());
+    let mut funded = Vec::with_capacity(payers.len());
+    for payer in payers {
+        let (game_pda, _) = get_game_pda(&cli.program_id, &payer.pubkey());
+        match check_start_balance(rpc, cli, &payer.pubkey(), &game_pda) {
+            Ok(()) => funded.push(payer),
+            Err(e) => results.push((payer.pubkey(), Err(e))),
+        }
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    let played = runtime.block_on(multiplay::run(
+        solana::nonblocking::AsyncRpc::from_blocking(rpc),
+        cli.program_id,
+        cli.fee_collector,
+        funded,
+        limits,
+    ));
+    results.extend(played.into_iter().map(|(wallet, stats)| (wallet, stats.map_err(anyhow::Error::from))));
+    Ok(results)
+}
+
+/// One wallet's `--keypairs` run: reset if asked, then `run_games`.
+#[cfg(not(feature = "async"))]
+fn play_wallet(cli: &Cli, payer: &Keypair) -> Result<RunStats> {
+    let rpc = build_rpc(cli);
+    let (program_id, fee_collector) = (cli.program_id, cli.fee_collector);
+    let (game_pda, _) = get_game_pda(&program_id, &payer.pubkey());
+    info!(target: LOG_TARGET, "{}: playing on {}", payer.pubkey(), game_pda);
+    check_start_balance(&rpc, cli, &payer.pubkey(), &game_pda)?;
+    if cli.reset {
+        reset_game_pda(&rpc, &program_id, &fee_collector, payer, &game_pda)?;
+    }
+    run_games(&rpc, &program_id, &fee_collector, payer, &game_pda, cli)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_autoplay(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    game_pda: &Pubkey,
+    cli: &Cli,
+    watch: &mut Option<BoardWatch>,
+    stats: &mut RunStats,
+) -> Result<GameResult> {
     let mut moves_sent = 0u32;
-    loop {
-        match fetch_board(rpc, game_pda)? {
+    let mut last_move: Option<(u8, u8)> = None;
+    let mut delay = HumanDelay::for_game(cli, stats.games);
+    let strategy = strategy::build(cli.strategy, cli.seed.wrapping_add(u64::from(stats.games)));
+    // What `apply_move` says the board should hold after our last confirmed move.
+    let mut predicted: Option<[u8; 5]> = None;
+    let mut seat: Option<Seat> = None;
+    // Whether the account was seen this game, and whether our last send landed,
+    // to tell a close caused by our move from one caused by somebody else.
+    let mut seen_account = false;
+    let mut just_moved = false;
+    // A board `--ws` pushed while we waited, read instead of fetching.
+    let mut pushed: Option<Option<[u8; 5]>> = None;
+    // `BoardAdvanced` refusals in a row on a board that had not moved on.
+    let mut refused = 0u32;
+    let mut moves: Option<Vec<(u8, u8)>> = None;
+    let end = loop {
+        if shutdown::requested() {
+            info!(target: LOG_TARGET, "Ctrl-C — stopping autoplay.");
+            break GameEnd::Interrupted;
+        }
+        let turn_started = Instant::now();
+        // A push carries no lamports, so the stake is only known after a fetch.
+        let (read, stake) = match pushed.take() {
+            Some(read) => (read, None),
+            None => match fetch_game_state(rpc, program_id, game_pda)? {
+                Some(state) => (Some(state.board), Some(state)),
+                None => (None, None),
+            },
+        };
+        match read {
             Some(board) => {
-                print_board("board", &board);
+                // Moves leave the lamports alone, so the first read of a game tells the stake.
+                if let (false, Some(state)) = (seen_account, stake) {
+                    info!(target: LOG_TARGET, "stake: {} lamports, {} squares eaten", state.stake, state.eaten());
+                }
+                seen_account = true;
+                just_moved = false;
+                if seat.is_none() {
+                    // Only a game watched from its empty board can be written out move by move.
+                    moves = (board == [0u8; 5]).then(Vec::new);
+                }
+                if let Some(expected) = predicted.take() {
+                    // The program's reply is whatever turned our predicted board into this one.
+                    if board != expected {
+                        moves = moves.and_then(|mut moves| {
+                            moves.push(game::move_between(expected, board)?);
+                            Some(moves)
+                        });
+                    }
+                    let prediction = Prediction::compare(expected, board);
+                    stats.record_prediction(prediction);
+                    if prediction == Prediction::Mismatch {
+                        warn!(target: LOG_TARGET, "board after our move is missing it: predicted {:02x?}, read {:02x?}", expected, board);
+                    }
+                }
+                save_board(cli, board)?;
                 if is_glass_only(board) {
-                    info!("Only glass remains — game over.");
-                    break;
+                    print_board("board", &board, None);
+                    info!(target: LOG_TARGET, "Only glass remains — game over.");
+                    break GameEnd::GlassOnly;
                 }
 
-                let (r, c) = pick_forced_victory(board)
-                    .or_else(|| pick_any_legal(board))
-                    .unwrap_or((0, 0));
-                info!("chosen: ({},{})", r, c);
-                if r == 0 && c == 0 {
-                    info!("No safe move — stopping.");
-                    break;
+                let seat = *seat.get_or_insert_with(|| announce_seat(Seat::from_board(board)));
+                if cli.ev_play && game::solver_ready() {
+                    let pot = match stake {
+                        Some(state) => state.stake,
+                        None => fetch_pot(rpc, game_pda)?,
+                    };
+                    let fee = estimate_move_fee(rpc, program_id, fee_collector, &payer.pubkey(), game_pda)?;
+                    let position = ev::Position::read(board, pot, fee);
+                    info!(target: LOG_TARGET,
+                        "ev: pot={} fee/move={} moves left={} play={} cash-out={}",
+                        pot, fee, position.moves_left, position.play_value(), position.cash_out_value()
+                    );
+                    if let ev::Call::CashOut(reason) = ev::decide(&position) {
+                        info!(target: LOG_TARGET, "ev: cashing out — {}", reason);
+                        reset_game_pda(rpc, program_id, fee_collector, payer, game_pda)?;
+                        break GameEnd::CashedOut;
+                    }
+                }
+                let decision = game::decide(board, cli.cash_out, || pick_move(cli, &*strategy, seat, board));
+                if !cli.quiet {
+                    print_board("board", &board, decision.square());
+                }
+                let (r, c) = match decision {
+                    Decision::Move(r, c) => (r, c),
+                    Decision::CashOut => {
+                        info!(target: LOG_TARGET, "cash-out requested");
+                        reset_game_pda(rpc, program_id, fee_collector, payer, game_pda)?;
+                        break GameEnd::CashedOut;
+                    }
+                    Decision::NoMove => {
+                        info!(target: LOG_TARGET, "No legal move — stopping.");
+                        break GameEnd::NoMove;
+                    }
+                };
+                info!(target: LOG_TARGET, "chosen: ({},{})", r, c);
+                // Latency stats measure the RPC, so the deliberate pause is left out.
+                let paused = delay.as_mut().map(HumanDelay::pause).unwrap_or_default();
+                if shutdown::requested() {
+                    info!(target: LOG_TARGET, "Ctrl-C — stopping before sending.");
+                    break GameEnd::Interrupted;
                 }
 
-                send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
+                if let Some(watch) = watch {
+                    watch.drain();
+                }
+                let sent = match cli.min_balance_lamports {
+                    Some(min) if !cli.dry_run => solana::check_balance(rpc, &payer.pubkey(), min)
+                        .and_then(|_| send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)),
+                    _ => send_move(rpc, program_id, fee_collector, payer, game_pda, r, c),
+                };
+                let outcome = match sent {
+                    Err(e @ (SolanaError::FeeAboveCap { .. } | SolanaError::LowBalance { .. })) => {
+                        warn!(target: LOG_TARGET, "{} — waiting {} ms before trying again", e, cli.interval_ms);
+                        shutdown::sleep_ms(cli.interval_ms);
+                        continue;
+                    }
+                    sent => sent?,
+                };
+                match outcome {
+                    SendOutcome::Confirmed(sig) => {
+                        record_move(cli, r, c, &sig, &board)?;
+                        if let Some(moves) = moves.as_mut() {
+                            moves.push((r, c));
+                        }
+                        just_moved = true;
+                        refused = 0;
+                        last_move = Some((r, c));
+                        predicted = Some(game::apply_move(board, r, c));
+                    }
+                    SendOutcome::Dropped => {
+                        info!(target: LOG_TARGET, "re-reading the board before trying again");
+                        continue;
+                    }
+                    SendOutcome::BoardAdvanced => {
+                        let fresh = fetch_board(rpc, program_id, game_pda)?;
+                        if fresh != Some(board) {
+                            info!(target: LOG_TARGET, "the board moved on — choosing again");
+                            pushed = Some(fresh);
+                            continue;
+                        }
+                        // The board we chose on still stands, so a quick retry would be refused too.
+                        refused += 1;
+                        moves_sent += 1;
+                        if refused >= MAX_REFUSED_MOVES {
+                            warn!(target: LOG_TARGET, "({},{}) refused {} times on an unchanged board — stopping", r, c, refused);
+                            break GameEnd::Refused;
+                        }
+                        if moves_sent >= cli.max_moves {
+                            warn!(target: LOG_TARGET, "Reached max_moves={} — stopping.", cli.max_moves);
+                            break GameEnd::MaxMoves;
+                        }
+                        warn!(target: LOG_TARGET, "board unchanged after the refusal — waiting {} ms before trying again", cli.interval_ms);
+                        shutdown::sleep_ms(cli.interval_ms);
+                        continue;
+                    }
+                    SendOutcome::Unknown(sig) => {
+                        warn!(target: LOG_TARGET, "cannot tell whether {} landed — stopping to avoid a double move", sig);
+                        break GameEnd::Unconfirmed;
+                    }
+                    SendOutcome::DryRun(_) => break GameEnd::DryRun,
+                }
                 moves_sent += 1;
+                stats.record_move(turn_started.elapsed().saturating_sub(paused), is_forced_win(board, r, c));
                 if moves_sent >= cli.max_moves {
-                    warn!("Reached max_moves={} — stopping.", cli.max_moves);
-                    break;
+                    warn!(target: LOG_TARGET, "Reached max_moves={} — stopping.", cli.max_moves);
+                    break GameEnd::MaxMoves;
+                }
+                if stats.over_budget(cli.time_budget()) {
+                    info!(target: LOG_TARGET, "time budget reached.");
+                    break GameEnd::TimeBudget;
+                }
+                pushed = next_turn(watch, cli.interval_ms)?;
+            }
+            None if seen_account && !just_moved => {
+                warn!(target: LOG_TARGET, "game account closed while we were not moving — game ended by the other side.");
+                break GameEnd::EndedByOpponent;
+            }
+            None if seat.is_some() => {
+                info!(target: LOG_TARGET, "game account closed — game finished.");
+                // Unless we took the poison ourselves, the program's closing reply took it.
+                if let Some(moves) = moves.as_mut().filter(|_| last_move != Some((5, 8))) {
+                    moves.push((5, 8));
                 }
-                thread::sleep(Duration::from_millis(cli.interval_ms));
+                break GameEnd::Closed;
             }
             None => {
-                if !cli.init_if_missing {
-                    warn!("PDA missing — stopping autoplay");
-                    break;
+                if !may_open_game(rpc, cli)? {
+                    warn!(target: LOG_TARGET, "PDA missing — stopping autoplay");
+                    break GameEnd::Missing;
                 }
-                info!("No PDA found — starting a NEW game by making the first move.");
-                let empty = [0u8; 5];
-                let (r, c) = pick_forced_victory(empty)
-                    .or_else(|| pick_any_legal(empty))
-                    .unwrap_or((5, 1));
-                info!("opening: ({},{})", r, c);
-                send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
-                thread::sleep(Duration::from_millis(cli.interval_ms));
+                info!(target: LOG_TARGET, "No PDA found — starting a NEW game by making the first move.");
+                seat = Some(announce_seat(Seat::First));
+                let (r, c) = open_game(&*strategy);
+                info!(target: LOG_TARGET, "opening: ({},{})", r, c);
+                let paused = delay.as_mut().map(HumanDelay::pause).unwrap_or_default();
+                if shutdown::requested() {
+                    info!(target: LOG_TARGET, "Ctrl-C — stopping before opening.");
+                    break GameEnd::Interrupted;
+                }
+                if let Some(watch) = watch {
+                    watch.drain();
+                }
+                match send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)? {
+                    SendOutcome::Confirmed(sig) => {
+                        record_move(cli, r, c, &sig, &[0u8; 5])?;
+                        moves = Some(vec![(r, c)]);
+                        last_move = Some((r, c));
+                        predicted = Some(game::apply_move([0u8; 5], r, c));
+                        stats.record_move(turn_started.elapsed().saturating_sub(paused), is_forced_win([0u8; 5], r, c));
+                    }
+                    SendOutcome::Dropped | SendOutcome::BoardAdvanced => seat = None,
+                    SendOutcome::Unknown(sig) => {
+                        warn!(target: LOG_TARGET, "cannot tell whether opening {} landed — stopping", sig);
+                        break GameEnd::Unconfirmed;
+                    }
+                    SendOutcome::DryRun(_) => break GameEnd::DryRun,
+                }
+                if stats.over_budget(cli.time_budget()) {
+                    info!(target: LOG_TARGET, "time budget reached.");
+                    break GameEnd::TimeBudget;
+                }
+                pushed = next_turn(watch, cli.interval_ms)?;
             }
         }
-    }
+    };
 
-    if let Some(final_board) = fetch_board(rpc, game_pda)? {
-        print_board("final", &final_board);
+    let board = fetch_board(rpc, program_id, game_pda)?;
+    if let Some(final_board) = board {
+        print_board("final", &final_board, None);
     } else {
-        info!("final board: account missing/closed");
+        info!(target: LOG_TARGET, "final board: account missing/closed");
     }
-    Ok(())
+    let won = match end {
+        GameEnd::Closed => Some(last_move != Some((5, 8))),
+        GameEnd::GlassOnly => Some(false),
+        _ => None,
+    };
+    Ok(GameResult { end, board, won, moves })
+}
+
+/// Waits for the next autoplay turn: for the game account to change when
+/// `--ws` is on, giving up after `interval_ms` so a missed push costs one
+/// fetch; otherwise a plain `interval_ms` sleep. Both end early on Ctrl-C.
+/// A lost subscription is dropped, and the run polls from then on.
+fn next_turn(watch: &mut Option<BoardWatch>, interval_ms: u64) -> Result<Option<Option<[u8; 5]>>> {
+    let Some(subscribed) = watch.as_ref() else {
+        shutdown::sleep_ms(interval_ms);
+        return Ok(None);
+    };
+    let deadline = Instant::now() + Duration::from_millis(interval_ms);
+    while !shutdown::requested() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        match subscribed.next_change(left.min(Duration::from_millis(100))) {
+            Push::Board(read) => return Ok(Some(read?)),
+            Push::Quiet => {}
+            Push::Lost => {
+                warn!(target: LOG_TARGET, "--ws subscription lost; polling every {}ms instead", interval_ms);
+                *watch = None;
+                shutdown::sleep_ms(u64::try_from(left.as_millis()).unwrap_or(u64::MAX));
+                break;
+            }
+        }
+    }
+    Ok(None)
 }
 
 fn run_single_move(
-    rpc: &RpcClient,
+    rpc: &MultiRpc,
     program_id: &Pubkey,
     fee_collector: &Pubkey,
     payer: &Keypair,
     game_pda: &Pubkey,
     cli: &Cli,
-) -> Result<()> {
-    match fetch_board(rpc, game_pda)? {
+) -> Result<SingleOutcome> {
+    let strategy = strategy::build(cli.strategy, cli.seed);
+    if !cli.batch.is_empty() {
+        let board = fetch_board(rpc, program_id, game_pda)?.unwrap_or([0u8; 5]);
+        info!(target: LOG_TARGET, "sending batch of {} moves: {:?}", cli.batch.len(), cli.batch);
+        let outcome = send_moves(rpc, program_id, fee_collector, payer, game_pda, board, &cli.batch)?;
+        if matches!(outcome, SendOutcome::DryRun(_)) {
+            return Ok(SingleOutcome::MoveSent);
+        }
+        if let SendOutcome::Confirmed(sig) = &outcome {
+            let mut on = board;
+            for &(r, c) in &cli.batch {
+                record_move(cli, r, c, sig, &on)?;
+                on = game::apply_move(on, r, c);
+            }
+        }
+        if let Some(updated) = fetch_board(rpc, program_id, game_pda)? {
+            print_board("updated", &updated, None);
+        }
+        return Ok(sent_outcome(&outcome));
+    }
+
+    match fetch_board(rpc, program_id, game_pda)? {
         Some(board) => {
-            print_board("current", &board);
+            save_board(cli, board)?;
             if is_glass_only(board) {
-                info!("Only glass remains — game ended.");
-                return Ok(());
+                print_board("current", &board, None);
+                info!(target: LOG_TARGET, "Only glass remains — game ended.");
+                return Ok(SingleOutcome::GameOver);
             }
 
-            let (r, c) = if cli.cash_out {
-                (0, 0)
-            } else if let (Some(r), Some(c)) = (cli.row, cli.col) {
-                (r, c)
+            let decision = if let (false, Some(r), Some(c)) = (cli.cash_out, cli.row, cli.col) {
+                if !is_legal_move(board, r, c) {
+                    return Err(SolanaError::InvalidMove {
+                        r,
+                        c,
+                        reason: "square already eaten or off the board",
+                    }
+                    .into());
+                }
+                Decision::Move(r, c)
             } else {
-                pick_forced_victory(board)
-                    .or_else(|| pick_any_legal(board))
-                    .unwrap_or((0, 0))
+                game::decide(board, cli.cash_out, || {
+                    pick_move(cli, &*strategy, announce_seat(Seat::from_board(board)), board)
+                })
             };
 
-            info!("chosen move: ({},{})", r, c);
-            if r == 0 && c == 0 {
-                info!("No safe move / cash-out.");
-                return Ok(());
+            if !cli.quiet {
+                print_board("current", &board, decision.square());
             }
+            let (r, c) = match decision {
+                Decision::Move(r, c) => (r, c),
+                Decision::CashOut => {
+                    reset_game_pda(rpc, program_id, fee_collector, payer, game_pda)?;
+                    return Ok(SingleOutcome::CashedOut);
+                }
+                Decision::NoMove => {
+                    info!(target: LOG_TARGET, "No legal move.");
+                    return Ok(SingleOutcome::NoMove);
+                }
+            };
+            info!(target: LOG_TARGET, "chosen move: ({},{})", r, c);
 
-            send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
-            if let Some(updated) = fetch_board(rpc, game_pda)? {
-                print_board("updated", &updated);
+            let outcome = send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
+            if matches!(outcome, SendOutcome::Dropped | SendOutcome::BoardAdvanced) {
+                return Ok(SingleOutcome::NotConfirmed);
+            }
+            if matches!(outcome, SendOutcome::DryRun(_)) {
+                return Ok(SingleOutcome::MoveSent);
+            }
+            if let SendOutcome::Confirmed(sig) = &outcome {
+                record_move(cli, r, c, sig, &board)?;
+            }
+            if let Some(updated) = fetch_board(rpc, program_id, game_pda)? {
+                save_board(cli, updated)?;
+                print_board("updated", &updated, None);
             } else {
-                warn!("account closed after our move");
+                warn!(target: LOG_TARGET, "account closed after our move");
             }
+            Ok(sent_outcome(&outcome))
         }
         None => {
-            if !cli.init_if_missing {
-                warn!("game account missing/closed — aborting");
-                return Ok(());
+            if !may_open_game(rpc, cli)? {
+                warn!(target: LOG_TARGET, "game account missing/closed — aborting");
+                return Ok(SingleOutcome::GameOver);
+            }
+            info!(target: LOG_TARGET, "No PDA found — starting NEW game.");
+            announce_seat(Seat::First);
+            let (r, c) = open_game(&*strategy);
+            info!(target: LOG_TARGET, "opening: ({},{})", r, c);
+            let outcome = send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
+            if matches!(outcome, SendOutcome::Dropped | SendOutcome::BoardAdvanced) {
+                return Ok(SingleOutcome::NotConfirmed);
+            }
+            if matches!(outcome, SendOutcome::DryRun(_)) {
+                return Ok(SingleOutcome::MoveSent);
             }
-            info!("No PDA found — starting NEW game.");
-            let empty = [0u8; 5];
-            let (r, c) = pick_forced_victory(empty)
-                .or_else(|| pick_any_legal(empty))
-                .unwrap_or((5, 1));
-            info!("opening: ({},{})", r, c);
-            send_move(rpc, program_id, fee_collector, payer, game_pda, r, c)?;
-            if let Some(updated) = fetch_board(rpc, game_pda)? {
-                print_board("new board", &updated);
+            if let SendOutcome::Confirmed(sig) = &outcome {
+                record_move(cli, r, c, sig, &[0u8; 5])?;
             }
+            if let Some(updated) = fetch_board(rpc, program_id, game_pda)? {
+                print_board("new board", &updated, None);
+            }
+            Ok(sent_outcome(&outcome))
+        }
+    }
+}
+
+fn sent_outcome(outcome: &SendOutcome) -> SingleOutcome {
+    match outcome {
+        SendOutcome::Confirmed(_) | SendOutcome::DryRun(_) => SingleOutcome::MoveSent,
+        _ => SingleOutcome::NotConfirmed,
+    }
+}
+
+fn announce_seat(seat: Seat) -> Seat {
+    match seat {
+        Seat::First => info!(target: LOG_TARGET, "we are player one: opening from an empty board"),
+        Seat::Second => info!(target: LOG_TARGET, "we are player two: picking up a game already in progress"),
+    }
+    seat
+}
+
+/// One-time guard before anything can be sent on mainnet with the default program
+/// and collector: asks for `YES` on stdin unless `--yes` was passed. Without a
+/// terminal to ask on it fails instead of waiting on stdin.
+fn confirm_mainnet(rpc: &MultiRpc, cli: &Cli) -> Result<()> {
+    if cli.yes || cli.dry_run || !cli.uses_default_ids() || !is_mainnet(rpc)? {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("about to send on mainnet but stdin is not a terminal; pass --yes to confirm");
+    }
+    eprint!("About to send a real transaction spending SOL on mainnet. Type YES to continue: ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if answer.trim() != "YES" {
+        bail!("mainnet transaction not confirmed");
+    }
+    Ok(())
+}
+
+/// Stops the run before its first send when `payer` holds less than
+/// `--min-balance-lamports`. Without the flag, the floor is one move's fee
+/// plus `GAME_ACCOUNT_LAMPORTS` when the run may open a game: the PDA is
+/// missing or reset, or autoplay may start over after this game. A dry run
+/// spends nothing, so an empty wallet may still rehearse.
+fn check_start_balance(rpc: &MultiRpc, cli: &Cli, payer: &Pubkey, game_pda: &Pubkey) -> Result<()> {
+    if cli.dry_run {
+        return Ok(());
+    }
+    let min = match cli.min_balance_lamports {
+        Some(min) => min,
+        None => {
+            let fee = estimate_move_fee(rpc, &cli.program_id, &cli.fee_collector, payer, game_pda)?;
+            let opens = cli.init_if_missing
+                && (cli.reset || (cli.autoplay && cli.loop_games != 1) || fetch_board(rpc, &cli.program_id, game_pda)?.is_none());
+            if opens { fee + solana::GAME_ACCOUNT_LAMPORTS } else { fee }
         }
+    };
+    let balance = solana::check_balance(rpc, payer, min)?;
+    info!(target: LOG_TARGET, "payer balance: {} lamports", balance);
+    Ok(())
+}
+
+/// Whether a missing PDA may be replaced by opening a new game. `--init_if_missing=false`
+/// always says no. Otherwise, opening on mainnet with the default program and collector
+/// spends real fees, so it fails unless `--confirm-new-game` or `--dry-run` was passed.
+fn may_open_game(rpc: &MultiRpc, cli: &Cli) -> Result<bool> {
+    if !cli.init_if_missing {
+        return Ok(false);
+    }
+    if cli.confirm_new_game || cli.dry_run || !cli.uses_default_ids() {
+        return Ok(true);
+    }
+    if is_mainnet(rpc)? {
+        bail!("no game in progress on mainnet; pass --confirm-new-game to open one with the default program");
+    }
+    Ok(true)
+}
+
+/// Opening move for a fresh game. We always move first from the empty board,
+/// which is a first-player win, so the solver's stored reply is the opening
+/// (without the solver, any legal move).
+fn open_game(strategy: &dyn Strategy) -> (u8, u8) {
+    strategy.choose([0u8; 5]).unwrap_or((5, 1))
+}
+
+/// Picks our reply for `board`. Seat does not change the table lookup (it is
+/// always our turn) but as player two we may inherit a lost position, in which
+/// case we keep playing rather than giving up.
+fn pick_move(cli: &Cli, strategy: &dyn Strategy, seat: Seat, board: [u8; 5]) -> Option<(u8, u8)> {
+    if !game::solver_ready() {
+        return strategy.choose(board);
+    }
+    debug!(target: LOG_TARGET, "winning moves: {:?}", winning_moves(board));
+    if mover_is_winning_under(board, cli.rules()) {
+        match seat {
+            Seat::First => warn!(target: LOG_TARGET, "lost the forced win as player one — playing on"),
+            Seat::Second => info!(target: LOG_TARGET, "inherited a losing position — playing on"),
+        }
+    }
+    strategy.choose(board)
+}
+
+/// True when `(r, c)` is one of the solver's forced wins on `board`.
+fn is_forced_win(board: [u8; 5], r: u8, c: u8) -> bool {
+    game::solver_ready() && game::winning_moves(board).contains(&(r, c))
+}
+
+/// A board snapshot on stdout: the rendered grid under `--output text`, one
+/// JSON record under `--output json`, or the dashboard's board pane under `--tui`.
+fn print_board(tag: &str, s: &[u8; 5], highlight: Option<(u8, u8)>) {
+    #[cfg(feature = "tui")]
+    if tui::active() {
+        tui::show_board(s, highlight);
+        return;
+    }
+    match render::output_format() {
+        OutputFormat::Text => {
+            info!(target: LOG_TARGET, "{}:", tag);
+            print!("{}", render::format_board(s, highlight));
+        }
+        OutputFormat::Json => println!("{}", render::board_record(tag, s, highlight)),
+    }
+}
+
+/// Our confirmed move `(r, c)` on `board`: printed, and appended to
+/// `--history-file` when given.
+fn record_move(cli: &Cli, r: u8, c: u8, sig: &Signature, board: &[u8; 5]) -> Result<()> {
+    print_move(r, c, sig, board);
+    #[cfg(feature = "tui")]
+    tui::record_move(&sig.to_string());
+    if let Some(path) = &cli.history_file {
+        let entry = history::Entry::now(*board, (r, c), *sig);
+        history::append(Path::new(&config::expand_home(path)), &entry)
+            .with_context(|| format!("failed to append to history file {}", path))?;
     }
     Ok(())
 }
 
-fn print_board(tag: &str, s: &[u8; 5]) {
-    info!("{}:", tag);
-    for (i, row) in s.iter().enumerate() {
-        println!("row{}: {:08b}", i + 1, row);
+/// Our confirmed move `(r, c)` on `board`. Text output already logs it, so
+/// only `--output json` writes a record.
+fn print_move(r: u8, c: u8, sig: &Signature, board: &[u8; 5]) {
+    if render::output_format() == OutputFormat::Json {
+        println!("{}", render::move_record(r, c, &sig.to_string(), board));
     }
 }
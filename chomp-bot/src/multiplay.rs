@@ -0,0 +1,134 @@
+//! `--keypairs` under the `async` feature: one game per wallet at once on the
+//! nonblocking RPC client, so a slow confirmation only holds up its own game.
+//! Each game plays the `--strategy` move on its player's own PDA until the
+//! program closes it.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use tokio::task::JoinSet;
+
+use crate::config::StrategyKind;
+use crate::game::{self, is_glass_only, Decision};
+use crate::shutdown;
+use crate::solana::nonblocking::{fetch_board, send_move, AsyncRpc};
+use crate::solana::{self, get_game_pda, SendOutcome, SolanaError};
+use crate::stats::RunStats;
+use crate::strategy;
+use crate::{is_forced_win, GameEnd};
+
+const LOG_TARGET: &str = "chomp::game";
+
+/// What every game of a run shares.
+#[derive(Copy, Clone, Debug)]
+pub struct Limits {
+    pub interval: Duration,
+    pub max_moves: u32,
+    /// Whether a missing PDA may be opened with a new game.
+    pub open_games: bool,
+    /// `--max-concurrent`: how many games run at once.
+    pub max_concurrent: usize,
+    pub strategy: StrategyKind,
+    /// `--seed`; each game adds its index so the players draw apart.
+    pub seed: u64,
+}
+
+/// Plays every wallet's game to its end, at most `max_concurrent` at a time.
+/// Returns each wallet's stats, or the error its game stopped on, in the
+/// order the games ended.
+pub async fn run(
+    rpc: AsyncRpc,
+    program_id: Pubkey,
+    fee_collector: Pubkey,
+    payers: Vec<Keypair>,
+    limits: Limits,
+) -> Vec<(Pubkey, solana::Result<RunStats>)> {
+    let rpc = Arc::new(rpc);
+    let mut games = JoinSet::new();
+    let mut results = Vec::with_capacity(payers.len());
+    for (index, payer) in (0u64..).zip(payers) {
+        if games.len() >= limits.max_concurrent {
+            if let Some(joined) = games.join_next().await {
+                results.push(joined.expect("game task panicked"));
+            }
+        }
+        if shutdown::requested() {
+            break;
+        }
+        let rpc = Arc::clone(&rpc);
+        games.spawn(async move {
+            let seed = limits.seed.wrapping_add(index);
+            let stats = play(&rpc, &program_id, &fee_collector, &payer, limits, seed).await;
+            (payer.pubkey(), stats)
+        });
+    }
+    while let Some(joined) = games.join_next().await {
+        results.push(joined.expect("game task panicked"));
+    }
+    results
+}
+
+/// One game on `payer`'s PDA, counted as `run_games` counts one.
+async fn play(
+    rpc: &AsyncRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    limits: Limits,
+    seed: u64,
+) -> solana::Result<RunStats> {
+    let player = payer.pubkey();
+    let strategy = strategy::build(limits.strategy, seed);
+    let (game_pda, _) = get_game_pda(program_id, &player);
+    let mut stats = RunStats::default();
+    let mut moves = 0;
+    let end = loop {
+        if shutdown::requested() {
+            break GameEnd::Interrupted;
+        }
+        let turn_started = Instant::now();
+        let board = match fetch_board(rpc, program_id, &game_pda).await? {
+            Some(board) if is_glass_only(board) => break GameEnd::GlassOnly,
+            Some(board) => board,
+            None if moves > 0 => break GameEnd::Closed,
+            None if !limits.open_games => break GameEnd::Missing,
+            None => [0u8; 5],
+        };
+        // There is no cash-out on the nonblocking client, so only moves are decided.
+        let decision = game::decide(board, false, || strategy.choose(board));
+        let Decision::Move(r, c) = decision else {
+            break GameEnd::NoMove;
+        };
+        info!(target: LOG_TARGET, "{}: playing ({},{})", player, r, c);
+        let outcome = match send_move(rpc, program_id, fee_collector, payer, &game_pda, r, c).await
+        {
+            Err(e @ SolanaError::FeeAboveCap { .. }) => {
+                warn!(target: LOG_TARGET, "{}: {}; waiting for fees to drop", player, e);
+                tokio::time::sleep(limits.interval).await;
+                continue;
+            }
+            sent => sent?,
+        };
+        match outcome {
+            SendOutcome::Confirmed(_) => {
+                moves += 1;
+                stats.record_move(turn_started.elapsed(), is_forced_win(board, r, c));
+            }
+            SendOutcome::Dropped | SendOutcome::BoardAdvanced => {}
+            SendOutcome::Unknown(_) => break GameEnd::Unconfirmed,
+            SendOutcome::DryRun(_) => break GameEnd::DryRun,
+        }
+        if moves >= limits.max_moves {
+            break GameEnd::MaxMoves;
+        }
+        tokio::time::sleep(limits.interval).await;
+    };
+    info!(target: LOG_TARGET, "{}: {:?} after {} moves", player, end, moves);
+    stats.record_game(matches!(end, GameEnd::Closed | GameEnd::GlassOnly));
+    Ok(stats)
+}
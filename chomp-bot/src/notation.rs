@@ -0,0 +1,274 @@
+//! Chomp notation, a compact text form of one game for sharing it and feeding
+//! it back to `--moves`. A header of `[Tag "value"]` lines gives the board
+//! shape, the program and the result, then the moves follow in order as
+//! `r<row>c<col>`, whitespace separated:
+//!
+//! ```text
+//! [Shape "5x8"]
+//! [Program "ChompZg47TcVy5fk2LxPEpW6SytFYBES5SHoqgrm8A4D"]
+//! [Result "second"]
+//! r5c7 r4c8 r5c8
+//! ```
+//!
+//! `Result` is the seat that won, `first` or `second`, or `unfinished`.
+//! `Program` may be left out. Blank lines and lines starting with `#` are
+//! skipped.
+
+use std::fmt;
+use std::str::FromStr;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::game::Seat;
+use crate::table::BoardShape;
+
+/// Moves written per line by `Display`.
+const MOVES_PER_LINE: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    pub shape: BoardShape,
+    pub program: Option<Pubkey>,
+    /// The winning seat, `None` while the poison is still on the board.
+    pub winner: Option<Seat>,
+    pub moves: Vec<(u8, u8)>,
+}
+
+impl Game {
+    /// A game of `moves` on `shape`. Whoever takes the poison loses, so the
+    /// result follows from the moves.
+    pub fn new(shape: BoardShape, program: Option<Pubkey>, moves: Vec<(u8, u8)>) -> Self {
+        let poison = (shape.rows(), shape.cols());
+        let winner = match moves.iter().position(|&mv| mv == poison) {
+            Some(i) if i % 2 == 0 => Some(Seat::Second),
+            Some(_) => Some(Seat::First),
+            None => None,
+        };
+        Game {
+            shape,
+            program,
+            winner,
+            moves,
+        }
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Shape \"{}\"]", self.shape)?;
+        if let Some(program) = &self.program {
+            writeln!(f, "[Program \"{}\"]", program)?;
+        }
+        let result = match self.winner {
+            Some(Seat::First) => "first",
+            Some(Seat::Second) => "second",
+            None => "unfinished",
+        };
+        writeln!(f, "[Result \"{}\"]", result)?;
+        for line in self.moves.chunks(MOVES_PER_LINE) {
+            let line: Vec<String> = line.iter().map(|&(r, c)| format!("r{}c{}", r, c)).collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Game {
+    type Err = String;
+
+    /// Reads a game back, checking that every move is open on the board the
+    /// moves before it leave, that nothing follows the poison, and that the
+    /// `Result` tag agrees with the moves.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (mut shape, mut program, mut result) = (None, None, None);
+        let mut moves = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let at = |e: String| format!("line {}: {}", i + 1, e);
+            if let Some(tag) = line.strip_prefix('[') {
+                if !moves.is_empty() {
+                    return Err(at("header tags must come before the moves".into()));
+                }
+                let (key, value) = parse_tag(tag).map_err(at)?;
+                match key {
+                    "Shape" => shape = Some(parse_shape(value).map_err(at)?),
+                    "Program" => {
+                        program = Some(
+                            value
+                                .parse()
+                                .map_err(|_| at(format!("`{}` is not a pubkey", value)))?,
+                        )
+                    }
+                    "Result" => result = Some(parse_result(value).map_err(at)?),
+                    // Unknown tags are kept out of `Game` but do not fail the read.
+                    _ => {}
+                }
+                continue;
+            }
+            for token in line.split_whitespace() {
+                moves.push(parse_square(token).map_err(at)?);
+            }
+        }
+        let shape = shape.ok_or("missing the [Shape \"RxC\"] tag")?;
+        check_moves(shape, &moves)?;
+        let game = Game::new(shape, program, moves);
+        match result {
+            None => Err("missing the [Result \"..\"] tag".into()),
+            Some(result) if result != game.winner => Err(format!(
+                "the Result tag says {:?} but the moves say {:?}",
+                result, game.winner
+            )),
+            Some(_) => Ok(game),
+        }
+    }
+}
+
+/// `Key "value"]`, the rest of a tag line after its `[`.
+fn parse_tag(tag: &str) -> Result<(&str, &str), String> {
+    let bad = || format!("expected `[Key \"value\"]`, got `[{}`", tag);
+    let inner = tag.strip_suffix(']').ok_or_else(bad)?;
+    let (key, value) = inner.split_once(char::is_whitespace).ok_or_else(bad)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(bad)?;
+    Ok((key, value))
+}
+
+fn parse_shape(value: &str) -> Result<BoardShape, String> {
+    let bad = || format!("shape `{}` is not `RxC`", value);
+    let (rows, cols) = value.split_once('x').ok_or_else(bad)?;
+    BoardShape::new(
+        rows.parse().map_err(|_| bad())?,
+        cols.parse().map_err(|_| bad())?,
+    )
+}
+
+fn parse_result(value: &str) -> Result<Option<Seat>, String> {
+    match value {
+        "first" => Ok(Some(Seat::First)),
+        "second" => Ok(Some(Seat::Second)),
+        "unfinished" => Ok(None),
+        _ => Err(format!(
+            "result `{}` is not first, second or unfinished",
+            value
+        )),
+    }
+}
+
+/// `r<row>c<col>`, e.g. `r5c1`.
+fn parse_square(token: &str) -> Result<(u8, u8), String> {
+    let bad = || format!("move `{}` is not `r<row>c<col>`", token);
+    let (r, c) = token
+        .strip_prefix('r')
+        .and_then(|rest| rest.split_once('c'))
+        .ok_or_else(bad)?;
+    Ok((r.parse().map_err(|_| bad())?, c.parse().map_err(|_| bad())?))
+}
+
+/// Checks `moves` against `shape` from the empty board: each square must be
+/// on the board and uneaten, and the game must stop at the poison.
+fn check_moves(shape: BoardShape, moves: &[(u8, u8)]) -> Result<(), String> {
+    // Eaten squares per row, top first: a move eats its square and everything
+    // above and to the left of it.
+    let mut eaten = vec![0u8; usize::from(shape.rows())];
+    let poison = (shape.rows(), shape.cols());
+    for (i, &(r, c)) in moves.iter().enumerate() {
+        if i > 0 && moves[i - 1] == poison {
+            return Err(format!(
+                "move #{} r{}c{} comes after the poison was taken",
+                i + 1,
+                r,
+                c
+            ));
+        }
+        let open = (1..=shape.rows()).contains(&r)
+            && (1..=shape.cols()).contains(&c)
+            && eaten[usize::from(r - 1)] < c;
+        if !open {
+            return Err(format!(
+                "move #{} r{}c{} is not an open square on the {} board",
+                i + 1,
+                r,
+                c,
+                shape
+            ));
+        }
+        for row in &mut eaten[..usize::from(r)] {
+            *row = (*row).max(c);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game;
+
+    #[test]
+    fn games_round_trip_through_text() {
+        let program = Pubkey::new_unique();
+        let played = game::self_play([0u8; 5], game::choose_move, game::pick_any_legal);
+        let exported = Game::new(BoardShape::STANDARD, Some(program), played.moves.clone());
+        let expected = if played.first_won {
+            Seat::First
+        } else {
+            Seat::Second
+        };
+        assert_eq!(exported.winner, Some(expected));
+
+        let text = exported.to_string();
+        assert!(text.starts_with("[Shape \"5x8\"]\n"), "{}", text);
+        assert!(text.contains("[Result \"first\"]"), "{}", text);
+        assert!(text.contains(&format!("r{}c{}", played.moves[0].0, played.moves[0].1)));
+        assert_eq!(text.parse::<Game>().unwrap(), exported);
+
+        let unfinished = Game::new(BoardShape::new(3, 4).unwrap(), None, vec![(1, 2), (3, 1)]);
+        let text = unfinished.to_string();
+        assert_eq!(
+            text,
+            "[Shape \"3x4\"]\n[Result \"unfinished\"]\nr1c2 r3c1\n"
+        );
+        assert_eq!(text.parse::<Game>().unwrap(), unfinished);
+    }
+
+    #[test]
+    fn imports_check_the_moves_and_the_result() {
+        let header = "# shared game\n[Shape \"5x8\"]\n[Event \"club night\"]\n";
+        let parse = |body: &str| format!("{}{}", header, body).parse::<Game>();
+
+        let game = parse("[Result \"second\"]\nr5c8\n").unwrap();
+        assert_eq!(game.moves, [(5, 8)]);
+        assert_eq!(game.winner, Some(Seat::Second));
+
+        assert_eq!(
+            parse("[Result \"unfinished\"]\nr2c3 r1c2\n").unwrap_err(),
+            "move #2 r1c2 is not an open square on the 5x8 board"
+        );
+        assert_eq!(
+            parse("[Result \"second\"]\nr5c8 r1c1\n").unwrap_err(),
+            "move #2 r1c1 comes after the poison was taken"
+        );
+        assert!(parse("[Result \"first\"]\nr5c8\n")
+            .unwrap_err()
+            .starts_with("the Result tag says"));
+        assert_eq!(
+            parse("[Result \"unfinished\"]\nr2 c3\n").unwrap_err(),
+            "line 5: move `r2` is not `r<row>c<col>`"
+        );
+        assert_eq!(
+            "[Result \"unfinished\"]\nr1c1\n"
+                .parse::<Game>()
+                .unwrap_err(),
+            "missing the [Shape \"RxC\"] tag"
+        );
+        assert!(parse("r1c1\n")
+            .unwrap_err()
+            .starts_with("missing the [Result"));
+    }
+}
@@ -0,0 +1,297 @@
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::stats::RunStats;
+
+const BIT_TEST: [u8; 8] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+const POISON: (u8, u8) = (5, 8);
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+static JSON: AtomicBool = AtomicBool::new(false);
+static BINARY: AtomicBool = AtomicBool::new(false);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// `auto` colors only when the stream is a terminal and `NO_COLOR` is unset.
+    pub fn enabled(self, stream_is_tty: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && stream_is_tty
+            }
+        }
+    }
+
+    /// Style for the logger, which writes to stderr.
+    pub fn write_style(self) -> env_logger::WriteStyle {
+        if self.enabled(std::io::stderr().is_terminal()) {
+            env_logger::WriteStyle::Always
+        } else {
+            env_logger::WriteStyle::Never
+        }
+    }
+}
+
+/// Turns board coloring on or off; boards are printed to stdout.
+pub fn init_color(choice: ColorChoice) {
+    COLOR.store(choice.enabled(std::io::stdout().is_terminal()), Ordering::Relaxed);
+}
+
+fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// What goes to stdout: boards for people, or one JSON record per line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+pub fn init_output(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn output_format() -> OutputFormat {
+    if JSON.load(Ordering::Relaxed) {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    }
+}
+
+/// How `format_board` draws a board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BoardFormat {
+    /// Numbered rows and columns: `#` still there, `.` eaten, `X` the poison
+    /// and `*` the highlighted square.
+    Grid,
+    /// Each row mask as eight bits, `1` for eaten.
+    Binary,
+}
+
+pub fn init_board_format(format: BoardFormat) {
+    BINARY.store(format == BoardFormat::Binary, Ordering::Relaxed);
+}
+
+/// `{"event":"board",...}`: a snapshot labelled `tag`, with the move about to
+/// be played, if any, as `[row, col]`.
+pub fn board_record(tag: &str, s: &[u8; 5], highlight: Option<(u8, u8)>) -> String {
+    serde_json::json!({
+        "event": "board",
+        "tag": tag,
+        "board": s,
+        "highlight": highlight.map(|(r, c)| [r, c]),
+    })
+    .to_string()
+}
+
+/// `{"event":"game",...}`: an open game from `list-games`, with its player when
+/// known.
+pub fn game_record(pda: &str, player: Option<&str>, board: &[u8; 5], stake: u64) -> String {
+    serde_json::json!({
+        "event": "game",
+        "pda": pda,
+        "player": player,
+        "board": board,
+        "stake": stake,
+    })
+    .to_string()
+}
+
+/// `{"event":"move",...}`: our confirmed move `(row, col)` on `board`, the
+/// position it was played on.
+pub fn move_record(row: u8, col: u8, signature: &str, board: &[u8; 5]) -> String {
+    serde_json::json!({
+        "event": "move",
+        "row": row,
+        "col": col,
+        "signature": signature,
+        "board": board,
+    })
+    .to_string()
+}
+
+/// `{"event":"summary",...}`: the counters of a finished autoplay run;
+/// `mean_latency_ms` is `null` when no move was sent.
+pub fn summary_record(stats: &RunStats) -> String {
+    serde_json::json!({
+        "event": "summary",
+        "games": stats.games,
+        "games_finished": stats.games_finished,
+        "moves_sent": stats.moves_sent,
+        "forced_moves": stats.forced_moves,
+        "fallback_moves": stats.moves_sent - stats.forced_moves,
+        "elapsed_ms": stats.elapsed().as_millis() as u64,
+        "mean_latency_ms": stats.mean_latency().map(|d| d.as_millis() as u64),
+        "lamports_spent": stats.lamports_spent,
+    })
+    .to_string()
+}
+
+/// Checkmark prefix for confirmed transactions.
+pub fn ok_mark() -> String {
+    if color() {
+        "✅".green().to_string()
+    } else {
+        "✅".to_string()
+    }
+}
+
+/// Renders the board in the `--board-format` chosen. Eaten squares are
+/// dimmed, the poison is red and `highlight` (usually the move we are about
+/// to send) is green.
+pub fn format_board(s: &[u8; 5], highlight: Option<(u8, u8)>) -> String {
+    if BINARY.load(Ordering::Relaxed) {
+        format_binary(s, highlight)
+    } else {
+        format_grid(s, highlight)
+    }
+}
+
+fn format_grid(s: &[u8; 5], highlight: Option<(u8, u8)>) -> String {
+    let colored = color();
+    let mut out = String::from(" ");
+    for col in 1..=BIT_TEST.len() {
+        out.push_str(&format!(" {}", col));
+    }
+    out.push('\n');
+    for (i, row) in s.iter().enumerate() {
+        out.push_str(&(i + 1).to_string());
+        for (j, bit) in BIT_TEST.iter().enumerate() {
+            let cell = ((i + 1) as u8, (j + 1) as u8);
+            let eaten = row & bit != 0;
+            let glyph = if Some(cell) == highlight {
+                '*'
+            } else if eaten {
+                '.'
+            } else if cell == POISON {
+                'X'
+            } else {
+                '#'
+            };
+            out.push(' ');
+            if !colored || glyph == '#' {
+                out.push(glyph);
+            } else if glyph == '*' {
+                out.push_str(&glyph.green().bold().to_string());
+            } else if glyph == 'X' {
+                out.push_str(&glyph.red().to_string());
+            } else {
+                out.push_str(&glyph.dimmed().to_string());
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The row masks one per line, as bits.
+fn format_binary(s: &[u8; 5], highlight: Option<(u8, u8)>) -> String {
+    let colored = color();
+    let mut out = String::new();
+    for (i, row) in s.iter().enumerate() {
+        out.push_str(&format!("row{}: ", i + 1));
+        for (j, bit) in BIT_TEST.iter().enumerate() {
+            let cell = ((i + 1) as u8, (j + 1) as u8);
+            let eaten = row & bit != 0;
+            let glyph = if eaten { '1' } else { '0' };
+            if !colored {
+                out.push(glyph);
+            } else if Some(cell) == highlight {
+                out.push_str(&glyph.green().bold().to_string());
+            } else if cell == POISON && !eaten {
+                out.push_str(&glyph.red().to_string());
+            } else if eaten {
+                out.push_str(&glyph.dimmed().to_string());
+            } else {
+                out.push(glyph);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_choices_ignore_tty() {
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+    }
+
+    #[test]
+    fn json_records_are_single_lines() {
+        let board = board_record("current", &[0xC0, 0, 0, 0, 0], Some((2, 1)));
+        assert_eq!(
+            board,
+            r#"{"board":[192,0,0,0,0],"event":"board","highlight":[2,1],"tag":"current"}"#
+        );
+        let mv = move_record(2, 1, "5sig", &[0xC0, 0, 0, 0, 0]);
+        let parsed: serde_json::Value = serde_json::from_str(&mv).unwrap();
+        assert_eq!(parsed["event"], "move");
+        assert_eq!(parsed["row"], 2);
+        assert_eq!(parsed["col"], 1);
+        assert_eq!(parsed["signature"], "5sig");
+        assert_eq!(parsed["board"], serde_json::json!([192, 0, 0, 0, 0]));
+        assert!(!mv.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&board_record("final", &[0; 5], None))
+                .unwrap()["highlight"],
+            serde_json::Value::Null
+        );
+
+        let mut stats = RunStats::default();
+        stats.record_move(std::time::Duration::from_millis(800), true);
+        stats.record_move(std::time::Duration::from_millis(1200), false);
+        stats.lamports_spent = 10_000;
+        let summary: serde_json::Value = serde_json::from_str(&summary_record(&stats)).unwrap();
+        assert_eq!(summary["event"], "summary");
+        assert_eq!(summary["moves_sent"], 2);
+        assert_eq!(summary["forced_moves"], 1);
+        assert_eq!(summary["fallback_moves"], 1);
+        assert_eq!(summary["mean_latency_ms"], 1000);
+        assert_eq!(summary["lamports_spent"], 10_000);
+        let idle: serde_json::Value =
+            serde_json::from_str(&summary_record(&RunStats::default())).unwrap();
+        assert_eq!(idle["mean_latency_ms"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn plain_grid_marks_eaten_squares_and_the_poison() {
+        init_color(ColorChoice::Never);
+        let out = format_grid(&[0xFF, 0xF0, 0xC0, 0, 0], Some((3, 3)));
+        assert_eq!(
+            out,
+            "  1 2 3 4 5 6 7 8\n\
+             1 . . . . . . . .\n\
+             2 . . . . # # # #\n\
+             3 . . * # # # # #\n\
+             4 # # # # # # # #\n\
+             5 # # # # # # # X\n"
+        );
+    }
+
+    #[test]
+    fn plain_board_matches_binary_rows() {
+        init_color(ColorChoice::Never);
+        let out = format_binary(&[0xFF, 0xF0, 0, 0, 0x01], Some((2, 5)));
+        assert_eq!(
+            out,
+            "row1: 11111111\nrow2: 11110000\nrow3: 00000000\nrow4: 00000000\nrow5: 00000001\n"
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! `serve` subcommand: the solver over plain HTTP/1.1, for front-ends that
+//! want hints without talking to Solana. One request per connection, handled
+//! on the accepting thread; the table lookups are far cheaper than the I/O.
+//! Reads and writes time out after `IO_TIMEOUT`, so a client that stalls
+//! mid-request cannot hold up everyone queued behind it.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use crate::game::{self, Outcome};
+
+const LOG_TARGET: &str = "chomp::server";
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+/// Longest a connection may wait on the client for each read or write.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct BestMoveRequest {
+    rows: [u8; 5],
+}
+
+#[derive(Serialize)]
+struct BestMoveResponse {
+    /// 1-based `[row, col]`, or `null` once the board is fully eaten.
+    #[serde(rename = "move")]
+    best: Option<(u8, u8)>,
+    classification: &'static str,
+    depth: u8,
+    moves_remaining: usize,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+pub fn serve(addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("failed to bind {}", addr))?;
+    info!(target: LOG_TARGET, "hint server listening on http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(mut stream) => {
+                let timeouts = stream
+                    .set_read_timeout(Some(IO_TIMEOUT))
+                    .and_then(|()| stream.set_write_timeout(Some(IO_TIMEOUT)));
+                if let Err(e) = timeouts {
+                    warn!(target: LOG_TARGET, "failed to set socket timeouts: {}", e);
+                    continue;
+                }
+                if let Err(e) = handle(&mut stream) {
+                    warn!(target: LOG_TARGET, "request failed: {:#}", e);
+                }
+            }
+            Err(e) => warn!(target: LOG_TARGET, "accept failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+fn handle(stream: &mut TcpStream) -> Result<()> {
+    let (status, body) = match read_request(stream)? {
+        Some((method, path, body)) => route(&method, &path, &body),
+        None => (400, error_json("malformed HTTP request")),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Reads one request and returns its method, path and body, or `None` when it
+/// does not parse, is larger than `MAX_REQUEST_BYTES` or stalls past the read
+/// timeout.
+fn read_request(stream: &mut TcpStream) -> Result<Option<(String, String, Vec<u8>)>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = match stream.read(&mut chunk) {
+            Ok(n) => n,
+            // The read timeout surfaces as either kind, depending on the platform.
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if n == 0 || buf.len() + n > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut req = httparse::Request::new(&mut headers);
+        let header_len = match req.parse(&buf) {
+            Ok(httparse::Status::Complete(len)) => len,
+            Ok(httparse::Status::Partial) => continue,
+            Err(_) => return Ok(None),
+        };
+        let content_length = req
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("content-length"))
+            .and_then(|h| std::str::from_utf8(h.value).ok()?.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        if buf.len() < header_len + content_length {
+            continue;
+        }
+        let method = req.method.unwrap_or_default().to_string();
+        let path = req.path.unwrap_or_default().to_string();
+        let body = buf[header_len..header_len + content_length].to_vec();
+        return Ok(Some((method, path, body)));
+    }
+}
+
+fn route(method: &str, path: &str, body: &[u8]) -> (u16, String) {
+    match (method, path) {
+        ("GET", "/health") => (200, r#"{"status":"ok"}"#.to_string()),
+        ("POST", "/best-move") => best_move(body),
+        (_, "/health" | "/best-move") => (405, error_json("method not allowed")),
+        _ => (404, error_json("not found")),
+    }
+}
+
+fn best_move(body: &[u8]) -> (u16, String) {
+    let req: BestMoveRequest = match serde_json::from_slice(body) {
+        Ok(req) => req,
+        Err(e) => return (400, error_json(&format!("expected {{\"rows\":[5 bytes]}}: {}", e))),
+    };
+    if let Err(e) = game::validate_board(req.rows) {
+        return (400, error_json(&e));
+    }
+    let resp = BestMoveResponse {
+        best: game::choose_move(req.rows),
+        classification: match game::classify(req.rows) {
+            Outcome::Winning => "winning",
+            Outcome::Losing => "losing",
+        },
+        depth: game::depth(req.rows),
+        moves_remaining: game::moves_remaining(req.rows),
+    };
+    (200, serde_json::to_string(&resp).expect("response serializes"))
+}
+
+fn error_json(msg: &str) -> String {
+    serde_json::to_string(&ErrorResponse {
+        error: msg.to_string(),
+    })
+    .expect("error serializes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn best_move_reports_forced_win() {
+        let (status, body) = route("POST", "/best-move", br#"{"rows":[0,0,0,0,0]}"#);
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["move"], serde_json::json!([1, 2]));
+        assert_eq!(json["classification"], "winning");
+    }
+
+    #[test]
+    fn rejects_bad_boards_and_routes() {
+        assert_eq!(route("POST", "/best-move", br#"{"rows":[160,0,0,0,0]}"#).0, 400);
+        assert_eq!(route("POST", "/best-move", b"not json").0, 400);
+        assert_eq!(route("GET", "/best-move", b"").0, 405);
+        assert_eq!(route("GET", "/health", b"").0, 200);
+        assert_eq!(route("GET", "/nope", b"").0, 404);
+    }
+
+    #[test]
+    fn stalled_clients_get_a_400_after_the_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(b"GET /health HTTP/1.1\r\n").unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        handle(&mut stream).unwrap();
+        drop(stream);
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 "), "{}", response);
+    }
+}
@@ -0,0 +1,48 @@
+//! Ctrl-C handling for autoplay. The first SIGINT only raises a flag, so the
+//! move in flight finishes and the run ends through its normal exit path
+//! (summary, final board file). A second SIGINT exits immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+const NAP_SLICE: Duration = Duration::from_millis(100);
+
+pub fn install() -> std::io::Result<()> {
+    // SAFETY: the handler only touches an atomic and calls `_exit`, both of
+    // which are async-signal-safe.
+    unsafe {
+        signal_hook_registry::register(libc::SIGINT, || {
+            if REQUESTED.swap(true, Ordering::SeqCst) {
+                libc::_exit(130);
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// Raises the flag as the first Ctrl-C would, for `--tui`, whose raw-mode
+/// terminal turns Ctrl-C into a key press instead of SIGINT.
+#[cfg(feature = "tui")]
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// True once Ctrl-C has been pressed.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Sleeps for `ms`, returning early when Ctrl-C is pressed.
+pub fn sleep_ms(ms: u64) {
+    let deadline = Instant::now() + Duration::from_millis(ms);
+    while !requested() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        thread::sleep(left.min(NAP_SLICE));
+    }
+}
@@ -1,76 +1,1762 @@
-use anyhow::{bail, Context, Result};
-use log::{info, warn};
-use solana_client::rpc_client::RpcClient;
+use clap::ValueEnum;
+use log::{debug, info, trace, warn};
+use rand::Rng;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    pubsub_client::{AccountSubscription, PubsubClient, PubsubClientError},
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig},
+    rpc_filter::RpcFilterType,
+    rpc_request::{RpcError, RpcResponseErrorData, MAX_MULTIPLE_ACCOUNTS},
+    rpc_response::RpcSimulateTransactionResult,
+};
 use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
+    account::Account,
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    system_program, transaction::Transaction,
+    signature::{Keypair, Signature, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use solana_transaction_status::TransactionStatus;
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
 };
-use std::{thread, time::Duration};
+
+use crate::errors;
+use crate::game::{apply_move, is_legal_move, validate_board};
+use crate::render::ok_mark;
+use crate::stats::nearest_rank;
+
+/// Log target for the RPC layer, e.g. `RUST_LOG=chomp::solana=debug`.
+pub const LOG_TARGET: &str = "chomp::solana";
+
+const PRIORITY_FEE_REFRESH: Duration = Duration::from_secs(30);
+/// How many blocks a reused blockhash must still have left. A blockhash lasts
+/// 150 blocks, so half of that is left for the send to land.
+const BLOCKHASH_MARGIN: u64 = 75;
+/// How long `submit` keeps signing with one blockhash when the block height
+/// cannot be read: `BLOCKHASH_MARGIN` blocks at about 400 ms each.
+const BLOCKHASH_REUSE: Duration = Duration::from_secs(30);
+/// Base fee per signature; every move is signed by the payer alone.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// What the program moves from the player into a new game account on the
+/// first move, the stake a win pays back.
+pub const GAME_ACCOUNT_LAMPORTS: u64 = 1_000_000;
+/// Without `--compute-unit-limit` a move is billed at the runtime's default
+/// allowance for one instruction.
+const DEFAULT_COMPUTE_UNITS: u64 = 200_000;
+const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+
+pub type Result<T> = std::result::Result<T, SolanaError>;
+
+#[cfg(feature = "async")]
+pub mod nonblocking;
+
+/// Failures at the RPC boundary, split by what a caller can do about them.
+#[derive(Debug)]
+pub enum SolanaError {
+    /// The RPC reports that the account does not exist.
+    AccountMissing(Pubkey),
+    /// Transport-level failure: the endpoint could not be reached.
+    RpcUnreachable(Box<ClientError>),
+    /// The blockhash expired before the transaction landed.
+    BlockhashExpired,
+    /// The payer cannot cover the fee or the rent.
+    InsufficientFunds,
+    /// The program rejected an instruction; `code` is the `u64` form of its
+    /// `ProgramError`, and `logs` are the program's, when the RPC returned them.
+    ProgramRejected { code: u64, logs: Vec<String> },
+    /// A transaction was sent but its effect never became visible in time.
+    ConfirmationTimeout { sig: Signature, waited: Duration },
+    /// The account holds data but belongs to another program.
+    WrongOwner { key: Pubkey, owner: Pubkey },
+    /// The account's data matches no known game layout, e.g. after a program upgrade.
+    UnknownLayout {
+        key: Pubkey,
+        len: usize,
+        layout: String,
+    },
+    /// The bytes read as a board are not a position the program can produce.
+    InvalidBoard { key: Pubkey, reason: String },
+    /// A move was refused locally before anything was sent.
+    InvalidMove { r: u8, c: u8, reason: &'static str },
+    /// The RPC's fee for the transaction is over `--max-fee-lamports`; nothing was sent.
+    FeeAboveCap { fee: u64, cap: u64 },
+    /// The payer holds less than `--min-balance-lamports`; nothing was sent.
+    LowBalance {
+        payer: Pubkey,
+        balance: u64,
+        min: u64,
+    },
+    /// Any other RPC error.
+    Rpc(Box<ClientError>),
+    /// Every attempt allowed by the `RetryPolicy` failed; `last` is the final error.
+    RetriesExhausted {
+        attempts: u32,
+        last: Box<SolanaError>,
+    },
+}
+
+/// What a caller should do after a program rejection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The board moved on since we read it; re-fetch and choose again.
+    Refetch,
+    /// Nothing to retry; surface the error.
+    Stop,
+}
+
+impl SolanaError {
+    /// True for failures of the connection rather than of the game.
+    pub fn is_rpc(&self) -> bool {
+        match self {
+            SolanaError::RetriesExhausted { last, .. } => last.is_rpc(),
+            _ => matches!(self, SolanaError::RpcUnreachable(_) | SolanaError::Rpc(_)),
+        }
+    }
+
+    /// True when trying again with a fresh blockhash may succeed: the endpoint
+    /// was unreachable or the blockhash expired. Program rejections and the
+    /// like fail the same way every time.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            SolanaError::RpcUnreachable(_) | SolanaError::BlockhashExpired
+        )
+    }
+
+    /// Maps known program error codes to a recovery. Moves are checked against
+    /// the board before sending, so `InvalidInstructionData` from the program
+    /// means the square was eaten after our read, not that the move was bad.
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            SolanaError::ProgramRejected { code, .. }
+                if *code == u64::from(ProgramError::InvalidInstructionData) =>
+            {
+                Recovery::Refetch
+            }
+            _ => Recovery::Stop,
+        }
+    }
+
+    fn from_transaction_error(err: &TransactionError) -> Option<Self> {
+        match err {
+            TransactionError::BlockhashNotFound => Some(SolanaError::BlockhashExpired),
+            TransactionError::InsufficientFundsForFee
+            | TransactionError::InsufficientFundsForRent { .. } => {
+                Some(SolanaError::InsufficientFunds)
+            }
+            TransactionError::InstructionError(_, InstructionError::InsufficientFunds) => {
+                Some(SolanaError::InsufficientFunds)
+            }
+            TransactionError::InstructionError(_, ix_err) => ProgramError::try_from(ix_err.clone())
+                .ok()
+                .map(|e| SolanaError::ProgramRejected {
+                    code: u64::from(e),
+                    logs: Vec::new(),
+                }),
+            _ => None,
+        }
+    }
+
+    /// A program rejection with the program's `logs` attached; anything else
+    /// is returned as it is.
+    fn with_logs(self, logs: Vec<String>) -> Self {
+        match self {
+            SolanaError::ProgramRejected { code, .. } => {
+                SolanaError::ProgramRejected { code, logs }
+            }
+            other => other,
+        }
+    }
+
+    /// `from_transaction_error`, keeping errors it does not recognise as RPC
+    /// transaction errors.
+    fn from_failed_transaction(err: &TransactionError) -> Self {
+        Self::from_transaction_error(err).unwrap_or_else(|| {
+            ClientError::from(ClientErrorKind::TransactionError(err.clone())).into()
+        })
+    }
+}
+
+impl From<ClientError> for SolanaError {
+    fn from(err: ClientError) -> Self {
+        if let Some(tx_err) = err.get_transaction_error() {
+            if let Some(classified) = SolanaError::from_transaction_error(&tx_err) {
+                return classified.with_logs(preflight_logs(&err));
+            }
+        }
+        match err.kind() {
+            ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => {
+                SolanaError::RpcUnreachable(Box::new(err))
+            }
+            // The client asks for the node version before some calls and folds
+            // a transport failure there into a request error.
+            ClientErrorKind::RpcError(RpcError::RpcRequestError(msg))
+                if msg.starts_with("cluster version query failed") =>
+            {
+                SolanaError::RpcUnreachable(Box::new(err))
+            }
+            _ => SolanaError::Rpc(Box::new(err)),
+        }
+    }
+}
+
+impl fmt::Display for SolanaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolanaError::AccountMissing(key) => write!(f, "account {} does not exist", key),
+            SolanaError::RpcUnreachable(e) => write!(f, "RPC unreachable: {}", e),
+            SolanaError::BlockhashExpired => {
+                write!(f, "blockhash expired before the transaction landed")
+            }
+            SolanaError::InsufficientFunds => write!(f, "insufficient funds for fee or rent"),
+            SolanaError::ProgramRejected { code, logs } => {
+                write!(f, "program rejected the move (code {:#x})", code)?;
+                match errors::describe(*code, logs) {
+                    Some(reason) => write!(f, ": {}", reason),
+                    None => Ok(()),
+                }
+            }
+            SolanaError::ConfirmationTimeout { sig, waited } => write!(
+                f,
+                "{} not confirmed after {:?}; check it on an explorer before resending",
+                sig, waited
+            ),
+            SolanaError::WrongOwner { key, owner } => {
+                write!(
+                    f,
+                    "account {} is owned by {}, not the game program",
+                    key, owner
+                )
+            }
+            SolanaError::UnknownLayout { key, len, layout } => {
+                write!(
+                    f,
+                    "account {} holds {} bytes, which do not match game layout {}; \
+                     the program may have been upgraded (see --account-version, --board-layout)",
+                    key, len, layout
+                )
+            }
+            SolanaError::InvalidBoard { key, reason } => {
+                write!(f, "account {} does not hold a valid board: {}", key, reason)
+            }
+            SolanaError::InvalidMove { r, c, reason } => {
+                write!(f, "invalid move ({},{}): {}", r, c, reason)
+            }
+            SolanaError::FeeAboveCap { fee, cap } => write!(
+                f,
+                "estimated fee of {} lamports is over the {} lamport cap; not sending",
+                fee, cap
+            ),
+            SolanaError::LowBalance {
+                payer,
+                balance,
+                min,
+            } => write!(
+                f,
+                "payer {} holds {} lamports, under the {} lamport minimum for fees and rent",
+                payer, balance, min
+            ),
+            SolanaError::Rpc(e) => write!(f, "RPC error: {}", e),
+            SolanaError::RetriesExhausted { attempts, last } => {
+                write!(f, "gave up after {} attempts: {}", attempts, last)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolanaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SolanaError::RpcUnreachable(e) | SolanaError::Rpc(e) => Some(e.as_ref()),
+            SolanaError::RetriesExhausted { last, .. } => Some(last.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// How `send_transaction` asks the RPC to handle a send.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendSettings {
+    pub skip_preflight: bool,
+    /// Commitment the preflight simulation reads at; `None` uses the read commitment.
+    pub preflight_commitment: Option<CommitmentConfig>,
+    /// Rebroadcasts the RPC node may make; `None` leaves it to the node.
+    pub max_retries: Option<usize>,
+}
+
+impl SendSettings {
+    fn rpc_config(self, reads: CommitmentConfig) -> RpcSendTransactionConfig {
+        RpcSendTransactionConfig {
+            skip_preflight: self.skip_preflight,
+            preflight_commitment: Some(self.preflight_commitment.unwrap_or(reads).commitment),
+            max_retries: self.max_retries,
+            ..RpcSendTransactionConfig::default()
+        }
+    }
+}
+
+/// Which cluster an RPC URL points at, going by well-known substrings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+    Localnet,
+    /// A local fork of mainnet (`--fork`): mainnet programs and accounts, but
+    /// nothing sent there reaches the real cluster.
+    Fork,
+    /// A custom endpoint; callers should assume it may be mainnet.
+    Unknown,
+}
+
+impl Cluster {
+    pub fn infer(url: &str) -> Self {
+        let url = url.to_ascii_lowercase();
+        if url.contains("devnet") {
+            Cluster::Devnet
+        } else if url.contains("testnet") {
+            Cluster::Testnet
+        } else if url.contains("mainnet") {
+            Cluster::Mainnet
+        } else if url.contains("127.0.0.1") || url.contains("localhost") {
+            Cluster::Localnet
+        } else {
+            Cluster::Unknown
+        }
+    }
+
+    /// Solana Explorer page for `sig`. Local and unknown endpoints are passed
+    /// to the explorer as a custom cluster URL.
+    pub fn explorer_tx_url(self, sig: &Signature, rpc_url: &str) -> String {
+        let base = format!("https://explorer.solana.com/tx/{}", sig);
+        match self {
+            Cluster::Mainnet => base,
+            Cluster::Devnet => format!("{}?cluster=devnet", base),
+            Cluster::Testnet => format!("{}?cluster=testnet", base),
+            Cluster::Localnet | Cluster::Fork | Cluster::Unknown => {
+                format!(
+                    "{}?cluster=custom&customUrl={}",
+                    base,
+                    percent_encode(rpc_url)
+                )
+            }
+        }
+    }
+}
+
+/// `s` as a URL query value: everything but the RFC 3986 unreserved
+/// characters is percent-encoded, so a `?`, `&` or `#` in it stays inside
+/// the value.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(char::from(byte))
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// Compute-unit price attached to each transaction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PriorityFee {
+    None,
+    /// A fixed price in micro-lamports per compute unit.
+    Fixed(u64),
+    /// This percentile of the fees recently paid for the accounts we write,
+    /// re-queried every `PRIORITY_FEE_REFRESH`.
+    Auto {
+        percentile: u32,
+    },
+}
+
+/// How often `submit` tries a transaction again after a transient error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts after the first one.
+    pub max_retries: u32,
+    /// Pause before the first retry; each later one doubles it.
+    pub base: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 2,
+            base: Duration::from_millis(500),
+        }
+    }
+}
+
+/// How long a sent transaction is watched, from `--confirm-timeout-ms` and
+/// `--confirm-poll-ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmPolicy {
+    /// Give up on a confirmation after this long.
+    pub timeout: Duration,
+    /// Pause between status checks.
+    pub poll: Duration,
+}
+
+impl Default for ConfirmPolicy {
+    fn default() -> Self {
+        ConfirmPolicy {
+            timeout: Duration::from_secs(15),
+            poll: Duration::from_millis(500),
+        }
+    }
+}
+
+impl ConfirmPolicy {
+    /// The pause before the next check when `elapsed` has already gone by, or
+    /// `None` once the timeout is reached. The last pause is cut short so the
+    /// final check lands on the timeout.
+    pub fn next_pause(&self, elapsed: Duration) -> Option<Duration> {
+        let left = self.timeout.checked_sub(elapsed).filter(|d| !d.is_zero())?;
+        Some(left.min(self.poll))
+    }
+}
+
+impl RetryPolicy {
+    /// Pause before retry number `retry` (0 for the first): `base * 2^retry`,
+    /// jittered down to as little as half so that clients failing together do
+    /// not come back together.
+    pub fn delay(&self, retry: u32, rng: &mut impl Rng) -> Duration {
+        let full = self.base.saturating_mul(1 << retry.min(16));
+        full.mul_f64(rng.gen_range(0.5..=1.0))
+    }
+}
+
+/// The primary RPC plus an optional `--rpc-fallback`. Each call goes to the
+/// primary first and is repeated once on the fallback when the primary is
+/// unreachable. The fallback does not get its own retry loop: it shares the
+/// resubmit budget of `submit`.
+pub struct MultiRpc {
+    primary: RpcClient,
+    fallback: Option<RpcClient>,
+    send: SendSettings,
+    simulate_first: bool,
+    dry_run: bool,
+    fork: bool,
+    account_layout: AccountLayout,
+    board_layout: Option<BoardLayout>,
+    priority_fee: PriorityFee,
+    compute_unit_limit: Option<u32>,
+    max_fee: Option<u64>,
+    retry: RetryPolicy,
+    confirm: ConfirmPolicy,
+    /// Last `PriorityFee::Auto` price and when it was computed.
+    auto_fee: Cell<Option<(Instant, u64)>>,
+    /// Fees of the transactions `submit` saw confirm.
+    lamports_spent: Cell<u64>,
+    /// The blockhash `submit` last signed with.
+    blockhash: RefCell<Option<RecentBlockhash>>,
+}
+
+/// A blockhash `submit` may sign with again, and what it already signed.
+#[derive(Debug)]
+struct RecentBlockhash {
+    hash: Hash,
+    last_valid_block_height: u64,
+    fetched: Instant,
+    /// The same instructions over the same hash sign to the same transaction,
+    /// which the cluster would treat as already processed.
+    signed: Vec<Signature>,
+}
+
+impl MultiRpc {
+    pub fn new(primary: RpcClient, fallback: Option<RpcClient>) -> Self {
+        Self {
+            primary,
+            fallback,
+            send: SendSettings::default(),
+            simulate_first: false,
+            dry_run: false,
+            fork: false,
+            account_layout: AccountLayout::Auto,
+            board_layout: None,
+            priority_fee: PriorityFee::None,
+            compute_unit_limit: None,
+            max_fee: None,
+            retry: RetryPolicy::default(),
+            confirm: ConfirmPolicy::default(),
+            auto_fee: Cell::new(None),
+            lamports_spent: Cell::new(0),
+            blockhash: RefCell::new(None),
+        }
+    }
+
+    pub fn with_priority_fee(mut self, fee: PriorityFee) -> Self {
+        self.priority_fee = fee;
+        self
+    }
+
+    /// Micro-lamport price for a transaction writing `accounts`, if any.
+    fn compute_unit_price(&self, accounts: &[Pubkey]) -> Result<Option<u64>> {
+        let percentile = match self.priority_fee {
+            PriorityFee::None => return Ok(None),
+            PriorityFee::Fixed(price) => return Ok(Some(price)),
+            PriorityFee::Auto { percentile } => percentile,
+        };
+        if let Some((at, price)) = self.auto_fee.get() {
+            if at.elapsed() < PRIORITY_FEE_REFRESH {
+                return Ok(Some(price));
+            }
+        }
+        let fees: Vec<u64> = self
+            .call("get_recent_prioritization_fees", &accounts.len(), |rpc| {
+                Ok(rpc.get_recent_prioritization_fees(accounts)?)
+            })?
+            .iter()
+            .map(|f| f.prioritization_fee)
+            .collect();
+        let price = nearest_rank(&fees, percentile).unwrap_or(0);
+        info!(
+            target: LOG_TARGET,
+            "priority fee: p{} of {} recent slots = {} micro-lamports/CU",
+            percentile,
+            fees.len(),
+            price
+        );
+        self.auto_fee.set(Some((Instant::now(), price)));
+        Ok(Some(price))
+    }
+
+    /// Sends without the RPC's preflight simulation: one round trip less, but a
+    /// bad transaction is only noticed once its status comes back failed.
+    pub fn with_skip_preflight(mut self, skip: bool) -> Self {
+        self.send.skip_preflight = skip;
+        self
+    }
+
+    /// Runs preflight at `commitment` instead of the read commitment.
+    pub fn with_preflight_commitment(mut self, commitment: Option<CommitmentConfig>) -> Self {
+        self.send.preflight_commitment = commitment;
+        self
+    }
+
+    /// Lets the RPC node rebroadcast each send at most `retries` times
+    /// instead of following its own policy.
+    pub fn with_rpc_max_retries(mut self, retries: Option<usize>) -> Self {
+        self.send.max_retries = retries;
+        self
+    }
+
+    /// Requests `limit` compute units per transaction instead of the default.
+    pub fn with_compute_unit_limit(mut self, limit: Option<u32>) -> Self {
+        self.compute_unit_limit = limit;
+        self
+    }
+
+    /// Asks the RPC for each transaction's fee before sending it, and sends
+    /// nothing that would cost more than `cap` lamports.
+    pub fn with_max_fee(mut self, cap: Option<u64>) -> Self {
+        self.max_fee = cap;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_confirm(mut self, confirm: ConfirmPolicy) -> Self {
+        self.confirm = confirm;
+        self
+    }
+
+    /// Simulates every signed transaction before sending it, and sends nothing
+    /// when the simulation fails.
+    pub fn with_simulate_first(mut self, simulate: bool) -> Self {
+        self.simulate_first = simulate;
+        self
+    }
+
+    /// Logs each signed transaction instead of sending it. Reads, blockhashes
+    /// and `--simulate-first` simulations still go to the RPC.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Marks the primary as a local mainnet fork, whatever its URL says.
+    pub fn with_fork(mut self, fork: bool) -> Self {
+        self.fork = fork;
+        self
+    }
+
+    pub fn with_account_layout(mut self, layout: AccountLayout) -> Self {
+        self.account_layout = layout;
+        self
+    }
+
+    /// Reads the board at a fixed offset instead; overrides `with_account_layout`.
+    pub fn with_board_layout(mut self, layout: Option<BoardLayout>) -> Self {
+        self.board_layout = layout;
+        self
+    }
+
+    /// Lamports paid in fees by the transactions sent through this client
+    /// that confirmed.
+    pub fn lamports_spent(&self) -> u64 {
+        self.lamports_spent.get()
+    }
+
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.primary.commitment()
+    }
+
+    /// Cluster of the primary endpoint.
+    pub fn cluster(&self) -> Cluster {
+        if self.fork {
+            return Cluster::Fork;
+        }
+        Cluster::infer(&self.primary.url())
+    }
+
+    fn explorer_link(&self, sig: &Signature) -> String {
+        self.cluster().explorer_tx_url(sig, &self.primary.url())
+    }
+
+    fn call<T: fmt::Debug>(
+        &self,
+        method: &str,
+        args: &dyn fmt::Display,
+        f: impl Fn(&RpcClient) -> Result<T>,
+    ) -> Result<T> {
+        match attempt(&self.primary, method, args, &f) {
+            Err(e @ SolanaError::RpcUnreachable(_)) => {
+                let Some(fallback) = &self.fallback else {
+                    return Err(e);
+                };
+                warn!(target: LOG_TARGET,
+                    "{} failed on {} ({}); trying {}",
+                    method,
+                    self.primary.url(),
+                    e,
+                    fallback.url()
+                );
+                let v = attempt(fallback, method, args, &f)?;
+                info!(target: LOG_TARGET, "{} served by fallback {}", method, fallback.url());
+                Ok(v)
+            }
+            out => out,
+        }
+    }
+
+    pub fn get_account(&self, key: &Pubkey) -> Result<Account> {
+        self.call("get_account", key, |rpc| {
+            rpc.get_account(key)
+                .map_err(|e| classify_fetch_error(key, e))
+        })
+    }
+
+    /// The accounts at `keys`, in order, `None` where one does not exist.
+    pub fn get_multiple_accounts(&self, keys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        self.call("get_multiple_accounts", &keys.len(), |rpc| {
+            Ok(rpc.get_multiple_accounts(keys)?)
+        })
+    }
+
+    /// The accounts `program_id` owns, only those `data_len` bytes long when given.
+    pub fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        data_len: Option<u64>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        self.call("get_program_accounts", program_id, |rpc| {
+            let config = RpcProgramAccountsConfig {
+                filters: data_len.map(|len| vec![RpcFilterType::DataSize(len)]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(rpc.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                with_context: None,
+            };
+            Ok(rpc.get_program_accounts_with_config(program_id, config)?)
+        })
+    }
+
+    pub fn get_genesis_hash(&self) -> Result<Hash> {
+        self.call("get_genesis_hash", &"", |rpc| Ok(rpc.get_genesis_hash()?))
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        self.call("get_latest_blockhash", &"", |rpc| {
+            Ok(rpc.get_latest_blockhash()?)
+        })
+    }
+
+    /// The blockhash `submit` last signed with while it stays valid for
+    /// `BLOCKHASH_MARGIN` more blocks, else a fresh one. When the block
+    /// height cannot be read, a hash younger than `BLOCKHASH_REUSE` is kept.
+    fn recent_blockhash(&self) -> Result<Hash> {
+        let cached = self
+            .blockhash
+            .borrow()
+            .as_ref()
+            .map(|recent| (recent.hash, recent.last_valid_block_height, recent.fetched));
+        if let Some((hash, last_valid_block_height, fetched)) = cached {
+            let live = match self.call("get_block_height", &"", |rpc| {
+                Ok(rpc.get_block_height_with_commitment(rpc.commitment())?)
+            }) {
+                Ok(height) => height.saturating_add(BLOCKHASH_MARGIN) < last_valid_block_height,
+                Err(e) => {
+                    debug!(target: LOG_TARGET, "cannot read the block height ({}); going by the blockhash's age", e);
+                    fetched.elapsed() < BLOCKHASH_REUSE
+                }
+            };
+            if live {
+                debug!(target: LOG_TARGET,
+                    "reusing blockhash {} (valid through block {})",
+                    hash,
+                    last_valid_block_height
+                );
+                return Ok(hash);
+            }
+        }
+        let (hash, last_valid_block_height) = self.call("get_latest_blockhash", &"", |rpc| {
+            Ok(rpc.get_latest_blockhash_with_commitment(rpc.commitment())?)
+        })?;
+        *self.blockhash.borrow_mut() = Some(RecentBlockhash {
+            hash,
+            last_valid_block_height,
+            fetched: Instant::now(),
+            signed: Vec::new(),
+        });
+        Ok(hash)
+    }
+
+    /// Records `tx` as signed over the cached blockhash; false when an
+    /// identical transaction already was.
+    fn claim_signature(&self, tx: &Transaction) -> bool {
+        let mut recent = self.blockhash.borrow_mut();
+        let Some(recent) = recent
+            .as_mut()
+            .filter(|r| r.hash == tx.message.recent_blockhash)
+        else {
+            return true;
+        };
+        if recent.signed.contains(&tx.signatures[0]) {
+            return false;
+        }
+        recent.signed.push(tx.signatures[0]);
+        true
+    }
+
+    fn forget_blockhash(&self) {
+        self.blockhash.take();
+    }
+
+    /// Sends the already-signed `tx`, so a fallback resubmission carries the same signature.
+    pub fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        trace!(target: LOG_TARGET, "send_transaction body: {:?}", tx);
+        self.call("send_transaction", &tx.signatures[0], |rpc| {
+            let config = self.send.rpc_config(rpc.commitment());
+            Ok(rpc.send_transaction_with_config(tx, config)?)
+        })
+        .inspect_err(log_rejection)
+    }
+
+    /// Runs `tx` through the RPC's simulator; the result carries its error,
+    /// if any, and the program logs.
+    pub fn simulate_transaction(&self, tx: &Transaction) -> Result<RpcSimulateTransactionResult> {
+        self.call("simulate_transaction", &tx.signatures[0], |rpc| {
+            Ok(rpc.simulate_transaction(tx)?.value)
+        })
+    }
+
+    pub fn get_balance(&self, key: &Pubkey) -> Result<u64> {
+        self.call("get_balance", key, |rpc| Ok(rpc.get_balance(key)?))
+    }
+
+    pub fn request_airdrop(&self, to: &Pubkey, lamports: u64) -> Result<Signature> {
+        self.call("request_airdrop", to, |rpc| {
+            Ok(rpc.request_airdrop(to, lamports)?)
+        })
+    }
+
+    pub fn get_fee_for_message(&self, tx: &Transaction) -> Result<u64> {
+        self.call("get_fee_for_message", &tx.signatures[0], |rpc| {
+            Ok(rpc.get_fee_for_message(&tx.message)?)
+        })
+    }
+
+    pub fn get_signature_statuses(
+        &self,
+        sigs: &[Signature],
+    ) -> Result<Vec<Option<TransactionStatus>>> {
+        let first = sigs.first().map(ToString::to_string).unwrap_or_default();
+        self.call("get_signature_statuses", &first, |rpc| {
+            Ok(rpc.get_signature_statuses(sigs)?.value)
+        })
+    }
+
+    pub fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool> {
+        self.call("is_blockhash_valid", blockhash, |rpc| {
+            Ok(rpc.is_blockhash_valid(blockhash, rpc.commitment())?)
+        })
+    }
+}
+
+/// Runs one call against `rpc`, logging the method, its argument, the endpoint,
+/// timing and outcome at `debug`, and the full response at `trace`.
+fn attempt<T: fmt::Debug>(
+    rpc: &RpcClient,
+    method: &str,
+    args: &dyn fmt::Display,
+    f: &impl Fn(&RpcClient) -> Result<T>,
+) -> Result<T> {
+    let started = Instant::now();
+    let out = f(rpc);
+    log_attempt(&rpc.url(), method, args, started.elapsed(), &out);
+    out
+}
+
+fn log_attempt<T: fmt::Debug>(
+    url: &str,
+    method: &str,
+    args: &dyn fmt::Display,
+    elapsed: Duration,
+    out: &Result<T>,
+) {
+    match out {
+        Ok(v) => {
+            debug!(target: LOG_TARGET,
+                "rpc {}({}) via {}: ok in {:?}",
+                method,
+                args,
+                url,
+                elapsed
+            );
+            trace!(target: LOG_TARGET, "rpc {} response: {:?}", method, v);
+        }
+        Err(e) => debug!(target: LOG_TARGET,
+            "rpc {}({}) via {}: failed in {:?}: {}",
+            method,
+            args,
+            url,
+            elapsed,
+            e
+        ),
+    }
+}
+
+fn is_account_not_found(err: &ClientError) -> bool {
+    matches!(err.kind(), ClientErrorKind::RpcError(RpcError::ForUser(msg)) if msg.starts_with("AccountNotFound"))
+}
+
+/// What we know about a submitted transaction once polling gives up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    /// The signature reached the client's commitment level without error.
+    Confirmed(Signature),
+    /// Every attempt expired with its blockhash and can no longer land.
+    Dropped,
+    /// Still pending with a live blockhash; it may land later, so do not resend.
+    Unknown(Signature),
+    /// The program refused the move because the square was already eaten,
+    /// usually after a stale read. Nothing landed; re-fetch the board.
+    BoardAdvanced,
+    /// `--dry-run`: the transaction was built and signed, then logged instead
+    /// of sent.
+    DryRun(Signature),
+}
+
+/// True when `rpc` serves mainnet-beta. Well-known URLs decide it directly;
+/// for a custom endpoint the genesis hash is asked for instead. A fork shares
+/// mainnet's genesis hash, so it is never asked.
+pub fn is_mainnet(rpc: &MultiRpc) -> Result<bool> {
+    match rpc.cluster() {
+        Cluster::Mainnet => Ok(true),
+        Cluster::Unknown => Ok(rpc.get_genesis_hash()?.to_string() == MAINNET_GENESIS_HASH),
+        Cluster::Devnet | Cluster::Testnet | Cluster::Localnet | Cluster::Fork => Ok(false),
+    }
+}
 
 pub fn get_game_pda(program_id: &Pubkey, player: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[player.as_ref()], program_id)
 }
 
-pub fn fetch_board(rpc: &RpcClient, game_pda: &Pubkey) -> Result<Option<[u8; 5]>> {
+/// Byte layout of the game account, chosen with `--account-version`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AccountLayout {
+    /// Tell `V1` and `V2` apart by data length.
+    Auto,
+    /// The deployed program: the 5-byte board and 3 bytes of padding.
+    V1,
+    /// A version-tagged account: byte 0 is `2`, the board is bytes 1..6, 16 bytes in all.
+    V2,
+}
+
+impl AccountLayout {
+    const V1_LEN: usize = 8;
+    const V2_LEN: usize = 16;
+    const V2_TAG: u8 = 2;
+
+    /// The data lengths `parse` accepts.
+    fn lens(self) -> &'static [usize] {
+        match self {
+            AccountLayout::Auto => &[Self::V1_LEN, Self::V2_LEN],
+            AccountLayout::V1 => &[Self::V1_LEN],
+            AccountLayout::V2 => &[Self::V2_LEN],
+        }
+    }
+
+    /// The board in `data`, or `None` when `data` is not laid out as `self`.
+    /// Lengths must match exactly so that a changed layout is never misread.
+    pub fn parse(self, data: &[u8]) -> Option<[u8; 5]> {
+        let layout = match self {
+            AccountLayout::Auto => match data.len() {
+                Self::V1_LEN => AccountLayout::V1,
+                Self::V2_LEN => AccountLayout::V2,
+                _ => return None,
+            },
+            layout => layout,
+        };
+        let board = match layout {
+            AccountLayout::V1 if data.len() == Self::V1_LEN => &data[..5],
+            AccountLayout::V2 if data.len() == Self::V2_LEN && data[0] == Self::V2_TAG => {
+                &data[1..6]
+            }
+            _ => return None,
+        };
+        board.try_into().ok()
+    }
+}
+
+/// Where the board sits in the account data, as `offset=N,rows=5`, for a
+/// program that puts a discriminator or header in front of it. Bytes past the
+/// board are ignored.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BoardLayout {
+    pub offset: usize,
+    pub rows: usize,
+}
+
+impl BoardLayout {
+    pub fn slice(self, data: &[u8]) -> Option<[u8; 5]> {
+        data.get(self.offset..self.offset.checked_add(self.rows)?)?
+            .try_into()
+            .ok()
+    }
+}
+
+impl fmt::Display for BoardLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset={},rows={}", self.offset, self.rows)
+    }
+}
+
+impl FromStr for BoardLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut layout = BoardLayout { offset: 0, rows: 5 };
+        for part in s.split(',') {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got `{}`", part))?;
+            let value: usize = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("bad number in `{}`", part))?;
+            match key.trim() {
+                "offset" => layout.offset = value,
+                "rows" => layout.rows = value,
+                other => return Err(format!("unknown board layout key `{}`", other)),
+            }
+        }
+        if layout.rows != 5 {
+            return Err(format!(
+                "only 5-row boards are supported, got rows={}",
+                layout.rows
+            ));
+        }
+        Ok(layout)
+    }
+}
+
+/// What a game account holds beyond the board. The deployed program keeps
+/// no wager, turn counter or opponent in its data, only the board and
+/// padding, so the stake is the account's lamports: the deposit that opened
+/// the game plus any top-up, all paid out when the game ends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GameState {
+    pub board: [u8; 5],
+    /// Lamports held by the game account.
+    pub stake: u64,
+}
+
+impl GameState {
+    /// Squares eaten by either side so far, which stands in for the turn
+    /// count the account does not keep.
+    pub fn eaten(&self) -> u32 {
+        self.board.iter().map(|row| row.count_ones()).sum()
+    }
+}
+
+/// Reads the board stored in `game_pda`; see `fetch_game_state`.
+pub fn fetch_board(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+) -> Result<Option<[u8; 5]>> {
+    Ok(fetch_game_state(rpc, program_id, game_pda)?.map(|state| state.board))
+}
+
+/// Reads the game held by `game_pda`. An account that is not owned by
+/// `program_id` is refused rather than decoded, since `--game-pda` lets the
+/// caller point us at any address. Data that fits no known layout is an error
+/// too, rather than a guess. Only an account the RPC reports as not found
+/// reads as `None`; transient failures are retried under the `RetryPolicy`
+/// and then returned, so an outage never looks like a closed game.
+pub fn fetch_game_state(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+) -> Result<Option<GameState>> {
+    retrying_read(rpc, game_pda, || {
+        fetch_game_state_once(rpc, program_id, game_pda)
+    })
+}
+
+/// The boards of several game accounts, read as `fetch_board` reads one, in
+/// one `get_multiple_accounts` call per `MAX_MULTIPLE_ACCOUNTS` keys. Missing
+/// accounts read as `None`; any account that fails the ownership or layout
+/// checks fails the whole call.
+pub fn fetch_boards(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    game_pdas: &[Pubkey],
+) -> Result<Vec<Option<[u8; 5]>>> {
+    let mut boards = Vec::with_capacity(game_pdas.len());
+    for keys in game_pdas.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let what = format!("{} game accounts", keys.len());
+        let accounts = retrying_read(rpc, &what, || rpc.get_multiple_accounts(keys))?;
+        for (key, acc) in keys.iter().zip(accounts) {
+            let board = match acc {
+                Some(acc) => {
+                    read_board_account(program_id, key, &acc, rpc.account_layout, rpc.board_layout)?
+                }
+                None => None,
+            };
+            boards.push(board);
+        }
+    }
+    Ok(boards)
+}
+
+/// Every open game account of `program_id`, by address, for `list-games`. The
+/// RPC is only asked for accounts of a length `--account-version` reads, in one
+/// `get_program_accounts` call per length; `--board-layout` says nothing about
+/// the length, so then every account the program owns is fetched. An account
+/// that does not decode to a valid board is logged and left out, so one odd
+/// account cannot hide the rest.
+pub fn list_games(rpc: &MultiRpc, program_id: &Pubkey) -> Result<Vec<(Pubkey, GameState)>> {
+    let lens: Vec<Option<u64>> = match rpc.board_layout {
+        Some(_) => vec![None],
+        None => rpc
+            .account_layout
+            .lens()
+            .iter()
+            .map(|&len| Some(len as u64))
+            .collect(),
+    };
+    let mut games = Vec::new();
+    for len in lens {
+        let what = format!("accounts of {}", program_id);
+        let accounts = retrying_read(rpc, &what, || rpc.get_program_accounts(program_id, len))?;
+        for (key, acc) in accounts {
+            match read_game_state(program_id, &key, &acc, rpc.account_layout, rpc.board_layout) {
+                Ok(Some(state)) => games.push((key, state)),
+                Ok(None) => {}
+                Err(e) => warn!(target: LOG_TARGET, "skipping {}: {}", key, e),
+            }
+        }
+    }
+    games.sort_by_key(|(key, _)| *key);
+    Ok(games)
+}
+
+/// Runs the read `f` of `what`, retrying transient errors under the
+/// `RetryPolicy` and then giving up with `RetriesExhausted`.
+fn retrying_read<T>(
+    rpc: &MultiRpc,
+    what: &dyn fmt::Display,
+    f: impl Fn() -> Result<T>,
+) -> Result<T> {
+    let policy = rpc.retry;
+    let mut retry = 0;
+    loop {
+        match f() {
+            Err(e) if e.is_transient() && retry < policy.max_retries => {
+                let delay = policy.delay(retry, &mut rand::thread_rng());
+                warn!(target: LOG_TARGET, "reading {} failed: {}; retrying in {:?}", what, e, delay);
+                thread::sleep(delay);
+                retry += 1;
+            }
+            Err(e) if retry > 0 => {
+                return Err(SolanaError::RetriesExhausted {
+                    attempts: retry + 1,
+                    last: Box::new(e),
+                })
+            }
+            other => return other,
+        }
+    }
+}
+
+fn fetch_game_state_once(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+) -> Result<Option<GameState>> {
     match rpc.get_account(game_pda) {
-        Ok(acc) if acc.data.len() >= 5 => {
-            let mut s = [0u8; 5];
-            s.copy_from_slice(&acc.data[..5]);
-            Ok(Some(s))
+        Ok(acc) => read_game_state(
+            program_id,
+            game_pda,
+            &acc,
+            rpc.account_layout,
+            rpc.board_layout,
+        ),
+        Err(SolanaError::AccountMissing(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// The board held by `acc`, the account at `game_pda`: `None` once it has
+/// been emptied, an error when another program owns it.
+fn read_board_account(
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+    acc: &Account,
+    account: AccountLayout,
+    board: Option<BoardLayout>,
+) -> Result<Option<[u8; 5]>> {
+    Ok(read_game_state(program_id, game_pda, acc, account, board)?.map(|state| state.board))
+}
+
+/// `read_board_account` with the stake kept.
+fn read_game_state(
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+    acc: &Account,
+    account: AccountLayout,
+    board: Option<BoardLayout>,
+) -> Result<Option<GameState>> {
+    if acc.data.is_empty() {
+        Ok(None)
+    } else if acc.owner != *program_id {
+        Err(SolanaError::WrongOwner {
+            key: *game_pda,
+            owner: acc.owner,
+        })
+    } else {
+        let board = decode_board(game_pda, &acc.data, account, board)?;
+        Ok(Some(GameState {
+            board,
+            stake: acc.lamports,
+        }))
+    }
+}
+
+/// What `BoardWatch::next_change` heard.
+pub enum Push {
+    /// The pushed board, `Ok(None)` once the account was closed.
+    Board(Result<Option<[u8; 5]>>),
+    /// Nothing usable arrived in time, so the caller should fetch instead.
+    Quiet,
+    /// The subscription ended; no more pushes will come.
+    Lost,
+}
+
+/// `--ws`: the game account as `account_subscribe` pushes it, read the same
+/// way as `fetch_board`, so autoplay can react to a change instead of
+/// polling for it.
+pub struct BoardWatch {
+    subscription: AccountSubscription,
+    program_id: Pubkey,
+    game_pda: Pubkey,
+    account_layout: AccountLayout,
+    board_layout: Option<BoardLayout>,
+}
+
+impl BoardWatch {
+    pub fn subscribe(
+        url: &str,
+        rpc: &MultiRpc,
+        program_id: &Pubkey,
+        game_pda: &Pubkey,
+    ) -> std::result::Result<Self, Box<PubsubClientError>> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(rpc.commitment()),
+            ..RpcAccountInfoConfig::default()
+        };
+        let subscription =
+            PubsubClient::account_subscribe(url, game_pda, Some(config)).map_err(Box::new)?;
+        info!(target: LOG_TARGET, "watching {} over {}", game_pda, url);
+        Ok(BoardWatch {
+            subscription,
+            program_id: *program_id,
+            game_pda: *game_pda,
+            account_layout: rpc.account_layout,
+            board_layout: rpc.board_layout,
+        })
+    }
+
+    /// Drops the pushes already queued, so the next one is newer than this call.
+    pub fn drain(&self) {
+        let stale = self.subscription.1.try_iter().count();
+        if stale > 0 {
+            trace!(target: LOG_TARGET, "dropped {} queued pushes of {}", stale, self.game_pda);
         }
-        Ok(_) => Ok(None),
-        Err(_) => Ok(None),
+    }
+
+    /// The next pushed board, waiting at most `timeout`.
+    pub fn next_change(&self, timeout: Duration) -> Push {
+        let update = match self.subscription.1.recv_timeout(timeout) {
+            Ok(update) => update,
+            Err(e) if e.is_timeout() => return Push::Quiet,
+            Err(_) => return Push::Lost,
+        };
+        debug!(target: LOG_TARGET, "{} changed at slot {}", self.game_pda, update.context.slot);
+        let Some(acc) = update.value.decode::<Account>() else {
+            warn!(target: LOG_TARGET, "cannot decode the pushed {}; fetching it instead", self.game_pda);
+            return Push::Quiet;
+        };
+        Push::Board(read_board_account(
+            &self.program_id,
+            &self.game_pda,
+            &acc,
+            self.account_layout,
+            self.board_layout,
+        ))
+    }
+}
+
+/// Asks the cluster's faucet for `lamports` to `to` and waits for the airdrop
+/// to confirm like one of our own transactions.
+pub fn airdrop(rpc: &MultiRpc, to: &Pubkey, lamports: u64) -> Result<Signature> {
+    let bh = rpc.get_latest_blockhash()?;
+    let sig = rpc.request_airdrop(to, lamports)?;
+    info!(target: LOG_TARGET, "airdrop of {} lamports requested: {}", lamports, sig);
+    match poll_signature(rpc, &sig, &bh)? {
+        SendOutcome::Confirmed(sig) => Ok(sig),
+        SendOutcome::Unknown(sig) => Err(SolanaError::ConfirmationTimeout {
+            sig,
+            waited: rpc.confirm.timeout,
+        }),
+        _ => Err(SolanaError::BlockhashExpired),
+    }
+}
+
+/// The payer's balance, or `LowBalance` when it is under `min` lamports.
+pub fn check_balance(rpc: &MultiRpc, payer: &Pubkey, min: u64) -> Result<u64> {
+    let balance = rpc.get_balance(payer)?;
+    debug!(target: LOG_TARGET, "payer {} holds {} lamports (minimum {})", payer, balance, min);
+    if balance < min {
+        return Err(SolanaError::LowBalance {
+            payer: *payer,
+            balance,
+            min,
+        });
+    }
+    Ok(balance)
+}
+
+/// Lamports held by `game_pda`, which a win pays back; 0 once it is closed.
+pub fn fetch_pot(rpc: &MultiRpc, game_pda: &Pubkey) -> Result<u64> {
+    match rpc.get_account(game_pda) {
+        Ok(acc) => Ok(acc.lamports),
+        Err(SolanaError::AccountMissing(_)) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Estimated lamports for one move transaction: the signature fee plus the
+/// priority fee at the price `submit` would attach right now.
+pub fn estimate_move_fee(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    player: &Pubkey,
+    game_pda: &Pubkey,
+) -> Result<u64> {
+    let ix = make_move_ix(program_id, player, game_pda, fee_collector, 1, 1)?;
+    let price = rpc.compute_unit_price(&writable_accounts(&[ix]))?;
+    Ok(transaction_fee(rpc.compute_unit_limit, price, 1))
+}
+
+/// Lamports charged for a transaction of `instructions` instructions behind
+/// `budgeted(limit, price, ..)`: the signature fee plus the priority fee. With
+/// no limit, each instruction is billed at the runtime's default allowance.
+fn transaction_fee(limit: Option<u32>, price: Option<u64>, instructions: usize) -> u64 {
+    let units = limit.map_or(DEFAULT_COMPUTE_UNITS * instructions as u64, u64::from);
+    LAMPORTS_PER_SIGNATURE + priority_fee_lamports(price.unwrap_or(0), units)
+}
+
+/// Lamports paid for `units` compute units at `price` micro-lamports each.
+fn priority_fee_lamports(price: u64, units: u64) -> u64 {
+    (u128::from(price) * u128::from(units)).div_ceil(1_000_000) as u64
+}
+
+/// The board in `data`: through `--board-layout` when given, else the
+/// `--account-version` layout. Either way it must pass `validate_board`.
+fn decode_board(
+    key: &Pubkey,
+    data: &[u8],
+    account: AccountLayout,
+    board: Option<BoardLayout>,
+) -> Result<[u8; 5]> {
+    let (rows, layout) = match board {
+        Some(board) => (board.slice(data), board.to_string()),
+        None => (account.parse(data), format!("{:?}", account)),
+    };
+    let rows = rows.ok_or(SolanaError::UnknownLayout {
+        key: *key,
+        len: data.len(),
+        layout,
+    })?;
+    validate_board(rows).map_err(|reason| SolanaError::InvalidBoard { key: *key, reason })?;
+    Ok(rows)
+}
+
+fn classify_fetch_error(key: &Pubkey, err: ClientError) -> SolanaError {
+    if is_account_not_found(&err) {
+        SolanaError::AccountMissing(*key)
+    } else {
+        err.into()
     }
 }
 
 pub fn send_move(
-    rpc: &RpcClient,
+    rpc: &MultiRpc,
     program_id: &Pubkey,
     fee_collector: &Pubkey,
     payer: &Keypair,
     game_pda: &Pubkey,
     r: u8,
     c: u8,
-) -> Result<()> {
+) -> Result<SendOutcome> {
     let ix = make_move_ix(program_id, &payer.pubkey(), game_pda, fee_collector, r, c)?;
-    let bh = rpc.get_latest_blockhash().context("fetch blockhash")?;
-    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], bh);
-    let sig = rpc.send_and_confirm_transaction(&tx).context("send tx")?;
-    info!("✅ Sent move ({},{}): {}", r, c, sig);
+    let outcome = match submit(rpc, payer, &[ix]) {
+        Err(e) if e.recovery() == Recovery::Refetch => SendOutcome::BoardAdvanced,
+        other => other?,
+    };
+    log_move_outcome(&outcome, r, c, |sig| rpc.explorer_link(sig));
+    Ok(outcome)
+}
+
+fn log_move_outcome(outcome: &SendOutcome, r: u8, c: u8, explorer: impl Fn(&Signature) -> String) {
+    match outcome {
+        SendOutcome::Confirmed(sig) => {
+            info!(target: LOG_TARGET, "{} Sent move ({},{}): {}", ok_mark(), r, c, sig);
+            debug!(target: LOG_TARGET, "explorer: {}", explorer(sig));
+        }
+        SendOutcome::Dropped => {
+            warn!(target: LOG_TARGET, "move ({},{}) was dropped — it never landed", r, c)
+        }
+        SendOutcome::Unknown(sig) => {
+            warn!(target: LOG_TARGET, "move ({},{}) not confirmed in time: {}", r, c, sig);
+            warn!(target: LOG_TARGET, "check it before resending: {}", explorer(sig));
+        }
+        SendOutcome::BoardAdvanced => {
+            warn!(target: LOG_TARGET, "move ({},{}) already made / board advanced", r, c)
+        }
+        SendOutcome::DryRun(sig) => {
+            info!(target: LOG_TARGET, "dry run: would send move ({},{}) as {}", r, c, sig)
+        }
+    }
+}
+
+/// Sends several of our moves in one transaction, for self-play and tests on a
+/// localnet. Each move is checked against `board` with the earlier moves of the
+/// batch applied. The program answers every instruction with its own move, and
+/// those replies are not modelled here, so against the real mainnet program a
+/// later move can find its square already eaten and the whole transaction fails.
+pub fn send_moves(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    game_pda: &Pubkey,
+    board: [u8; 5],
+    moves: &[(u8, u8)],
+) -> Result<SendOutcome> {
+    let mut board = board;
+    let mut ixs = Vec::with_capacity(moves.len());
+    for (i, &(r, c)) in moves.iter().enumerate() {
+        ixs.push(make_move_ix(
+            program_id,
+            &payer.pubkey(),
+            game_pda,
+            fee_collector,
+            r,
+            c,
+        )?);
+        if !is_legal_move(board, r, c) {
+            warn!(target: LOG_TARGET, "batched move #{} is already eaten", i + 1);
+            return Err(SolanaError::InvalidMove {
+                r,
+                c,
+                reason: "already eaten earlier in the batch",
+            });
+        }
+        board = apply_move(board, r, c);
+    }
+    let outcome = match submit(rpc, payer, &ixs) {
+        Err(e) if e.recovery() == Recovery::Refetch => SendOutcome::BoardAdvanced,
+        other => other?,
+    };
+    match &outcome {
+        SendOutcome::Confirmed(sig) => {
+            info!(target: LOG_TARGET, "{} Sent {} moves: {}", ok_mark(), moves.len(), sig);
+            debug!(target: LOG_TARGET, "explorer: {}", rpc.explorer_link(sig));
+        }
+        SendOutcome::Dropped => {
+            warn!(target: LOG_TARGET, "batch of {} moves was dropped", moves.len())
+        }
+        SendOutcome::Unknown(sig) => warn!(target: LOG_TARGET, "batch still unconfirmed: {}", sig),
+        SendOutcome::BoardAdvanced => {
+            warn!(target: LOG_TARGET, "batch hit a square the program had already eaten — board advanced")
+        }
+        SendOutcome::DryRun(sig) => {
+            info!(target: LOG_TARGET, "dry run: would send {} moves as {}", moves.len(), sig)
+        }
+    }
+    Ok(outcome)
+}
+
+/// Sends `ixs` and polls its signature status instead of trusting a single
+/// confirm call. A transaction whose blockhash expired without a status is
+/// known to be dropped and gets resubmitted with a fresh blockhash. Transient
+/// errors are retried the same way, after a `RetryPolicy::delay`; other errors
+/// are returned at once.
+fn submit(rpc: &MultiRpc, payer: &Keypair, ixs: &[Instruction]) -> Result<SendOutcome> {
+    let policy = rpc.retry;
+    for attempt in 0..=policy.max_retries {
+        if attempt > 0 {
+            warn!(target: LOG_TARGET,
+                "resubmitting (attempt {}/{})",
+                attempt + 1,
+                policy.max_retries + 1
+            );
+        }
+        let err = match submit_once(rpc, payer, ixs) {
+            // The blockhash has already expired, so there is nothing to wait for.
+            Ok(SendOutcome::Dropped) => continue,
+            Ok(outcome) => return Ok(outcome),
+            Err(e) if e.is_transient() => e,
+            Err(e) => return Err(e),
+        };
+        if attempt == policy.max_retries {
+            return Err(SolanaError::RetriesExhausted {
+                attempts: attempt + 1,
+                last: Box::new(err),
+            });
+        }
+        let delay = policy.delay(attempt, &mut rand::thread_rng());
+        warn!(target: LOG_TARGET, "attempt {} failed: {}; retrying in {:?}", attempt + 1, err, delay);
+        thread::sleep(delay);
+    }
+    Ok(SendOutcome::Dropped)
+}
+
+/// One signed transaction, sent and polled. The blockhash is only kept for
+/// the next one after this one confirmed: a drop or a failure may mean it
+/// expired, so any resubmission signs over a fresh one.
+fn submit_once(rpc: &MultiRpc, payer: &Keypair, ixs: &[Instruction]) -> Result<SendOutcome> {
+    let outcome = sign_and_send(rpc, payer, ixs);
+    if !matches!(
+        outcome,
+        Ok(SendOutcome::Confirmed(_) | SendOutcome::DryRun(_))
+    ) {
+        rpc.forget_blockhash();
+    }
+    outcome
+}
+
+/// Signs `ixs` over the recent blockhash, or over a fresh one when that hash
+/// already signed the same instructions.
+fn sign_recent(rpc: &MultiRpc, payer: &Keypair, ixs: &[Instruction]) -> Result<Transaction> {
+    let sign = |bh| Transaction::new_signed_with_payer(ixs, Some(&payer.pubkey()), &[payer], bh);
+    let tx = sign(rpc.recent_blockhash()?);
+    if rpc.claim_signature(&tx) {
+        return Ok(tx);
+    }
+    rpc.forget_blockhash();
+    let tx = sign(rpc.recent_blockhash()?);
+    rpc.claim_signature(&tx);
+    Ok(tx)
+}
+
+fn sign_and_send(rpc: &MultiRpc, payer: &Keypair, ixs: &[Instruction]) -> Result<SendOutcome> {
+    let price = rpc.compute_unit_price(&writable_accounts(ixs))?;
+    let all = budgeted(rpc.compute_unit_limit, price, ixs);
+    let tx = sign_recent(rpc, payer, &all)?;
+    let bh = tx.message.recent_blockhash;
+    if let Some(cap) = rpc.max_fee {
+        check_fee(rpc.get_fee_for_message(&tx)?, cap, &tx)?;
+    }
+    if rpc.simulate_first {
+        simulate(rpc, &tx)?;
+    }
+    if rpc.dry_run {
+        return Ok(dry_run(&tx));
+    }
+    let outcome = send_and_poll(rpc, &tx, &bh)?;
+    if let SendOutcome::Confirmed(_) = outcome {
+        let fee = transaction_fee(rpc.compute_unit_limit, price, ixs.len());
+        rpc.lamports_spent.set(rpc.lamports_spent.get() + fee);
+    }
+    Ok(outcome)
+}
+
+fn send_and_poll(rpc: &MultiRpc, tx: &Transaction, bh: &Hash) -> Result<SendOutcome> {
+    let sig = match rpc.send_transaction(tx) {
+        Ok(sig) => sig,
+        // The send may have reached the cluster before the connection broke,
+        // so it is only retried once it is known to be dropped. If its status
+        // cannot be read either, it is reported as unknown, never resent.
+        Err(e @ SolanaError::RpcUnreachable(_)) => {
+            let sig = tx.signatures[0];
+            return match poll_signature(rpc, &sig, bh) {
+                Ok(SendOutcome::Dropped) => Err(e),
+                Ok(outcome) => Ok(outcome),
+                Err(_) => Ok(SendOutcome::Unknown(sig)),
+            };
+        }
+        Err(e) => return Err(e),
+    };
+    poll_signature(rpc, &sig, bh)
+}
+
+/// `--max-fee-lamports`: logs the RPC's `fee` for `tx` and refuses it above `cap`.
+fn check_fee(fee: u64, cap: u64, tx: &Transaction) -> Result<()> {
+    info!(target: LOG_TARGET, "fee estimate for {}: {} lamports (cap {})", tx.signatures[0], fee, cap);
+    if fee > cap {
+        return Err(SolanaError::FeeAboveCap { fee, cap });
+    }
     Ok(())
 }
 
+/// `--dry-run`: logs the signed `tx` as the base64 wire bytes
+/// `send_transaction` would have carried.
+fn dry_run(tx: &Transaction) -> SendOutcome {
+    info!(target: LOG_TARGET, "dry run: not sending {}", tx.signatures[0]);
+    info!(target: LOG_TARGET, "dry run: signed transaction (base64): {}", wire_base64(tx));
+    SendOutcome::DryRun(tx.signatures[0])
+}
+
+fn wire_base64(tx: &Transaction) -> String {
+    base64::encode(bincode::serialize(tx).expect("a signed transaction serializes"))
+}
+
+/// `--simulate-first`: runs the exact transaction about to be sent, blockhash
+/// and accounts included. A failure is logged with the program's output and
+/// returned as the error its status would have carried once sent.
+fn simulate(rpc: &MultiRpc, tx: &Transaction) -> Result<()> {
+    let sim = rpc.simulate_transaction(tx)?;
+    let Some(err) = sim.err else {
+        return Ok(());
+    };
+    warn!(target: LOG_TARGET, "simulation of {} failed: {}; not sending it", tx.signatures[0], err);
+    let logs = sim.logs.unwrap_or_default();
+    for line in &logs {
+        warn!(target: LOG_TARGET, "  {}", line);
+    }
+    Err(SolanaError::from_failed_transaction(&err).with_logs(logs))
+}
+
+/// The program's logs from a send the RPC's preflight simulation refused.
+fn preflight_logs(err: &ClientError) -> Vec<String> {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(sim),
+            ..
+        }) => sim.logs.clone().unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Logs what the program said before rejecting a send, which the error's
+/// one-line message leaves out.
+fn log_rejection(err: &SolanaError) {
+    if let SolanaError::ProgramRejected { logs, .. } = err {
+        for line in logs {
+            warn!(target: LOG_TARGET, "  {}", line);
+        }
+    }
+}
+
+/// `ixs`, preceded by the compute-budget instructions the flags ask for.
+fn with_compute_budget(rpc: &MultiRpc, ixs: &[Instruction]) -> Result<Vec<Instruction>> {
+    let price = rpc.compute_unit_price(&writable_accounts(ixs))?;
+    Ok(budgeted(rpc.compute_unit_limit, price, ixs))
+}
+
+/// `ixs` behind a compute-unit limit and then a compute-unit price, each only
+/// when set. The instructions themselves are unchanged.
+fn budgeted(limit: Option<u32>, price: Option<u64>, ixs: &[Instruction]) -> Vec<Instruction> {
+    let mut all = Vec::with_capacity(ixs.len() + 2);
+    all.extend(limit.map(ComputeBudgetInstruction::set_compute_unit_limit));
+    all.extend(price.map(ComputeBudgetInstruction::set_compute_unit_price));
+    all.extend_from_slice(ixs);
+    all
+}
+
+/// Time spent in each phase of one `ping_move`.
+#[derive(Debug, Clone, Copy)]
+pub struct PingTimings {
+    pub blockhash: Duration,
+    pub sign: Duration,
+    pub simulate: Duration,
+}
+
+/// Times the phases of sending move `(r, c)` without sending it: the signed
+/// transaction is only simulated, so nothing lands and no fee is paid. A
+/// failed simulation is logged but still timed.
+pub fn ping_move(
+    rpc: &MultiRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    game_pda: &Pubkey,
+    r: u8,
+    c: u8,
+) -> Result<PingTimings> {
+    let ix = make_move_ix(program_id, &payer.pubkey(), game_pda, fee_collector, r, c)?;
+    let all = with_compute_budget(rpc, &[ix])?;
+
+    let started = Instant::now();
+    let bh = rpc.get_latest_blockhash()?;
+    let blockhash = started.elapsed();
+
+    let started = Instant::now();
+    let tx = Transaction::new_signed_with_payer(&all, Some(&payer.pubkey()), &[payer], bh);
+    let sign = started.elapsed();
+
+    let started = Instant::now();
+    if let Some(err) = rpc.simulate_transaction(&tx)?.err {
+        warn!(target: LOG_TARGET, "simulated move ({},{}) failed: {}", r, c, err);
+    }
+    let simulate = started.elapsed();
+
+    Ok(PingTimings {
+        blockhash,
+        sign,
+        simulate,
+    })
+}
+
+/// Accounts the transaction writes, which is what prioritization fees are keyed on.
+fn writable_accounts(ixs: &[Instruction]) -> Vec<Pubkey> {
+    let mut keys: Vec<Pubkey> = ixs
+        .iter()
+        .flat_map(|ix| ix.accounts.iter())
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Watches `sig` under the `ConfirmPolicy` until it is confirmed, fails, or
+/// its `blockhash` expires unseen; `Unknown` once the timeout runs out.
+fn poll_signature(rpc: &MultiRpc, sig: &Signature, blockhash: &Hash) -> Result<SendOutcome> {
+    let started = Instant::now();
+    loop {
+        let statuses = rpc.get_signature_statuses(&[*sig])?;
+        if let Some(Some(status)) = statuses.first() {
+            if let Some(err) = &status.err {
+                warn!(target: LOG_TARGET, "transaction {} failed: {}", sig, err);
+                return Err(SolanaError::from_failed_transaction(err));
+            }
+            if status.satisfies_commitment(rpc.commitment()) {
+                return Ok(SendOutcome::Confirmed(*sig));
+            }
+        } else if !rpc.is_blockhash_valid(blockhash)? {
+            return Ok(SendOutcome::Dropped);
+        }
+        let Some(pause) = rpc.confirm.next_pause(started.elapsed()) else {
+            return Ok(SendOutcome::Unknown(*sig));
+        };
+        thread::sleep(pause);
+    }
+}
+
 pub fn reset_game_pda(
-    rpc: &RpcClient,
+    rpc: &MultiRpc,
     program_id: &Pubkey,
     fee_collector: &Pubkey,
     payer: &Keypair,
     game_pda: &Pubkey,
 ) -> Result<()> {
-    info!("reset requested: checking current game PDA...");
-    let exists = fetch_board(rpc, game_pda)?.is_some();
+    info!(target: LOG_TARGET, "reset requested: checking current game PDA...");
+    let exists = fetch_board(rpc, program_id, game_pda)?.is_some();
     if !exists {
-        info!("No existing PDA — already fresh.");
+        info!(target: LOG_TARGET, "No existing PDA — already fresh.");
         return Ok(());
     }
 
-    info!("Closing PDA by sending cash-out (0,0)...");
+    info!(target: LOG_TARGET, "Closing PDA by sending cash-out (0,0)...");
     let ix = make_move_ix(program_id, &payer.pubkey(), game_pda, fee_collector, 0, 0)?;
-    let bh = rpc.get_latest_blockhash()?;
-    let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], bh);
-    let sig = rpc.send_and_confirm_transaction(&tx)?;
-    info!("✅ Cash-out tx: {}", sig);
-
-    for i in 0..20 {
-        thread::sleep(Duration::from_millis(500));
-        if fetch_board(rpc, game_pda)?.is_none() {
-            info!("PDA closed ({} checks). Fresh start ready.", i + 1);
+    let pending = match submit(rpc, payer, &[ix])? {
+        SendOutcome::Confirmed(sig) => {
+            info!(target: LOG_TARGET, "{} Cash-out tx: {}", ok_mark(), sig);
+            debug!(target: LOG_TARGET, "explorer: {}", rpc.explorer_link(&sig));
+            None
+        }
+        SendOutcome::Dropped | SendOutcome::BoardAdvanced => {
+            warn!(target: LOG_TARGET, "cash-out was dropped — PDA left untouched");
+            return Err(SolanaError::BlockhashExpired);
+        }
+        SendOutcome::Unknown(sig) => {
+            warn!(target: LOG_TARGET, "cash-out {} still unconfirmed — watching the PDA", sig);
+            Some(sig)
+        }
+        SendOutcome::DryRun(sig) => {
+            info!(target: LOG_TARGET, "dry run: would cash out as {}; PDA left untouched", sig);
+            return Ok(());
+        }
+    };
+
+    let started = Instant::now();
+    let mut checks = 0;
+    while let Some(pause) = rpc.confirm.next_pause(started.elapsed()) {
+        thread::sleep(pause);
+        checks += 1;
+        if fetch_board(rpc, program_id, game_pda)?.is_none() {
+            info!(target: LOG_TARGET, "PDA closed ({} checks). Fresh start ready.", checks);
             return Ok(());
         }
     }
-    warn!("PDA still present after waiting — continuing anyway.");
+    if let Some(sig) = pending {
+        return Err(SolanaError::ConfirmationTimeout {
+            sig,
+            waited: started.elapsed(),
+        });
+    }
+    warn!(target: LOG_TARGET, "PDA still present after waiting — continuing anyway.");
     Ok(())
 }
 
@@ -82,10 +1768,14 @@ fn make_move_ix(
     r: u8,
     c: u8,
 ) -> Result<Instruction> {
-    if !(r == 0 && c == 0) {
-        if !(1 <= r && r <= 5 && 1 <= c && c <= 8) {
-            bail!("r in 1..=5 and c in 1..=8 (or (0,0) to cash out)");
-        }
+    let cash_out = r == 0 && c == 0;
+    let on_board = (1..=5).contains(&r) && (1..=8).contains(&c);
+    if !(cash_out || on_board) {
+        return Err(SolanaError::InvalidMove {
+            r,
+            c,
+            reason: "r in 1..=5 and c in 1..=8 (or (0,0) to cash out)",
+        });
     }
     let data = [(r << 4) | c];
     Ok(Instruction {
@@ -99,3 +1789,618 @@ fn make_move_ix(
         ],
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use solana_account_decoder::UiAccount;
+    use solana_sdk::commitment_config::CommitmentLevel;
+
+    #[test]
+    fn classifies_transaction_errors() {
+        assert!(matches!(
+            SolanaError::from_transaction_error(&TransactionError::BlockhashNotFound),
+            Some(SolanaError::BlockhashExpired)
+        ));
+        assert!(matches!(
+            SolanaError::from_transaction_error(&TransactionError::InsufficientFundsForFee),
+            Some(SolanaError::InsufficientFunds)
+        ));
+        let rejected =
+            TransactionError::InstructionError(0, InstructionError::InvalidInstructionData);
+        assert!(matches!(
+            SolanaError::from_transaction_error(&rejected),
+            Some(SolanaError::ProgramRejected { code, .. }) if code == u64::from(ProgramError::InvalidInstructionData)
+        ));
+    }
+
+    #[test]
+    fn preflight_rejections_keep_the_program_logs() {
+        let sim = RpcSimulateTransactionResult {
+            err: Some(TransactionError::InstructionError(
+                0,
+                InstructionError::InvalidInstructionData,
+            )),
+            logs: Some(vec!["Program log: Invalid move".to_string()]),
+            accounts: None,
+            units_consumed: None,
+            return_data: None,
+        };
+        let err = SolanaError::from(ClientError::from(ClientErrorKind::RpcError(
+            RpcError::RpcResponseError {
+                code: -32002,
+                message: "Transaction simulation failed".to_string(),
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(sim),
+            },
+        )));
+        assert!(matches!(&err, SolanaError::ProgramRejected { logs, .. } if logs.len() == 1));
+        assert_eq!(err.recovery(), Recovery::Refetch);
+        assert_eq!(
+            err.to_string(),
+            "program rejected the move (code 0x300000000): \
+             the square is already eaten or off the board"
+        );
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        assert!(SolanaError::BlockhashExpired.is_transient());
+        let unreachable = SolanaError::from(ClientError::from(ClientErrorKind::Io(
+            std::io::Error::from(std::io::ErrorKind::ConnectionRefused),
+        )));
+        assert!(unreachable.is_transient());
+        assert!(!SolanaError::ProgramRejected {
+            code: 1,
+            logs: Vec::new()
+        }
+        .is_transient());
+        assert!(!SolanaError::InsufficientFunds.is_transient());
+
+        let exhausted = SolanaError::RetriesExhausted {
+            attempts: 3,
+            last: Box::new(unreachable),
+        };
+        assert!(exhausted.is_rpc());
+        assert!(!exhausted.is_transient());
+        assert!(exhausted
+            .to_string()
+            .starts_with("gave up after 3 attempts: RPC unreachable"));
+    }
+
+    #[test]
+    fn retry_delay_doubles_with_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base: Duration::from_millis(200),
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for (retry, full) in [(0, 200), (1, 400), (3, 1_600)] {
+            let delay = policy.delay(retry, &mut rng);
+            let full = Duration::from_millis(full);
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "{:?} for retry {}",
+                delay,
+                retry
+            );
+        }
+        assert!(policy.delay(u32::MAX, &mut rng) <= Duration::from_millis(200) * (1 << 16));
+    }
+
+    #[test]
+    fn confirmation_pauses_stop_at_the_timeout() {
+        let ms = Duration::from_millis;
+        let policy = ConfirmPolicy {
+            timeout: ms(1_200),
+            poll: ms(500),
+        };
+        assert_eq!(policy.next_pause(ms(0)), Some(ms(500)));
+        assert_eq!(policy.next_pause(ms(1_000)), Some(ms(200)));
+        assert_eq!(policy.next_pause(ms(1_200)), None);
+        assert_eq!(policy.next_pause(ms(5_000)), None);
+    }
+
+    #[test]
+    fn budget_instructions_go_first_in_order() {
+        let (program, player, pda, collector) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mv = make_move_ix(&program, &player, &pda, &collector, 2, 3).unwrap();
+        let all = budgeted(Some(50_000), Some(1_000), std::slice::from_ref(&mv));
+        assert_eq!(
+            all,
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(50_000),
+                ComputeBudgetInstruction::set_compute_unit_price(1_000),
+                mv.clone(),
+            ]
+        );
+        assert_eq!(
+            budgeted(None, Some(1_000), std::slice::from_ref(&mv))[0],
+            ComputeBudgetInstruction::set_compute_unit_price(1_000)
+        );
+        assert_eq!(budgeted(None, None, std::slice::from_ref(&mv)), vec![mv]);
+    }
+
+    #[test]
+    fn priority_fee_rounds_up_to_whole_lamports() {
+        assert_eq!(priority_fee_lamports(0, DEFAULT_COMPUTE_UNITS), 0);
+        assert_eq!(priority_fee_lamports(1, DEFAULT_COMPUTE_UNITS), 1);
+        assert_eq!(priority_fee_lamports(10_000, DEFAULT_COMPUTE_UNITS), 2_000);
+
+        assert_eq!(transaction_fee(None, None, 1), LAMPORTS_PER_SIGNATURE);
+        assert_eq!(transaction_fee(None, Some(10_000), 3), 5_000 + 6_000);
+        assert_eq!(transaction_fee(Some(50_000), Some(10_000), 3), 5_000 + 500);
+    }
+
+    #[test]
+    fn eaten_square_rejection_means_refetch() {
+        let eaten = SolanaError::from_transaction_error(&TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidInstructionData,
+        ))
+        .unwrap();
+        assert_eq!(eaten.recovery(), Recovery::Refetch);
+        let bad_account = SolanaError::from_transaction_error(&TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidArgument,
+        ))
+        .unwrap();
+        assert_eq!(bad_account.recovery(), Recovery::Stop);
+        assert_eq!(SolanaError::BlockhashExpired.recovery(), Recovery::Stop);
+    }
+
+    #[test]
+    fn account_not_found_is_missing_not_rpc() {
+        let key = Pubkey::new_unique();
+        let err = ClientError::from(RpcError::ForUser(format!(
+            "AccountNotFound: pubkey={}",
+            key
+        )));
+        assert!(
+            matches!(classify_fetch_error(&key, err), SolanaError::AccountMissing(k) if k == key)
+        );
+        let io = ClientError::from(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "down",
+        ));
+        assert!(classify_fetch_error(&key, io).is_rpc());
+    }
+
+    #[test]
+    fn unreachable_endpoints_stay_rpc_errors() {
+        let dead = || RpcClient::new("http://127.0.0.1:1".to_string());
+        let alone = MultiRpc::new(dead(), None);
+        let err = alone.get_latest_blockhash().unwrap_err();
+        assert!(matches!(err, SolanaError::RpcUnreachable(_)), "{:?}", err);
+        let both = MultiRpc::new(dead(), Some(dead()));
+        assert!(both.get_latest_blockhash().is_err_and(|e| e.is_rpc()));
+    }
+
+    #[test]
+    fn outages_are_not_missing_boards() {
+        let answering = |value: serde_json::Value| {
+            let mut mocks = solana_client::rpc_client::Mocks::new();
+            mocks.insert(
+                solana_client::rpc_request::RpcRequest::GetAccountInfo,
+                value,
+            );
+            MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None)
+        };
+        let (program, key) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let missing = answering(serde_json::json!({ "context": { "slot": 1 }, "value": null }));
+        assert_eq!(fetch_board(&missing, &program, &key).unwrap(), None);
+
+        let garbled = answering(serde_json::json!("not an account"));
+        let err = fetch_board(&garbled, &program, &key).unwrap_err();
+        assert!(matches!(&err, SolanaError::Rpc(_)), "{:?}", err);
+        assert!(err.is_rpc());
+    }
+
+    #[test]
+    fn infers_cluster_from_url() {
+        assert_eq!(Cluster::infer("https://api.mainnet-beta.solana.com"), Cluster::Mainnet);
+        assert_eq!(Cluster::infer("https://api.devnet.solana.com"), Cluster::Devnet);
+        assert_eq!(Cluster::infer("https://api.testnet.solana.com"), Cluster::Testnet);
+        assert_eq!(Cluster::infer("http://127.0.0.1:8899"), Cluster::Localnet);
+        assert_eq!(Cluster::infer("http://localhost:8899"), Cluster::Localnet);
+        assert_eq!(Cluster::infer("https://rpc.example.com"), Cluster::Unknown);
+        let sig = Signature::default();
+        assert!(Cluster::Devnet
+            .explorer_tx_url(&sig, "https://api.devnet.solana.com")
+            .ends_with("?cluster=devnet"));
+        assert!(!Cluster::Mainnet.explorer_tx_url(&sig, "").contains('?'));
+        assert!(Cluster::Unknown
+            .explorer_tx_url(&sig, "https://rpc.example.com/?api-key=a&b#c")
+            .ends_with("customUrl=https%3A%2F%2Frpc.example.com%2F%3Fapi-key%3Da%26b%23c"));
+    }
+
+    #[test]
+    fn parses_known_account_layouts_only() {
+        let board = [0xFF, 0xF0, 0xC0, 0, 0];
+        let mut v1 = board.to_vec();
+        v1.extend([0; 3]);
+        let mut v2 = vec![2];
+        v2.extend(board);
+        v2.resize(16, 0);
+
+        assert_eq!(AccountLayout::Auto.parse(&v1), Some(board));
+        assert_eq!(AccountLayout::Auto.parse(&v2), Some(board));
+        assert_eq!(AccountLayout::V1.parse(&v1), Some(board));
+        assert_eq!(AccountLayout::V2.parse(&v2), Some(board));
+
+        assert_eq!(AccountLayout::V1.parse(&v2), None);
+        assert_eq!(AccountLayout::V2.parse(&v1), None);
+        assert_eq!(AccountLayout::Auto.parse(&v1[..5]), None);
+        v2[0] = 3;
+        assert_eq!(AccountLayout::Auto.parse(&v2), None);
+    }
+
+    #[test]
+    fn board_layout_slices_synthetic_accounts() {
+        let key = Pubkey::new_unique();
+        let board = [0xFF, 0xF0, 0xC0, 0, 0];
+        let layout: BoardLayout = "offset=8,rows=5".parse().unwrap();
+        assert_eq!(layout.to_string(), "offset=8,rows=5");
+        assert_eq!("rows=5".parse(), Ok(BoardLayout { offset: 0, rows: 5 }));
+        assert!("offset=8,rows=6".parse::<BoardLayout>().is_err());
+        assert!("offset=x".parse::<BoardLayout>().is_err());
+        assert!("width=8".parse::<BoardLayout>().is_err());
+
+        // Anchor-style: 8-byte discriminator, the board, then other fields.
+        let mut anchor = vec![0xAA; 8];
+        anchor.extend(board);
+        anchor.extend([0; 11]);
+        assert_eq!(
+            decode_board(&key, &anchor, AccountLayout::Auto, Some(layout)).unwrap(),
+            board
+        );
+        // Too short for the offset.
+        assert!(matches!(
+            decode_board(&key, &anchor[..10], AccountLayout::Auto, Some(layout)),
+            Err(SolanaError::UnknownLayout { len: 10, .. })
+        ));
+        // The discriminator read as a board fails validation.
+        let at_zero = BoardLayout { offset: 0, rows: 5 };
+        assert!(matches!(
+            decode_board(&key, &anchor, AccountLayout::Auto, Some(at_zero)),
+            Err(SolanaError::InvalidBoard { .. })
+        ));
+        // Without a board layout the account version decides.
+        let mut v1 = board.to_vec();
+        v1.extend([0; 3]);
+        assert_eq!(
+            decode_board(&key, &v1, AccountLayout::Auto, None).unwrap(),
+            board
+        );
+    }
+
+    #[test]
+    fn pushed_accounts_read_like_fetched_ones() {
+        let (program, key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0xFF, 0xF0, 0xC0, 0, 0];
+        data.extend([0; 3]);
+        let acc = Account {
+            lamports: 1_000_000,
+            data,
+            owner: program,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let pushed = UiAccount::encode(&key, &acc, UiAccountEncoding::Base64, None, None);
+        let decoded: Account = pushed.decode().unwrap();
+        assert_eq!(
+            read_board_account(&program, &key, &decoded, AccountLayout::Auto, None).unwrap(),
+            Some([0xFF, 0xF0, 0xC0, 0, 0])
+        );
+        let state = read_game_state(&program, &key, &decoded, AccountLayout::Auto, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.stake, 1_000_000);
+        assert_eq!(state.eaten(), 8 + 4 + 2);
+        let closed = Account::new(0, 0, &system_program::id());
+        assert_eq!(
+            read_board_account(&program, &key, &closed, AccountLayout::Auto, None).unwrap(),
+            None
+        );
+        let foreign = Account {
+            owner: Pubkey::new_unique(),
+            ..acc
+        };
+        assert!(matches!(
+            read_board_account(&program, &key, &foreign, AccountLayout::Auto, None),
+            Err(SolanaError::WrongOwner { .. })
+        ));
+    }
+
+    #[test]
+    fn dry_runs_log_the_exact_signed_transaction() {
+        let payer = Keypair::new();
+        let (program, collector) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (pda, _) = get_game_pda(&program, &payer.pubkey());
+        let ix = make_move_ix(&program, &payer.pubkey(), &pda, &collector, 2, 3).unwrap();
+        let ixs = budgeted(Some(50_000), Some(1_000), &[ix]);
+        let tx = Transaction::new_signed_with_payer(
+            &ixs,
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::new_unique(),
+        );
+        assert_eq!(dry_run(&tx), SendOutcome::DryRun(tx.signatures[0]));
+        let wire = base64::decode(wire_base64(&tx)).unwrap();
+        let sent: Transaction = bincode::deserialize(&wire).unwrap();
+        assert_eq!(sent, tx);
+        sent.verify().unwrap();
+    }
+
+    #[test]
+    fn batch_fetch_reads_each_board_like_fetch_board() {
+        let program = Pubkey::new_unique();
+        let keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let account = |data: Vec<u8>| Account {
+            lamports: 1_000_000,
+            data,
+            owner: program,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let ui = |key: &Pubkey, acc: &Account| {
+            serde_json::to_value(UiAccount::encode(
+                key,
+                acc,
+                UiAccountEncoding::Base64,
+                None,
+                None,
+            ))
+            .unwrap()
+        };
+        let present = account(vec![0xFF, 0xF0, 0xC0, 0, 0, 0, 0, 0]);
+        let short = account(vec![0xFF, 0xF0]);
+        let mock = |value: serde_json::Value| {
+            let mut mocks = solana_client::rpc_client::Mocks::new();
+            mocks.insert(
+                solana_client::rpc_request::RpcRequest::GetMultipleAccounts,
+                serde_json::json!({ "context": { "slot": 1 }, "value": value }),
+            );
+            MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None)
+        };
+
+        let rpc = mock(serde_json::json!([ui(&keys[0], &present), null]));
+        assert_eq!(
+            fetch_boards(&rpc, &program, &keys[..2]).unwrap(),
+            [Some([0xFF, 0xF0, 0xC0, 0, 0]), None]
+        );
+
+        let rpc = mock(serde_json::json!([
+            ui(&keys[0], &present),
+            null,
+            ui(&keys[2], &short)
+        ]));
+        assert!(matches!(
+            fetch_boards(&rpc, &program, &keys),
+            Err(SolanaError::UnknownLayout { key, len: 2, .. }) if key == keys[2]
+        ));
+    }
+
+    #[test]
+    fn listed_games_skip_closed_and_undecodable_accounts() {
+        let program = Pubkey::new_unique();
+        let keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let keyed = |key: &Pubkey, data: Vec<u8>| {
+            let acc = Account {
+                lamports: 1_000_000,
+                data,
+                owner: program,
+                executable: false,
+                rent_epoch: 0,
+            };
+            serde_json::json!({
+                "pubkey": key.to_string(),
+                "account": UiAccount::encode(key, &acc, UiAccountEncoding::Base64, None, None),
+            })
+        };
+        let mut mocks = solana_client::rpc_client::Mocks::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetProgramAccounts,
+            serde_json::json!([
+                keyed(&keys[0], vec![0xFF, 0xF0, 0xC0, 0, 0, 0, 0, 0]),
+                keyed(&keys[1], vec![]),
+                keyed(&keys[2], vec![0x0F, 0, 0, 0, 0, 0, 0, 0]),
+            ]),
+        );
+        let rpc = MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None)
+            .with_account_layout(AccountLayout::V1);
+        assert_eq!(
+            list_games(&rpc, &program).unwrap(),
+            [(
+                keys[0],
+                GameState {
+                    board: [0xFF, 0xF0, 0xC0, 0, 0],
+                    stake: 1_000_000
+                }
+            )]
+        );
+        assert_eq!(AccountLayout::Auto.lens(), [8, 16]);
+    }
+
+    #[test]
+    fn fees_over_the_cap_are_not_sent() {
+        let payer = Keypair::new();
+        let (program, collector) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (pda, _) = get_game_pda(&program, &payer.pubkey());
+        let ix = make_move_ix(&program, &payer.pubkey(), &pda, &collector, 2, 3).unwrap();
+        let mut mocks = solana_client::rpc_client::Mocks::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetFeeForMessage,
+            serde_json::json!({ "context": { "slot": 1 }, "value": 12_000 }),
+        );
+        let rpc = MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None)
+            .with_max_fee(Some(10_000))
+            .with_dry_run(true);
+        let refused = submit_once(&rpc, &payer, std::slice::from_ref(&ix)).unwrap_err();
+        assert!(matches!(
+            refused,
+            SolanaError::FeeAboveCap {
+                fee: 12_000,
+                cap: 10_000
+            }
+        ));
+        assert!(!refused.is_transient() && !refused.is_rpc());
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::new_unique(),
+        );
+        assert!(check_fee(10_000, 10_000, &tx).is_ok());
+    }
+
+    #[test]
+    fn blockhashes_are_reused_until_stale_or_spent() {
+        let mut mocks = solana_client::rpc_client::Mocks::new();
+        let first = Hash::new_unique();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetLatestBlockhash,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "blockhash": first.to_string(), "lastValidBlockHeight": 1234 + 150 },
+            }),
+        );
+        // The mock answers with `first` once, then with its default hash,
+        // which expires at its default block height of 1234.
+        let rpc = MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None);
+        assert_eq!(rpc.recent_blockhash().unwrap(), first);
+        assert_eq!(rpc.recent_blockhash().unwrap(), first);
+
+        let payer = Keypair::new();
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+        let once = sign_recent(&rpc, &payer, std::slice::from_ref(&ix)).unwrap();
+        assert_eq!(once.message.recent_blockhash, first);
+        let again = sign_recent(&rpc, &payer, std::slice::from_ref(&ix)).unwrap();
+        assert_ne!(again.message.recent_blockhash, first);
+        assert_ne!(again.signatures[0], once.signatures[0]);
+
+        // Within `BLOCKHASH_MARGIN` of its last block, a hash is fetched again
+        // however young it is.
+        let now = rpc.recent_blockhash().unwrap();
+        let fetched = rpc.blockhash.borrow().as_ref().unwrap().fetched;
+        rpc.recent_blockhash().unwrap();
+        assert!(rpc.blockhash.borrow().as_ref().unwrap().fetched > fetched);
+        assert_eq!(rpc.blockhash.borrow().as_ref().unwrap().hash, now);
+        rpc.forget_blockhash();
+        assert!(rpc.blockhash.borrow().is_none());
+
+        // Without a block height, the age decides.
+        let mut mocks = solana_client::rpc_client::Mocks::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetBlockHeight,
+            serde_json::json!("not a height"),
+        );
+        let rpc = MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None);
+        rpc.recent_blockhash().unwrap();
+        let fetched = rpc.blockhash.borrow().as_ref().unwrap().fetched;
+        rpc.recent_blockhash().unwrap();
+        assert_eq!(rpc.blockhash.borrow().as_ref().unwrap().fetched, fetched);
+    }
+
+    #[test]
+    fn airdrops_wait_for_their_status() {
+        let to = Pubkey::new_unique();
+        let faucet = MultiRpc::new(RpcClient::new_mock("succeeds".to_string()), None);
+        assert_eq!(airdrop(&faucet, &to, 1).unwrap(), Signature::new(&[8; 64]));
+        let failing = MultiRpc::new(RpcClient::new_mock("instruction_error".to_string()), None);
+        assert!(airdrop(&failing, &to, 1).is_err());
+    }
+
+    #[test]
+    fn low_balances_are_refused() {
+        // Each mock answers once, so every check gets its own client.
+        let holding = |lamports: u64| {
+            let mut mocks = solana_client::rpc_client::Mocks::new();
+            mocks.insert(
+                solana_client::rpc_request::RpcRequest::GetBalance,
+                serde_json::json!({ "context": { "slot": 1 }, "value": lamports }),
+            );
+            MultiRpc::new(RpcClient::new_mock_with_mocks("succeeds", mocks), None)
+        };
+        let payer = Pubkey::new_unique();
+        let low = check_balance(&holding(4_000), &payer, LAMPORTS_PER_SIGNATURE).unwrap_err();
+        assert!(matches!(
+            low,
+            SolanaError::LowBalance {
+                balance: 4_000,
+                min: LAMPORTS_PER_SIGNATURE,
+                ..
+            }
+        ));
+        assert!(!low.is_transient() && !low.is_rpc());
+        assert!(low.to_string().contains("holds 4000 lamports"));
+        assert_eq!(
+            check_balance(&holding(4_000), &payer, 4_000).unwrap(),
+            4_000
+        );
+    }
+
+    #[test]
+    fn send_settings_default_to_preflight_at_the_read_commitment() {
+        let config = SendSettings::default().rpc_config(CommitmentConfig::finalized());
+        assert!(!config.skip_preflight);
+        assert_eq!(
+            config.preflight_commitment,
+            Some(CommitmentLevel::Finalized)
+        );
+        assert_eq!(config.max_retries, None);
+
+        let tuned = SendSettings {
+            skip_preflight: true,
+            preflight_commitment: Some(CommitmentConfig::processed()),
+            max_retries: Some(0),
+        }
+        .rpc_config(CommitmentConfig::finalized());
+        assert!(tuned.skip_preflight);
+        assert_eq!(tuned.preflight_commitment, Some(CommitmentLevel::Processed));
+        assert_eq!(tuned.max_retries, Some(0));
+    }
+
+    #[test]
+    fn fork_is_never_mainnet() {
+        let rpc = MultiRpc::new(RpcClient::new("https://rpc.example.com".to_string()), None)
+            .with_fork(true);
+        assert_eq!(rpc.cluster(), Cluster::Fork);
+        assert!(!is_mainnet(&rpc).unwrap());
+    }
+
+    #[test]
+    fn fee_accounts_are_the_writable_ones() {
+        let key = |n: u8| Pubkey::new_from_array([n; 32]);
+        let ix = make_move_ix(&key(1), &key(2), &key(3), &key(4), 5, 1).unwrap();
+        assert_eq!(
+            writable_accounts(&[ix.clone(), ix]),
+            vec![key(2), key(3), key(4)]
+        );
+    }
+
+    #[test]
+    fn rejects_off_board_moves() {
+        let key = Pubkey::new_unique();
+        assert!(matches!(
+            make_move_ix(&key, &key, &key, &key, 6, 1),
+            Err(SolanaError::InvalidMove { r: 6, c: 1, .. })
+        ));
+        assert!(make_move_ix(&key, &key, &key, &key, 0, 0).is_ok());
+    }
+}
@@ -0,0 +1,406 @@
+//! The RPC layer on `solana_client::nonblocking::rpc_client::RpcClient`, for
+//! playing many games at once on one thread pool (`--features async`). It
+//! mirrors the blocking `fetch_board` and `send_move`, sharing their decoding,
+//! instructions, error mapping and logging, but talks to the primary
+//! endpoint only: there is no fallback.
+
+use std::sync::Mutex;
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use tokio::time::sleep;
+
+use super::*;
+
+/// The async counterpart of `MultiRpc`, with the same send and read settings.
+pub struct AsyncRpc {
+    client: RpcClient,
+    cluster: Cluster,
+    send: SendSettings,
+    simulate_first: bool,
+    dry_run: bool,
+    account_layout: AccountLayout,
+    board_layout: Option<BoardLayout>,
+    priority_fee: PriorityFee,
+    compute_unit_limit: Option<u32>,
+    max_fee: Option<u64>,
+    retry: RetryPolicy,
+    confirm: ConfirmPolicy,
+    /// Last `PriorityFee::Auto` price and when it was computed, shared by
+    /// every game on this client.
+    auto_fee: Mutex<Option<(Instant, u64)>>,
+}
+
+impl AsyncRpc {
+    /// A client for `rpc`'s primary endpoint and commitment, carrying over its
+    /// fee, retry, confirmation, preflight and layout settings.
+    pub fn from_blocking(rpc: &MultiRpc) -> Self {
+        AsyncRpc {
+            client: RpcClient::new_with_commitment(rpc.primary.url(), rpc.commitment()),
+            cluster: rpc.cluster(),
+            send: rpc.send,
+            simulate_first: rpc.simulate_first,
+            dry_run: rpc.dry_run,
+            account_layout: rpc.account_layout,
+            board_layout: rpc.board_layout,
+            priority_fee: rpc.priority_fee,
+            compute_unit_limit: rpc.compute_unit_limit,
+            max_fee: rpc.max_fee,
+            retry: rpc.retry,
+            confirm: rpc.confirm,
+            auto_fee: Mutex::new(None),
+        }
+    }
+
+    fn explorer_link(&self, sig: &Signature) -> String {
+        self.cluster.explorer_tx_url(sig, &self.client.url())
+    }
+
+    /// Logs one call the way `attempt` does for the blocking client.
+    fn logged<T: fmt::Debug>(
+        &self,
+        method: &str,
+        args: &dyn fmt::Display,
+        started: Instant,
+        out: Result<T>,
+    ) -> Result<T> {
+        log_attempt(&self.client.url(), method, args, started.elapsed(), &out);
+        out
+    }
+
+    async fn compute_unit_price(&self, accounts: &[Pubkey]) -> Result<Option<u64>> {
+        let percentile = match self.priority_fee {
+            PriorityFee::None => return Ok(None),
+            PriorityFee::Fixed(price) => return Ok(Some(price)),
+            PriorityFee::Auto { percentile } => percentile,
+        };
+        if let Some((at, price)) = *self.auto_fee.lock().unwrap() {
+            if at.elapsed() < PRIORITY_FEE_REFRESH {
+                return Ok(Some(price));
+            }
+        }
+        let started = Instant::now();
+        let out = self
+            .client
+            .get_recent_prioritization_fees(accounts)
+            .await
+            .map_err(SolanaError::from);
+        let fees: Vec<u64> = self
+            .logged(
+                "get_recent_prioritization_fees",
+                &accounts.len(),
+                started,
+                out,
+            )?
+            .iter()
+            .map(|f| f.prioritization_fee)
+            .collect();
+        let price = nearest_rank(&fees, percentile).unwrap_or(0);
+        info!(
+            target: LOG_TARGET,
+            "priority fee: p{} of {} recent slots = {} micro-lamports/CU",
+            percentile,
+            fees.len(),
+            price
+        );
+        *self.auto_fee.lock().unwrap() = Some((Instant::now(), price));
+        Ok(Some(price))
+    }
+
+    pub async fn get_account(&self, key: &Pubkey) -> Result<Account> {
+        let started = Instant::now();
+        let out = self
+            .client
+            .get_account(key)
+            .await
+            .map_err(|e| classify_fetch_error(key, e));
+        self.logged("get_account", key, started, out)
+    }
+
+    pub async fn get_latest_blockhash(&self) -> Result<Hash> {
+        let started = Instant::now();
+        let out = self.client.get_latest_blockhash().await.map_err(Into::into);
+        self.logged("get_latest_blockhash", &"", started, out)
+    }
+
+    pub async fn get_fee_for_message(&self, tx: &Transaction) -> Result<u64> {
+        let started = Instant::now();
+        let out = self
+            .client
+            .get_fee_for_message(&tx.message)
+            .await
+            .map_err(Into::into);
+        self.logged("get_fee_for_message", &tx.signatures[0], started, out)
+    }
+
+    pub async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        trace!(target: LOG_TARGET, "send_transaction body: {:?}", tx);
+        let config = self.send.rpc_config(self.client.commitment());
+        let started = Instant::now();
+        let out = self
+            .client
+            .send_transaction_with_config(tx, config)
+            .await
+            .map_err(Into::into);
+        self.logged("send_transaction", &tx.signatures[0], started, out)
+            .inspect_err(log_rejection)
+    }
+
+    pub async fn simulate_transaction(
+        &self,
+        tx: &Transaction,
+    ) -> Result<RpcSimulateTransactionResult> {
+        let started = Instant::now();
+        let out = self
+            .client
+            .simulate_transaction(tx)
+            .await
+            .map(|r| r.value)
+            .map_err(Into::into);
+        self.logged("simulate_transaction", &tx.signatures[0], started, out)
+    }
+
+    pub async fn get_signature_statuses(
+        &self,
+        sigs: &[Signature],
+    ) -> Result<Vec<Option<TransactionStatus>>> {
+        let first = sigs.first().map(ToString::to_string).unwrap_or_default();
+        let started = Instant::now();
+        let out = self
+            .client
+            .get_signature_statuses(sigs)
+            .await
+            .map(|r| r.value)
+            .map_err(Into::into);
+        self.logged("get_signature_statuses", &first, started, out)
+    }
+
+    pub async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool> {
+        let started = Instant::now();
+        let out = self
+            .client
+            .is_blockhash_valid(blockhash, self.client.commitment())
+            .await
+            .map_err(Into::into);
+        self.logged("is_blockhash_valid", blockhash, started, out)
+    }
+}
+
+/// `solana::fetch_board` without blocking: the same ownership and layout
+/// checks, and the same retries of transient errors.
+pub async fn fetch_board(
+    rpc: &AsyncRpc,
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+) -> Result<Option<[u8; 5]>> {
+    let policy = rpc.retry;
+    let mut retry = 0;
+    loop {
+        match fetch_board_once(rpc, program_id, game_pda).await {
+            Err(e) if e.is_transient() && retry < policy.max_retries => {
+                let delay = policy.delay(retry, &mut rand::thread_rng());
+                warn!(target: LOG_TARGET, "reading {} failed: {}; retrying in {:?}", game_pda, e, delay);
+                sleep(delay).await;
+                retry += 1;
+            }
+            Err(e) if retry > 0 => {
+                return Err(SolanaError::RetriesExhausted {
+                    attempts: retry + 1,
+                    last: Box::new(e),
+                })
+            }
+            other => return other,
+        }
+    }
+}
+
+async fn fetch_board_once(
+    rpc: &AsyncRpc,
+    program_id: &Pubkey,
+    game_pda: &Pubkey,
+) -> Result<Option<[u8; 5]>> {
+    match rpc.get_account(game_pda).await {
+        Ok(acc) => read_board_account(
+            program_id,
+            game_pda,
+            &acc,
+            rpc.account_layout,
+            rpc.board_layout,
+        ),
+        Err(SolanaError::AccountMissing(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// `solana::send_move` without blocking.
+pub async fn send_move(
+    rpc: &AsyncRpc,
+    program_id: &Pubkey,
+    fee_collector: &Pubkey,
+    payer: &Keypair,
+    game_pda: &Pubkey,
+    r: u8,
+    c: u8,
+) -> Result<SendOutcome> {
+    let ix = make_move_ix(program_id, &payer.pubkey(), game_pda, fee_collector, r, c)?;
+    let outcome = match submit(rpc, payer, &[ix]).await {
+        Err(e) if e.recovery() == Recovery::Refetch => SendOutcome::BoardAdvanced,
+        other => other?,
+    };
+    log_move_outcome(&outcome, r, c, |sig| rpc.explorer_link(sig));
+    Ok(outcome)
+}
+
+/// The blocking `submit` loop: dropped transactions are resubmitted with a
+/// fresh blockhash, transient errors after a `RetryPolicy::delay`.
+async fn submit(rpc: &AsyncRpc, payer: &Keypair, ixs: &[Instruction]) -> Result<SendOutcome> {
+    let policy = rpc.retry;
+    for attempt in 0..=policy.max_retries {
+        if attempt > 0 {
+            warn!(target: LOG_TARGET,
+                "resubmitting (attempt {}/{})",
+                attempt + 1,
+                policy.max_retries + 1
+            );
+        }
+        let err = match submit_once(rpc, payer, ixs).await {
+            Ok(SendOutcome::Dropped) => continue,
+            Ok(outcome) => return Ok(outcome),
+            Err(e) if e.is_transient() => e,
+            Err(e) => return Err(e),
+        };
+        if attempt == policy.max_retries {
+            return Err(SolanaError::RetriesExhausted {
+                attempts: attempt + 1,
+                last: Box::new(err),
+            });
+        }
+        let delay = policy.delay(attempt, &mut rand::thread_rng());
+        warn!(target: LOG_TARGET, "attempt {} failed: {}; retrying in {:?}", attempt + 1, err, delay);
+        sleep(delay).await;
+    }
+    Ok(SendOutcome::Dropped)
+}
+
+async fn submit_once(rpc: &AsyncRpc, payer: &Keypair, ixs: &[Instruction]) -> Result<SendOutcome> {
+    let price = rpc.compute_unit_price(&writable_accounts(ixs)).await?;
+    let all = budgeted(rpc.compute_unit_limit, price, ixs);
+    let bh = rpc.get_latest_blockhash().await?;
+    let tx = Transaction::new_signed_with_payer(&all, Some(&payer.pubkey()), &[payer], bh);
+    if let Some(cap) = rpc.max_fee {
+        check_fee(rpc.get_fee_for_message(&tx).await?, cap, &tx)?;
+    }
+    if rpc.simulate_first {
+        simulate(rpc, &tx).await?;
+    }
+    if rpc.dry_run {
+        return Ok(dry_run(&tx));
+    }
+    let sig = match rpc.send_transaction(&tx).await {
+        Ok(sig) => sig,
+        // As in the blocking `submit_once`: a send that may have landed is
+        // never resent until it is known to be dropped.
+        Err(e @ SolanaError::RpcUnreachable(_)) => {
+            let sig = tx.signatures[0];
+            return match poll_signature(rpc, &sig, &bh).await {
+                Ok(SendOutcome::Dropped) => Err(e),
+                Ok(outcome) => Ok(outcome),
+                Err(_) => Ok(SendOutcome::Unknown(sig)),
+            };
+        }
+        Err(e) => return Err(e),
+    };
+    poll_signature(rpc, &sig, &bh).await
+}
+
+async fn simulate(rpc: &AsyncRpc, tx: &Transaction) -> Result<()> {
+    let sim = rpc.simulate_transaction(tx).await?;
+    let Some(err) = sim.err else {
+        return Ok(());
+    };
+    warn!(target: LOG_TARGET, "simulation of {} failed: {}; not sending it", tx.signatures[0], err);
+    let logs = sim.logs.unwrap_or_default();
+    for line in &logs {
+        warn!(target: LOG_TARGET, "  {}", line);
+    }
+    Err(SolanaError::from_failed_transaction(&err).with_logs(logs))
+}
+
+async fn poll_signature(rpc: &AsyncRpc, sig: &Signature, blockhash: &Hash) -> Result<SendOutcome> {
+    let started = Instant::now();
+    loop {
+        let statuses = rpc.get_signature_statuses(&[*sig]).await?;
+        if let Some(Some(status)) = statuses.first() {
+            if let Some(err) = &status.err {
+                warn!(target: LOG_TARGET, "transaction {} failed: {}", sig, err);
+                return Err(SolanaError::from_failed_transaction(err));
+            }
+            if status.satisfies_commitment(rpc.client.commitment()) {
+                return Ok(SendOutcome::Confirmed(*sig));
+            }
+        } else if !rpc.is_blockhash_valid(blockhash).await? {
+            return Ok(SendOutcome::Dropped);
+        }
+        let Some(pause) = rpc.confirm.next_pause(started.elapsed()) else {
+            return Ok(SendOutcome::Unknown(*sig));
+        };
+        sleep(pause).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unreachable_endpoint_is_an_rpc_error_not_a_missing_board() {
+        let blocking = MultiRpc::new(
+            solana_client::rpc_client::RpcClient::new("http://127.0.0.1:1".to_string()),
+            None,
+        )
+        .with_retry(RetryPolicy {
+            max_retries: 1,
+            base: Duration::from_millis(1),
+        });
+        let rpc = AsyncRpc::from_blocking(&blocking);
+        let key = Pubkey::new_unique();
+        let err = fetch_board(&rpc, &Pubkey::new_unique(), &key)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SolanaError::RetriesExhausted { attempts: 2, .. }),
+            "{:?}",
+            err
+        );
+        assert!(err.is_rpc());
+    }
+
+    #[tokio::test]
+    async fn failed_simulations_keep_the_program_logs() {
+        let mut mocks = solana_client::rpc_client::Mocks::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::SimulateTransaction,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "err": { "InstructionError": [0, { "Custom": 6 }] },
+                    "logs": ["Program log: Invalid move"],
+                },
+            }),
+        );
+        let blocking = MultiRpc::new(
+            solana_client::rpc_client::RpcClient::new_mock("succeeds".to_string()),
+            None,
+        );
+        let rpc = AsyncRpc {
+            client: RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks),
+            ..AsyncRpc::from_blocking(&blocking)
+        };
+        let tx = Transaction::new_with_payer(&[], Some(&Pubkey::new_unique()));
+        let err = simulate(&rpc, &tx).await.unwrap_err();
+        assert!(
+            matches!(&err, SolanaError::ProgramRejected { code: 6, logs } if logs.len() == 1),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the latency buckets, in seconds; the last bucket is open.
+const BUCKET_BOUNDS: [u64; 3] = [1, 2, 5];
+const BUCKET_LABELS: [&str; 4] = ["<1s", "1-2s", "2-5s", ">5s"];
+
+/// Nearest-rank percentile of `values`, `p` in `0..=100`.
+pub fn nearest_rank<T: Ord + Copy>(values: &[T], p: u32) -> Option<T> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    let rank = (p.min(100) as usize * sorted.len()).div_ceil(100);
+    Some(sorted[rank.saturating_sub(1)])
+}
+
+/// How the board read after one of our moves compares with the board
+/// `apply_move` predicted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Prediction {
+    /// Exactly our move was applied.
+    Exact,
+    /// Our move plus further squares: the program's or another player's reply.
+    Replied,
+    /// Squares our move should have eaten are still there.
+    Mismatch,
+}
+
+impl Prediction {
+    pub fn compare(predicted: [u8; 5], actual: [u8; 5]) -> Self {
+        if actual == predicted {
+            Prediction::Exact
+        } else if predicted.iter().zip(actual).all(|(p, a)| a & p == *p) {
+            Prediction::Replied
+        } else {
+            Prediction::Mismatch
+        }
+    }
+}
+
+/// Counters accumulated over a whole run, across every game played.
+#[derive(Clone, Debug)]
+pub struct RunStats {
+    pub started: Instant,
+    pub games: u32,
+    pub games_finished: u32,
+    pub moves_sent: u32,
+    /// Sent moves that were among the solver's forced wins; the rest came from
+    /// a losing position or a strategy's fallback.
+    pub forced_moves: u32,
+    /// Fees of the transactions that confirmed, cash-outs included.
+    pub lamports_spent: u64,
+    /// Wall-clock time of each sent move, from the board fetch to confirmation.
+    pub move_latencies: Vec<Duration>,
+    /// Boards read after our moves, by `Prediction`: exact, replied, mismatch.
+    pub predictions: [u32; 3],
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            games: 0,
+            games_finished: 0,
+            moves_sent: 0,
+            forced_moves: 0,
+            lamports_spent: 0,
+            move_latencies: Vec::new(),
+            predictions: [0; 3],
+        }
+    }
+}
+
+impl RunStats {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// True once the run has been going for at least `budget`.
+    pub fn over_budget(&self, budget: Option<Duration>) -> bool {
+        budget.is_some_and(|b| self.elapsed() >= b)
+    }
+
+    pub fn record_move(&mut self, latency: Duration, forced: bool) {
+        self.moves_sent += 1;
+        self.forced_moves += u32::from(forced);
+        self.move_latencies.push(latency);
+    }
+
+    pub fn record_prediction(&mut self, prediction: Prediction) {
+        self.predictions[prediction as usize] += 1;
+    }
+
+    pub fn record_game(&mut self, finished: bool) {
+        self.games += 1;
+        if finished {
+            self.games_finished += 1;
+        }
+    }
+
+    /// Adds another run's counts and latencies, e.g. one wallet's under
+    /// `--keypairs`; the combined run started when the earlier one did.
+    pub fn absorb(&mut self, other: &RunStats) {
+        self.started = self.started.min(other.started);
+        self.games += other.games;
+        self.games_finished += other.games_finished;
+        self.moves_sent += other.moves_sent;
+        self.forced_moves += other.forced_moves;
+        self.lamports_spent += other.lamports_spent;
+        self.move_latencies.extend_from_slice(&other.move_latencies);
+        for (mine, theirs) in self.predictions.iter_mut().zip(other.predictions) {
+            *mine += theirs;
+        }
+    }
+
+    /// Mean of the move latencies, `None` before the first move.
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let n = u32::try_from(self.move_latencies.len())
+            .ok()
+            .filter(|n| *n > 0)?;
+        Some(self.move_latencies.iter().sum::<Duration>() / n)
+    }
+
+    /// Nearest-rank percentile of the move latencies, `p` in `0..=100`.
+    pub fn latency_percentile(&self, p: u32) -> Option<Duration> {
+        nearest_rank(&self.move_latencies, p)
+    }
+
+    /// Move counts per latency bucket, in the order of `BUCKET_LABELS`.
+    fn latency_buckets(&self) -> [usize; 4] {
+        let mut counts = [0; 4];
+        for d in &self.move_latencies {
+            let i = BUCKET_BOUNDS
+                .iter()
+                .position(|b| *d < Duration::from_secs(*b))
+                .unwrap_or(BUCKET_BOUNDS.len());
+            counts[i] += 1;
+        }
+        counts
+    }
+
+    /// Multi-line latency report: one bar per bucket, then p50/p95.
+    pub fn latency_report(&self) -> String {
+        let counts = self.latency_buckets();
+        let widest = counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut out = String::from("move latency:\n");
+        for (label, n) in BUCKET_LABELS.iter().zip(counts) {
+            let bar = "#".repeat((n * 40).div_ceil(widest));
+            out.push_str(&format!("  {:>5} {:>4} {}\n", label, n, bar));
+        }
+        match (self.latency_percentile(50), self.latency_percentile(95)) {
+            (Some(p50), Some(p95)) => out.push_str(&format!(
+                "  p50={:.2}s p95={:.2}s",
+                p50.as_secs_f64(),
+                p95.as_secs_f64()
+            )),
+            _ => out.push_str("  no moves sent"),
+        }
+        out
+    }
+
+    /// Writes one latency per line, in milliseconds, in the order the moves were sent.
+    pub fn write_latencies(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+        for d in &self.move_latencies {
+            writeln!(file, "{}", d.as_millis())?;
+        }
+        file.flush()
+    }
+}
+
+impl fmt::Display for RunStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "games={} finished={} moves_sent={} forced/fallback={}/{} predicted exact/replied/mismatch={}/{}/{} avg_latency={:.2}s fees={} lamports elapsed={:.1}s",
+            self.games,
+            self.games_finished,
+            self.moves_sent,
+            self.forced_moves,
+            self.moves_sent - self.forced_moves,
+            self.predictions[0],
+            self.predictions[1],
+            self.predictions[2],
+            self.mean_latency().unwrap_or_default().as_secs_f64(),
+            self.lamports_spent,
+            self.elapsed().as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_latencies(ms: &[u64]) -> RunStats {
+        let mut stats = RunStats::default();
+        for m in ms {
+            stats.record_move(Duration::from_millis(*m), false);
+        }
+        stats
+    }
+
+    #[test]
+    fn predictions_split_replies_from_mismatches() {
+        let predicted = [0xC0, 0, 0, 0, 0];
+        assert_eq!(Prediction::compare(predicted, predicted), Prediction::Exact);
+        assert_eq!(
+            Prediction::compare(predicted, [0xC0, 0x80, 0x80, 0, 0]),
+            Prediction::Replied
+        );
+        assert_eq!(
+            Prediction::compare(predicted, [0x80, 0x80, 0, 0, 0]),
+            Prediction::Mismatch
+        );
+
+        let mut stats = RunStats::default();
+        stats.record_prediction(Prediction::Replied);
+        stats.record_prediction(Prediction::Replied);
+        stats.record_prediction(Prediction::Mismatch);
+        assert_eq!(stats.predictions, [0, 2, 1]);
+        assert!(stats.to_string().contains("exact/replied/mismatch=0/2/1"));
+    }
+
+    #[test]
+    fn absorbed_runs_add_up() {
+        let mut total = with_latencies(&[400, 1500]);
+        total.record_game(true);
+        let mut other = with_latencies(&[7000]);
+        other.record_game(false);
+        other.record_prediction(Prediction::Replied);
+        total.absorb(&other);
+        assert_eq!(
+            (total.games, total.games_finished, total.moves_sent),
+            (2, 1, 3)
+        );
+        assert_eq!(total.predictions, [0, 1, 0]);
+        assert_eq!(total.lamports_spent, 0);
+        assert_eq!(
+            total.latency_percentile(100),
+            Some(Duration::from_millis(7000))
+        );
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let stats = with_latencies(&[400, 1500, 900, 7000, 3000]);
+        assert_eq!(stats.latency_percentile(50), Some(Duration::from_millis(1500)));
+        assert_eq!(stats.latency_percentile(95), Some(Duration::from_millis(7000)));
+        assert_eq!(RunStats::default().latency_percentile(50), None);
+    }
+
+    #[test]
+    fn summary_splits_forced_moves_from_fallbacks() {
+        let mut stats = with_latencies(&[1000, 2000]);
+        stats.record_move(Duration::from_millis(3000), true);
+        stats.lamports_spent = 15_000;
+        assert_eq!(stats.mean_latency(), Some(Duration::from_millis(2000)));
+        let line = stats.to_string();
+        assert!(
+            line.contains("moves_sent=3 forced/fallback=1/2"),
+            "{}",
+            line
+        );
+        assert!(
+            line.contains("avg_latency=2.00s fees=15000 lamports"),
+            "{}",
+            line
+        );
+        assert_eq!(RunStats::default().mean_latency(), None);
+    }
+
+    #[test]
+    fn buckets_split_on_bounds() {
+        let stats = with_latencies(&[999, 1000, 1999, 2000, 5000, 12000]);
+        assert_eq!(stats.latency_buckets(), [1, 2, 1, 2]);
+    }
+}
@@ -0,0 +1,150 @@
+//! Move selection behind one trait, so the loops only ever call
+//! `strategy.choose(board)` and heuristics compose with `Fallback`.
+
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::config::StrategyKind;
+use crate::game;
+
+/// `Send` so an async `--keypairs` game task can own its strategy.
+pub trait Strategy: Send {
+    /// Our move on `board`, or `None` when this strategy has nothing to offer.
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)>;
+}
+
+/// Stored forced win, else `best_try`. Declines when the solver is unavailable.
+pub struct OptimalStrategy;
+
+impl Strategy for OptimalStrategy {
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)> {
+        game::solver_ready()
+            .then(|| game::choose_move(board))
+            .flatten()
+    }
+}
+
+/// Winning (or legal) move leaving the opponent the fewest replies. Declines
+/// when the solver is unavailable.
+pub struct MostConstrainingStrategy;
+
+impl Strategy for MostConstrainingStrategy {
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)> {
+        game::solver_ready()
+            .then(|| game::pick_most_constraining(board))
+            .flatten()
+    }
+}
+
+/// The biggest bite that is not the poison; needs no solver.
+pub struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)> {
+        game::pick_greedy(board)
+    }
+}
+
+/// A random open square, poison only when forced.
+pub struct RandomStrategy {
+    rng: RefCell<StdRng>,
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> Self {
+        RandomStrategy {
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)> {
+        game::pick_random_legal(board, &mut *self.rng.borrow_mut())
+    }
+}
+
+/// The first strategy, in order, that offers a move.
+pub struct Fallback(pub Vec<Box<dyn Strategy>>);
+
+impl Strategy for Fallback {
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)> {
+        self.0.iter().find_map(|s| s.choose(board))
+    }
+}
+
+/// `pick_any_legal`: the last resort of every built strategy.
+struct AnyLegal;
+
+impl Strategy for AnyLegal {
+    fn choose(&self, board: [u8; 5]) -> Option<(u8, u8)> {
+        game::pick_any_legal(board)
+    }
+}
+
+/// The `--strategy` choice backed by `pick_any_legal`, so a legal move is
+/// always found while one exists, with or without the solver.
+pub fn build(kind: StrategyKind, seed: u64) -> Box<dyn Strategy> {
+    let primary: Box<dyn Strategy> = match kind {
+        StrategyKind::Optimal => Box::new(OptimalStrategy),
+        StrategyKind::Constrain => Box::new(MostConstrainingStrategy),
+        StrategyKind::Greedy => Box::new(GreedyStrategy),
+        StrategyKind::Random => Box::new(RandomStrategy::new(seed)),
+        StrategyKind::AnyLegal => return Box::new(AnyLegal),
+    };
+    Box::new(Fallback(vec![primary, Box::new(AnyLegal)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Declines;
+
+    impl Strategy for Declines {
+        fn choose(&self, _: [u8; 5]) -> Option<(u8, u8)> {
+            None
+        }
+    }
+
+    #[test]
+    fn fallback_takes_the_first_offer() {
+        let chain = Fallback(vec![Box::new(Declines), Box::new(GreedyStrategy)]);
+        assert_eq!(chain.choose([0u8; 5]), game::pick_greedy([0u8; 5]));
+        assert_eq!(Fallback(vec![Box::new(Declines)]).choose([0u8; 5]), None);
+    }
+
+    #[test]
+    fn built_strategies_match_their_pickers() {
+        let empty = [0u8; 5];
+        assert_eq!(
+            build(StrategyKind::Optimal, 0).choose(empty),
+            game::choose_move(empty)
+        );
+        assert_eq!(
+            build(StrategyKind::Constrain, 0).choose(empty),
+            game::pick_most_constraining(empty)
+        );
+        assert_eq!(
+            build(StrategyKind::AnyLegal, 0).choose(empty),
+            game::pick_any_legal(empty)
+        );
+        let a = build(StrategyKind::Random, 9).choose(empty);
+        assert_eq!(a, build(StrategyKind::Random, 9).choose(empty));
+        assert!(a.is_some_and(|(r, c)| game::move_is_open(empty, r, c)));
+    }
+
+    #[test]
+    fn seeded_random_games_repeat() {
+        let play = |seed: u64| {
+            let first = build(StrategyKind::Random, seed);
+            let second = build(StrategyKind::Random, seed.wrapping_add(1));
+            game::self_play([0u8; 5], |b| first.choose(b), |b| second.choose(b))
+        };
+        let game = play(42);
+        assert_eq!(game.moves, play(42).moves);
+        assert_eq!(game.moves.last(), Some(&(5, 8)));
+        assert!((0..8).any(|seed| play(seed).moves != game.moves));
+    }
+}
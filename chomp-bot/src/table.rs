@@ -0,0 +1,901 @@
+//! The solver core: skyline encoding and the win/loss book. It depends on
+//! nothing else in the crate, so `build.rs` can include it to embed a
+//! precomputed table under the `embed-table` feature.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
+
+pub const ROW_COUNT: usize = 5;
+pub const COL_COUNT: usize = 8;
+pub const COLS_U8: u8 = COL_COUNT as u8;
+pub const TABLE_SIZE: usize = BoardShape::STANDARD.table_size();
+/// A row is one bitmask byte, so no board is wider than this.
+pub const MAX_COLS: u8 = 8;
+/// Leads every file written by `PositionTable::save_to`.
+const CACHE_MAGIC: &[u8; 8] = b"CHOMPTBL";
+/// Bumped whenever the header or the entry encoding of `to_bytes` changes.
+/// Version 2 added the rules byte.
+const CACHE_VERSION: u8 = 2;
+/// Most memory a table may take: its book plus the `depths` built from it.
+const MAX_TABLE_BYTES: usize = 1 << 30;
+
+/// Largest index `Skyline::encode` can produce: the skyline is a walk of `rows`
+/// ones and `cols` zeros, and it is biggest with every one in the high bits.
+pub const fn max_encoded_index(rows: usize, cols: usize) -> usize {
+    ((1 << rows) - 1) << cols
+}
+
+const _: () = assert!(
+    TABLE_SIZE > max_encoded_index(ROW_COUNT, COL_COUNT),
+    "TABLE_SIZE is too small for ROW_COUNT x COL_COUNT; raise it to at least 1 << (ROW_COUNT + COL_COUNT)"
+);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Classified {
+    Unexplored,
+    Winning(u8, u8),
+    Losing,
+}
+
+impl Classified {
+    /// The entry's byte in `PositionTable::to_bytes`.
+    fn code(self, shape: BoardShape) -> u8 {
+        match self {
+            Classified::Unexplored => 0,
+            Classified::Losing => 1,
+            Classified::Winning(0xFF, 0xFF) => 2,
+            Classified::Winning(r, c) => {
+                let code = 3 + usize::from(r) * usize::from(shape.cols) + usize::from(c) - 1;
+                u8::try_from(code).expect("move code fits a byte")
+            }
+        }
+    }
+
+    fn from_code(byte: u8, shape: BoardShape) -> Option<Self> {
+        let cols = usize::from(shape.cols);
+        let moves = usize::from(shape.rows) * cols;
+        match usize::from(byte) {
+            0 => Some(Classified::Unexplored),
+            1 => Some(Classified::Losing),
+            2 => Some(Classified::Winning(0xFF, 0xFF)),
+            code if code - 3 < moves => {
+                let (r, c) = ((code - 3) / cols, (code - 3) % cols + 1);
+                Some(Classified::Winning(r as u8, c as u8))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A `Classified` packed into its `to_bytes` code, so the book takes one byte
+/// per position instead of the enum's three. The move is stored relative to
+/// the board's width, so unpacking needs the shape.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Entry(u8);
+
+impl Entry {
+    const UNEXPLORED: Entry = Entry(0);
+    const LOSING: Entry = Entry(1);
+    /// `Classified::Winning(0xFF, 0xFF)`, the fully eaten ending.
+    const EATEN: Entry = Entry(2);
+
+    fn pack(entry: Classified, shape: BoardShape) -> Self {
+        Entry(entry.code(shape))
+    }
+
+    fn get(self, shape: BoardShape) -> Classified {
+        Classified::from_code(self.0, shape).expect("the book only holds valid codes")
+    }
+}
+
+/// How the pot is decided once the board runs out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rules {
+    /// Standard Chomp, and what the program enforces: whoever eats the poison loses.
+    PoisonLoses,
+    /// Whoever makes the final move wins. Eating the poison clears the board, so
+    /// this is the last move rather than a loss.
+    LastMoveWins,
+}
+
+/// Rows and columns of a board, with the poison in the bottom-right corner.
+/// Positions on it are eaten counts per row, top first, as in `Skyline`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BoardShape {
+    rows: u8,
+    cols: u8,
+}
+
+impl BoardShape {
+    /// The 5x8 board the program plays.
+    pub const STANDARD: BoardShape = BoardShape {
+        rows: ROW_COUNT as u8,
+        cols: COLS_U8,
+    };
+
+    /// A `rows` x `cols` board, or an error when it is empty, wider than
+    /// `MAX_COLS`, or its table would not fit in `MAX_TABLE_BYTES`.
+    pub fn new(rows: u8, cols: u8) -> Result<Self, String> {
+        if rows == 0 || !(1..=MAX_COLS).contains(&cols) {
+            return Err(format!(
+                "a board needs at least 1 row and 1 to {} columns, got {}x{}",
+                MAX_COLS, rows, cols
+            ));
+        }
+        let bits = u32::from(rows) + u32::from(cols);
+        let per_entry = std::mem::size_of::<Entry>() + 1;
+        match 1usize
+            .checked_shl(bits)
+            .and_then(|n| n.checked_mul(per_entry))
+        {
+            Some(bytes) if bytes <= MAX_TABLE_BYTES => Ok(BoardShape { rows, cols }),
+            _ => Err(format!(
+                "a {}x{} table has 2^{} entries and would need more than the {} MiB allowed",
+                rows,
+                cols,
+                bits,
+                MAX_TABLE_BYTES >> 20
+            )),
+        }
+    }
+
+    pub fn rows(&self) -> u8 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u8 {
+        self.cols
+    }
+
+    /// Entries in a table for this shape; every index `encode` returns is
+    /// below it.
+    pub const fn table_size(&self) -> usize {
+        1 << (self.rows as usize + self.cols as usize)
+    }
+
+    /// Table index of `counts`: a walk of one bit per row and one zero per
+    /// column, read from the top row's right edge.
+    pub fn encode(&self, counts: &[u8]) -> usize {
+        let mut idx = 0usize;
+        let mut trailing = self.cols;
+        counts.iter().for_each(|&val| {
+            if trailing > val {
+                idx <<= (trailing - val) as usize;
+                trailing = val;
+            }
+            idx = (idx << 1) | 1;
+        });
+        idx << trailing as usize
+    }
+
+    pub fn decode(&self, mut encoded: usize) -> Vec<u8> {
+        let last = self.rows as usize - 1;
+        let mut counts = vec![0u8; last + 1];
+        counts[last] = encoded.trailing_zeros() as u8;
+        encoded >>= (counts[last] + 1) as usize;
+
+        let mut zeros_seen = 0u8;
+        let mut cursor = last;
+
+        while encoded != 0 {
+            if encoded & 1 == 1 {
+                cursor -= 1;
+                counts[cursor] = counts[cursor + 1] + zeros_seen;
+                zeros_seen = 0;
+                encoded >>= 1;
+            } else {
+                zeros_seen += 1;
+                encoded >>= 1;
+            }
+        }
+
+        counts
+    }
+
+    /// Eaten counts from row bitmasks (bit `0x80` is column 1, a set bit is
+    /// eaten). Bits past the last column are ignored.
+    pub fn counts_from_masks(&self, masks: &[u8]) -> Vec<u8> {
+        masks
+            .iter()
+            .map(|mask| (mask.leading_ones() as u8).min(self.cols))
+            .collect()
+    }
+}
+
+impl fmt::Display for BoardShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.rows, self.cols)
+    }
+}
+
+/// Game lengths behind `PositionTable::depths`, for a solved `book`.
+fn count_plies(shape: BoardShape, book: &[Entry]) -> Vec<u8> {
+    const UNSET: u8 = u8::MAX;
+
+    fn walk(shape: BoardShape, book: &[Entry], idx: usize, memo: &mut [u8]) -> u8 {
+        if memo[idx] != UNSET {
+            return memo[idx];
+        }
+        let entry = book[idx];
+        let d = if entry == Entry::EATEN {
+            0
+        } else {
+            let snapshot = shape.decode(idx);
+            let mut children = Vec::new();
+            for r in 0..shape.rows {
+                for c in (snapshot[r as usize] + 1)..=shape.cols {
+                    let child = shape.encode(&after(&snapshot, r, c));
+                    if entry == Entry::LOSING || book[child] == Entry::LOSING {
+                        children.push(walk(shape, book, child, memo));
+                    }
+                }
+            }
+            let best = if entry == Entry::LOSING {
+                children.into_iter().max()
+            } else {
+                children.into_iter().min()
+            };
+            // Only the fully eaten board has no children.
+            best.map_or(0, |d| d + 1)
+        };
+        memo[idx] = d;
+        d
+    }
+
+    let mut memo = vec![UNSET; book.len()];
+    for idx in 0..book.len() {
+        if book[idx] != Entry::UNEXPLORED {
+            walk(shape, book, idx, &mut memo);
+        }
+    }
+    for d in memo.iter_mut().filter(|d| **d == UNSET) {
+        *d = 0;
+    }
+    memo
+}
+
+/// Table indices of every position of `shape`, grouped by the number of
+/// squares eaten.
+#[cfg(feature = "parallel")]
+fn skylines_by_eaten(shape: BoardShape) -> Vec<Vec<usize>> {
+    let rows = shape.rows as usize;
+    let mut layers = vec![Vec::new(); rows * shape.cols as usize + 1];
+    // Step through the non-increasing counts like an odometer: bump the last
+    // row that can still grow and clear the rows after it.
+    let mut counts = vec![0u8; rows];
+    loop {
+        let eaten: usize = counts.iter().map(|&n| usize::from(n)).sum();
+        layers[eaten].push(shape.encode(&counts));
+        let Some(r) = (0..rows)
+            .rev()
+            .find(|&r| counts[r] < if r == 0 { shape.cols } else { counts[r - 1] })
+        else {
+            return layers;
+        };
+        counts[r] += 1;
+        counts[r + 1..].fill(0);
+    }
+}
+
+/// Counts after eating column `c` (1-based) in rows `0..=r` (0-based).
+fn after(counts: &[u8], r: u8, c: u8) -> Vec<u8> {
+    let mut next = counts.to_vec();
+    for slot in next.iter_mut().take(r as usize + 1) {
+        *slot = (*slot).max(c);
+    }
+    next
+}
+
+/// Tracks how many squares are already eaten from each row of the standard
+/// board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Skyline(pub [u8; ROW_COUNT]);
+
+impl Skyline {
+    /// A skyline from per-row eaten counts, or `None` unless every count is at
+    /// most the row length and no row has more eaten than the row above it.
+    pub fn new(rows: [u8; ROW_COUNT]) -> Option<Self> {
+        let monotone = rows.windows(2).all(|pair| pair[0] >= pair[1]);
+        (monotone && rows[0] <= COLS_U8).then_some(Skyline(rows))
+    }
+
+    pub fn rows(&self) -> [u8; ROW_COUNT] {
+        self.0
+    }
+
+    pub fn encode(&self) -> usize {
+        BoardShape::STANDARD.encode(&self.0)
+    }
+
+    #[cfg(test)]
+    pub fn decode(encoded: usize) -> Self {
+        let counts = BoardShape::STANDARD.decode(encoded);
+        Self(
+            counts
+                .try_into()
+                .expect("the standard shape has ROW_COUNT rows"),
+        )
+    }
+
+    /// Skyline after eating column `c` (1-based) in rows `0..=r` (0-based).
+    #[cfg(test)]
+    pub fn after(&self, r: u8, c: u8) -> Self {
+        let mut next = *self;
+        for slot in next.0.iter_mut().take(r as usize + 1) {
+            *slot = (*slot).max(c);
+        }
+        next
+    }
+}
+
+pub struct PositionTable {
+    shape: BoardShape,
+    rules: Rules,
+    book: Vec<Entry>,
+    /// Perfect-play game length from each index, see `depths`. Walked on
+    /// first use, so loading a table does not pay for it.
+    plies: OnceLock<Vec<u8>>,
+}
+
+impl PositionTable {
+    // Only the tests call this directly once `solve` goes parallel.
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    pub fn new(shape: BoardShape, rules: Rules) -> Self {
+        let mut book = vec![Entry::UNEXPLORED; shape.table_size()];
+        // Base cases. Under `PoisonLoses` the player facing a fully eaten board
+        // has won and the glass-only one is lost. Under `LastMoveWins` the fully
+        // eaten board is lost, and the search finds that glass-only is won by
+        // taking the poison.
+        let full = vec![shape.cols; shape.rows as usize];
+        match rules {
+            Rules::PoisonLoses => {
+                let mut glass = full.clone();
+                glass[shape.rows as usize - 1] -= 1;
+                book[shape.encode(&full)] = Entry::EATEN;
+                book[shape.encode(&glass)] = Entry::LOSING;
+            }
+            Rules::LastMoveWins => book[shape.encode(&full)] = Entry::LOSING,
+        }
+
+        // Depth-first over an explicit stack, so the depth of the game tree
+        // never reaches the call stack. An index is pushed unexpanded to
+        // discover its children, then seen again once they are all resolved
+        // and classified: winning through the last move that leaves a losing
+        // child, losing when there is none.
+        let mut stack = vec![(shape.encode(&vec![0; shape.rows as usize]), false)];
+        while let Some((idx, expanded)) = stack.pop() {
+            if book[idx] != Entry::UNEXPLORED {
+                continue;
+            }
+            let snapshot = shape.decode(idx);
+            let mut children = (0..shape.rows).flat_map(|r| {
+                let snapshot = &snapshot;
+                ((snapshot[r as usize] + 1)..=shape.cols)
+                    .map(move |c| (r, c, shape.encode(&after(snapshot, r, c))))
+            });
+            if expanded {
+                book[idx] = children
+                    .rfind(|&(_, _, next_idx)| book[next_idx] == Entry::LOSING)
+                    .map_or(Entry::LOSING, |(r, c, _)| {
+                        Entry::pack(Classified::Winning(r, c), shape)
+                    });
+            } else {
+                stack.push((idx, true));
+                stack.extend(
+                    children
+                        .filter(|&(_, _, next_idx)| book[next_idx] == Entry::UNEXPLORED)
+                        .map(|(_, _, next_idx)| (next_idx, false)),
+                );
+            }
+        }
+
+        Self::from_book(shape, rules, book)
+    }
+
+    /// `new` spread over the rayon pool. Positions are classified a layer at
+    /// a time, from the most eaten to the empty board: every move eats at
+    /// least one square, so a layer only reads classifications settled by
+    /// earlier ones and its positions are independent of each other. They
+    /// share one array of `Entry` codes, written without locks, and the
+    /// moves are scanned in the same order as `new`, so both builders return
+    /// the same table.
+    #[cfg(feature = "parallel")]
+    pub fn new_parallel(shape: BoardShape, rules: Rules) -> Self {
+        use rayon::prelude::*;
+        use std::sync::atomic::{AtomicU8, Ordering};
+
+        let codes: Vec<AtomicU8> = (0..shape.table_size()).map(|_| AtomicU8::new(0)).collect();
+        let set = |counts: &[u8], entry: Classified| {
+            codes[shape.encode(counts)].store(entry.code(shape), Ordering::Relaxed)
+        };
+        let full = vec![shape.cols; shape.rows as usize];
+        match rules {
+            Rules::PoisonLoses => {
+                let mut glass = full.clone();
+                glass[shape.rows as usize - 1] -= 1;
+                set(&full, Classified::Winning(0xFF, 0xFF));
+                set(&glass, Classified::Losing);
+            }
+            Rules::LastMoveWins => set(&full, Classified::Losing),
+        }
+
+        let losing = Classified::Losing.code(shape);
+        for layer in skylines_by_eaten(shape).iter().rev() {
+            // `par_iter` returns once the whole layer is stored, which orders
+            // these relaxed writes before the next layer's reads.
+            layer.par_iter().for_each(|&idx| {
+                if codes[idx].load(Ordering::Relaxed) != 0 {
+                    return;
+                }
+                let snapshot = shape.decode(idx);
+                let entry = (0..shape.rows)
+                    .flat_map(|r| ((snapshot[r as usize] + 1)..=shape.cols).map(move |c| (r, c)))
+                    .rfind(|&(r, c)| {
+                        let next_idx = shape.encode(&after(&snapshot, r, c));
+                        codes[next_idx].load(Ordering::Relaxed) == losing
+                    })
+                    .map_or(Classified::Losing, |(r, c)| Classified::Winning(r, c));
+                codes[idx].store(entry.code(shape), Ordering::Relaxed);
+            });
+        }
+
+        let book = codes
+            .into_iter()
+            .map(|code| Entry(code.into_inner()))
+            .collect();
+        Self::from_book(shape, rules, book)
+    }
+
+    /// Builds the table with `new_parallel` when the `parallel` feature is
+    /// on and `new` otherwise.
+    pub fn solve(shape: BoardShape, rules: Rules) -> Self {
+        #[cfg(feature = "parallel")]
+        return Self::new_parallel(shape, rules);
+        #[cfg(not(feature = "parallel"))]
+        Self::new(shape, rules)
+    }
+
+    /// The stored winning move from `counts` as 0-based row and 1-based
+    /// column, or `None` when the position is lost or already fully eaten.
+    pub fn best_reply(&self, counts: &[u8]) -> Option<(usize, usize)> {
+        match self.classified(self.shape.encode(counts)) {
+            Classified::Winning(0xFF, 0xFF) => None,
+            Classified::Winning(r, c) => Some((r as usize, c as usize)),
+            _ => None,
+        }
+    }
+
+    pub fn is_winning(&self, counts: &[u8]) -> bool {
+        matches!(
+            self.classified(self.shape.encode(counts)),
+            Classified::Winning(..)
+        )
+    }
+
+    /// Re-derives every explored entry from its children: a stored winning
+    /// move must lead to a `Losing` child and a `Losing` entry must have none.
+    pub fn verify(&self) -> bool {
+        (0..self.book.len()).all(|idx| match self.classified(idx) {
+            Classified::Unexplored | Classified::Winning(0xFF, 0xFF) => true,
+            Classified::Winning(r, c) => {
+                let child = after(&self.shape.decode(idx), r, c);
+                self.book[self.shape.encode(&child)] == Entry::LOSING
+            }
+            Classified::Losing => self.all_winning_replies(&self.shape.decode(idx)).is_empty(),
+        })
+    }
+
+    /// Perfect-play game length for every explored index: the fewest plies
+    /// the winner needs and the most the loser can hold out for. Unexplored
+    /// indices are 0.
+    pub fn depths(&self) -> &[u8] {
+        self.plies
+            .get_or_init(|| count_plies(self.shape, &self.book))
+    }
+
+    /// The winning reply from `counts` that ends the game soonest, or `None`
+    /// where `best_reply` has none. Each ply is a paid transaction, so this
+    /// is the cheapest way to collect a won game.
+    pub fn best_reply_fastest(&self, counts: &[u8]) -> Option<(usize, usize)> {
+        self.best_reply(counts)?;
+        let plies = self.depths();
+        self.all_winning_replies(counts)
+            .into_iter()
+            .min_by_key(|&(r, c)| plies[self.shape.encode(&after(counts, r as u8, c as u8))])
+    }
+
+    /// From a `Losing` position, the reply that makes the winner work
+    /// longest, so an opponent who can blunder gets the most chances to. Ties
+    /// go to the smaller bite. `None` for winning positions.
+    pub fn best_defensive_reply(&self, counts: &[u8]) -> Option<(usize, usize)> {
+        if self.book[self.shape.encode(counts)] != Entry::LOSING {
+            return None;
+        }
+        (0..self.shape.rows)
+            .flat_map(|r| ((counts[r as usize] + 1)..=self.shape.cols).map(move |c| (r, c)))
+            .max_by_key(|&(r, c)| {
+                let next = after(counts, r, c);
+                let eaten: u32 = next.iter().map(|&n| u32::from(n)).sum();
+                (
+                    self.depths()[self.shape.encode(&next)],
+                    std::cmp::Reverse(eaten),
+                )
+            })
+            .map(|(r, c)| (r as usize, c as usize))
+    }
+
+    /// Every move from `counts` that leaves the opponent in a `Losing`
+    /// position, in the same `(row, col)` form as `best_reply`. The book only
+    /// keeps one move per position, so the children are rescanned here.
+    pub fn all_winning_replies(&self, counts: &[u8]) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        for r in 0..self.shape.rows {
+            for c in (counts[r as usize] + 1)..=self.shape.cols {
+                if self.book[self.shape.encode(&after(counts, r, c))] == Entry::LOSING {
+                    moves.push((r as usize, c as usize));
+                }
+            }
+        }
+        moves
+    }
+
+    /// One byte per entry, `table_size` bytes in all: 0 unexplored, 1 losing,
+    /// 2 the fully eaten ending, otherwise `3 + row * cols + col - 1` for the
+    /// stored winning move. `MAX_TABLE_BYTES` keeps `rows * cols` small
+    /// enough for that to fit.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.book.iter().map(|entry| entry.0).collect()
+    }
+
+    /// Inverse of `to_bytes` for a table of `shape` solved under `rules`;
+    /// rejects blobs of the wrong size or with entries `to_bytes` never writes.
+    pub fn from_bytes(shape: BoardShape, rules: Rules, bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != shape.table_size() {
+            return Err(format!(
+                "expected {} table bytes for {}, got {}",
+                shape.table_size(),
+                shape,
+                bytes.len()
+            ));
+        }
+        let mut book = Vec::with_capacity(bytes.len());
+        for (idx, &byte) in bytes.iter().enumerate() {
+            Classified::from_code(byte, shape)
+                .ok_or_else(|| format!("bad table byte {:#04x} at index {}", byte, idx))?;
+            book.push(Entry(byte));
+        }
+        Ok(Self::from_book(shape, rules, book))
+    }
+
+    fn from_book(shape: BoardShape, rules: Rules, book: Vec<Entry>) -> Self {
+        Self {
+            shape,
+            rules,
+            book,
+            plies: OnceLock::new(),
+        }
+    }
+
+    /// Writes `CACHE_MAGIC`, `CACHE_VERSION`, the rows and columns and the
+    /// rules (1 for `LastMoveWins`), then the `to_bytes` entries.
+    pub fn save_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(CACHE_MAGIC)?;
+        let rules = u8::from(self.rules == Rules::LastMoveWins);
+        w.write_all(&[CACHE_VERSION, self.shape.rows, self.shape.cols, rules])?;
+        w.write_all(&self.to_bytes())?;
+        w.flush()
+    }
+
+    /// Reads a table written by `save_to`. A foreign file, another format
+    /// version or a bad entry is `InvalidData`. The shape and rules come from
+    /// the header, so callers compare them with the game they play.
+    pub fn load_from<R: Read>(mut r: R) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut header = [0u8; CACHE_MAGIC.len() + 4];
+        r.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("not a strategy table: too short".to_string()),
+            _ => e,
+        })?;
+        let (magic, rest) = header.split_at(CACHE_MAGIC.len());
+        if magic != CACHE_MAGIC {
+            return Err(invalid("not a strategy table".to_string()));
+        }
+        if rest[0] != CACHE_VERSION {
+            return Err(invalid(format!(
+                "table format version {}, expected {}",
+                rest[0], CACHE_VERSION
+            )));
+        }
+        let shape = BoardShape::new(rest[1], rest[2]).map_err(invalid)?;
+        let rules = match rest[3] {
+            0 => Rules::PoisonLoses,
+            1 => Rules::LastMoveWins,
+            other => return Err(invalid(format!("unknown rules byte {}", other))),
+        };
+        let mut bytes = Vec::with_capacity(shape.table_size());
+        r.read_to_end(&mut bytes)?;
+        Self::from_bytes(shape, rules, &bytes).map_err(invalid)
+    }
+
+    fn classified(&self, idx: usize) -> Classified {
+        self.book[idx].get(self.shape)
+    }
+
+    pub fn shape(&self) -> BoardShape {
+        self.shape
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip_and_reject_garbage() {
+        let shape = BoardShape::STANDARD;
+        let table = PositionTable::new(shape, Rules::PoisonLoses);
+        let bytes = table.to_bytes();
+        assert_eq!(bytes.len(), TABLE_SIZE);
+        let decoded = PositionTable::from_bytes(shape, Rules::PoisonLoses, &bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        // Loading leaves the game lengths for the first `depths` call.
+        assert!(decoded.plies.get().is_none());
+        assert_eq!(decoded.depths(), table.depths());
+
+        assert!(PositionTable::from_bytes(shape, Rules::PoisonLoses, &bytes[1..]).is_err());
+        let narrow = BoardShape::new(5, 7).unwrap();
+        assert!(PositionTable::from_bytes(narrow, Rules::PoisonLoses, &bytes).is_err());
+        let mut bad = bytes;
+        bad[7] = 3 + 5 * 8;
+        assert!(PositionTable::from_bytes(shape, Rules::PoisonLoses, &bad).is_err());
+    }
+
+    /// The book as the enum it used to store, solved by plain recursion with
+    /// the same move order and base cases as `PositionTable::new`.
+    fn unpacked_book(shape: BoardShape, rules: Rules) -> Vec<Classified> {
+        fn solve(shape: BoardShape, idx: usize, book: &mut [Classified]) -> Classified {
+            if book[idx] != Classified::Unexplored {
+                return book[idx];
+            }
+            let snapshot = shape.decode(idx);
+            let mut entry = Classified::Losing;
+            for r in 0..shape.rows {
+                for c in (snapshot[r as usize] + 1)..=shape.cols {
+                    let child = shape.encode(&after(&snapshot, r, c));
+                    if solve(shape, child, book) == Classified::Losing {
+                        entry = Classified::Winning(r, c);
+                    }
+                }
+            }
+            book[idx] = entry;
+            entry
+        }
+
+        let mut book = vec![Classified::Unexplored; shape.table_size()];
+        let full = vec![shape.cols; shape.rows as usize];
+        match rules {
+            Rules::PoisonLoses => {
+                let mut glass = full.clone();
+                glass[shape.rows as usize - 1] -= 1;
+                book[shape.encode(&full)] = Classified::Winning(0xFF, 0xFF);
+                book[shape.encode(&glass)] = Classified::Losing;
+            }
+            Rules::LastMoveWins => book[shape.encode(&full)] = Classified::Losing,
+        }
+        solve(
+            shape,
+            shape.encode(&vec![0; shape.rows as usize]),
+            &mut book,
+        );
+        book
+    }
+
+    #[test]
+    fn packed_book_agrees_with_the_unpacked_one() {
+        assert_eq!(std::mem::size_of::<Entry>(), 1);
+        let shapes = [
+            BoardShape::STANDARD,
+            BoardShape::new(1, 1).unwrap(),
+            BoardShape::new(3, 8).unwrap(),
+            BoardShape::new(9, 4).unwrap(),
+        ];
+        for shape in shapes {
+            for rules in [Rules::PoisonLoses, Rules::LastMoveWins] {
+                let table = PositionTable::solve(shape, rules);
+                let expected = unpacked_book(shape, rules);
+                for (idx, &entry) in expected.iter().enumerate() {
+                    assert_eq!(
+                        table.classified(idx),
+                        entry,
+                        "{} under {:?} at index {}",
+                        shape,
+                        rules,
+                        idx
+                    );
+                    assert_eq!(Entry::pack(entry, shape).get(shape), entry);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn saved_tables_load_back_and_stale_ones_are_refused() {
+        let table = PositionTable::new(BoardShape::new(4, 6).unwrap(), Rules::LastMoveWins);
+        let mut file = Vec::new();
+        table.save_to(&mut file).unwrap();
+        assert_eq!(file.len(), 12 + (1 << 10));
+        let loaded = PositionTable::load_from(file.as_slice()).unwrap();
+        assert_eq!(loaded.shape(), table.shape());
+        assert_eq!(loaded.rules(), Rules::LastMoveWins);
+        assert_eq!(loaded.to_bytes(), table.to_bytes());
+
+        let reject = |bytes: &[u8]| match PositionTable::load_from(bytes) {
+            Ok(_) => panic!("loaded a bad table"),
+            Err(e) => e.to_string(),
+        };
+        let mut stale = file.clone();
+        stale[8] = CACHE_VERSION + 1;
+        assert!(reject(&stale).contains("version"));
+        assert!(reject(b"not a table at all").contains("not a strategy table"));
+        assert!(reject(b"junk").contains("too short"));
+        assert!(reject(&file[..file.len() - 1]).contains("table bytes"));
+    }
+
+    #[test]
+    fn shapes_are_bounded_by_width_and_memory() {
+        assert_eq!(BoardShape::new(5, 8), Ok(BoardShape::STANDARD));
+        assert!(BoardShape::new(0, 4).is_err());
+        assert!(BoardShape::new(4, 0).is_err());
+        assert!(BoardShape::new(4, 9).is_err());
+        assert!(BoardShape::new(21, 8).is_ok());
+        let err = BoardShape::new(22, 8).unwrap_err();
+        assert!(err.contains("2^30"), "{}", err);
+        assert!(BoardShape::new(255, 8).is_err());
+    }
+
+    #[test]
+    fn solves_other_shapes() {
+        // A 2xN bar is won by eating the single top-left square, leaving the
+        // top row one short of the bottom one.
+        let bar = PositionTable::new(BoardShape::new(2, 6).unwrap(), Rules::PoisonLoses);
+        assert_eq!(bar.all_winning_replies(&[0, 0]), vec![(0, 1)]);
+        // On a square board the only win eats all but an L of equal arms.
+        let square = PositionTable::new(BoardShape::new(4, 4).unwrap(), Rules::PoisonLoses);
+        assert_eq!(square.all_winning_replies(&[0; 4]), vec![(2, 3)]);
+
+        for (rows, cols) in [(4, 6), (6, 7), (1, 1)] {
+            let shape = BoardShape::new(rows, cols).unwrap();
+            let table = PositionTable::new(shape, Rules::PoisonLoses);
+            assert!(table.verify(), "{}", shape);
+            let empty = vec![0; rows as usize];
+            // Strategy stealing: every board but the lone poison is a first-player win.
+            assert_eq!(
+                table.is_winning(&empty),
+                (rows, cols) != (1, 1),
+                "{}",
+                shape
+            );
+            assert_eq!(shape.decode(shape.encode(&empty)), empty);
+        }
+    }
+
+    #[test]
+    fn iterative_build_keeps_the_known_opening() {
+        let table = PositionTable::new(BoardShape::STANDARD, Rules::PoisonLoses);
+        let empty = [0u8; ROW_COUNT];
+        // (1,2) in 1-based moves: eat the top row's first two squares.
+        assert_eq!(table.best_reply(&empty), Some((0, 2)));
+        assert_eq!(table.all_winning_replies(&empty), vec![(0, 2)]);
+        assert!(table.verify());
+        assert_eq!(table.depths()[BoardShape::STANDARD.encode(&empty)], 30);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_build_matches_the_sequential_one() {
+        let shapes = [
+            BoardShape::STANDARD,
+            BoardShape::new(1, 1).unwrap(),
+            BoardShape::new(3, 8).unwrap(),
+            BoardShape::new(9, 4).unwrap(),
+        ];
+        for shape in shapes {
+            for rules in [Rules::PoisonLoses, Rules::LastMoveWins] {
+                assert_eq!(
+                    PositionTable::new_parallel(shape, rules).to_bytes(),
+                    PositionTable::new(shape, rules).to_bytes(),
+                    "{} under {:?}",
+                    shape,
+                    rules
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lists_every_winning_reply_mid_game() {
+        let table = PositionTable::new(BoardShape::STANDARD, Rules::PoisonLoses);
+        // Four squares gone from the top row and one from the second.
+        let counts = [4, 1, 0, 0, 0];
+        let replies = table.all_winning_replies(&counts);
+        assert_eq!(replies, vec![(0, 5), (1, 2), (2, 1)]);
+        assert!(replies.contains(&table.best_reply(&counts).unwrap()));
+        for &(r, c) in &replies {
+            assert!(!table.is_winning(&after(&counts, r as u8, c as u8)));
+        }
+    }
+
+    #[test]
+    fn fastest_reply_takes_the_shortest_win() {
+        let table = PositionTable::new(BoardShape::STANDARD, Rules::PoisonLoses);
+        let counts = [3, 2, 2, 0, 0];
+        assert_eq!(table.all_winning_replies(&counts), vec![(1, 4), (3, 2)]);
+        // The stored move wins too, but the loser can hold out two plies longer.
+        assert_eq!(table.best_reply(&counts), Some((3, 2)));
+        assert_eq!(table.best_reply_fastest(&counts), Some((1, 4)));
+        let plies = |counts: &[u8]| table.depths()[BoardShape::STANDARD.encode(counts)];
+        assert_eq!(plies(&counts), 24);
+        assert_eq!(plies(&after(&counts, 1, 4)), 23);
+        assert_eq!(plies(&after(&counts, 3, 2)), 25);
+
+        let lost = [8, 8, 8, 8, 7];
+        assert_eq!(table.best_reply_fastest(&lost), None);
+    }
+
+    #[test]
+    fn defensive_reply_stalls_a_lost_game() {
+        let small = BoardShape::new(3, 3).unwrap();
+        let table = PositionTable::new(small, Rules::PoisonLoses);
+        let counts = [3, 1, 0];
+        assert!(!table.is_winning(&counts));
+        // (1,2) and (2,1) both hold out for four more plies and eat one square.
+        assert_eq!(table.best_defensive_reply(&counts), Some((2, 1)));
+        assert_eq!(table.depths()[small.encode(&counts)], 5);
+        assert_eq!(table.best_defensive_reply(&[0; 3]), None);
+
+        // Every one-square bite here loses within 16 plies; (3,2) lasts 18.
+        let table = PositionTable::new(BoardShape::STANDARD, Rules::PoisonLoses);
+        let counts = [6, 4, 4, 0, 0];
+        assert_eq!(table.best_defensive_reply(&counts), Some((3, 2)));
+        assert_eq!(
+            table.depths()[BoardShape::STANDARD.encode(&after(&counts, 3, 2))],
+            18
+        );
+    }
+
+    #[test]
+    fn last_move_wins_changes_the_opening() {
+        let empty = [0u8; ROW_COUNT];
+        let misere = PositionTable::new(BoardShape::STANDARD, Rules::PoisonLoses);
+        let normal = PositionTable::new(BoardShape::STANDARD, Rules::LastMoveWins);
+        // Taking the poison eats the whole board, which wins outright.
+        assert_eq!(normal.best_reply(&empty), Some((4, 8)));
+        assert_eq!(normal.all_winning_replies(&empty), vec![(4, 8)]);
+        assert!(!misere.all_winning_replies(&empty).contains(&(4, 8)));
+        assert_ne!(misere.best_reply(&empty), normal.best_reply(&empty));
+
+        let full = [COLS_U8; ROW_COUNT];
+        let glass = [8, 8, 8, 8, 7];
+        assert!(misere.is_winning(&full) && !normal.is_winning(&full));
+        assert!(!misere.is_winning(&glass) && normal.is_winning(&glass));
+        assert!(normal.verify());
+        assert_eq!(normal.depths()[BoardShape::STANDARD.encode(&full)], 0);
+        assert_eq!(normal.depths()[BoardShape::STANDARD.encode(&empty)], 1);
+    }
+
+    #[test]
+    fn masks_convert_within_the_shape() {
+        let shape = BoardShape::new(3, 4).unwrap();
+        assert_eq!(shape.counts_from_masks(&[0xF0, 0xFF, 0x80]), vec![4, 4, 1]);
+        assert_eq!(
+            BoardShape::STANDARD.counts_from_masks(&[0xFF, 0xFE, 0xC0, 0, 0]),
+            vec![8, 7, 2, 0, 0]
+        );
+    }
+}
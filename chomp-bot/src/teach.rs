@@ -0,0 +1,189 @@
+//! `--teach`: an offline quiz against the solver. Each turn the board is
+//! shown, the player guesses a move, and the guess is graded before the
+//! solver's answer is revealed. The guess is then played, blunders included,
+//! and the solver replies for the other side.
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::config::parse_move;
+use crate::game::{self, Outcome};
+use crate::render::format_board;
+
+const POISON: (u8, u8) = (5, 8);
+
+/// How a guess compares with the solver's analysis.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The guess is one of the winning moves.
+    Winning,
+    /// A winning move existed and the guess was not one.
+    Missed,
+    /// The position was already lost, so every move loses; not scored.
+    NoWin,
+}
+
+pub fn grade(board: [u8; 5], guess: (u8, u8)) -> Verdict {
+    let winners = game::winning_moves(board);
+    if winners.is_empty() {
+        Verdict::NoWin
+    } else if winners.contains(&guess) {
+        Verdict::Winning
+    } else {
+        Verdict::Missed
+    }
+}
+
+/// Guesses graded over a session; lost positions are not counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub asked: u32,
+    pub correct: u32,
+}
+
+impl Score {
+    fn record(&mut self, verdict: Verdict) {
+        if verdict != Verdict::NoWin {
+            self.asked += 1;
+            self.correct += u32::from(verdict == Verdict::Winning);
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.asked == 0 {
+            return write!(f, "no graded guesses");
+        }
+        write!(
+            f,
+            "{}/{} winning guesses ({:.0}%)",
+            self.correct,
+            self.asked,
+            100.0 * f64::from(self.correct) / f64::from(self.asked)
+        )
+    }
+}
+
+/// Runs the quiz from `start` until the game ends, the input runs out or the
+/// player types `q`.
+pub fn run(start: [u8; 5], input: impl BufRead, mut out: impl Write) -> io::Result<Score> {
+    let mut score = Score::default();
+    let mut board = start;
+    let mut lines = input.lines();
+    loop {
+        if game::is_glass_only(board) {
+            writeln!(out, "Only the poison is left for you — the solver wins.")?;
+            break;
+        }
+        write!(out, "{}", format_board(&board, None))?;
+        write!(out, "your move (r,c), or q to quit: ")?;
+        out.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("q") {
+            break;
+        }
+        let guess = match parse_move(line) {
+            Ok(mv) if game::move_is_open(board, mv.0, mv.1) => mv,
+            Ok((r, c)) => {
+                writeln!(out, "({},{}) is not an open square; try again.", r, c)?;
+                continue;
+            }
+            Err(e) => {
+                writeln!(out, "{}; try again.", e)?;
+                continue;
+            }
+        };
+
+        let verdict = grade(board, guess);
+        score.record(verdict);
+        explain(&mut out, board, guess, verdict)?;
+
+        if guess == POISON {
+            writeln!(out, "You ate the poison — the solver wins.")?;
+            break;
+        }
+        board = game::apply_move(board, guess.0, guess.1);
+        if game::is_glass_only(board) {
+            writeln!(out, "The solver is left with the poison — you win!")?;
+            break;
+        }
+        let reply = game::choose_move(board).expect("an open square besides the poison");
+        writeln!(out, "solver plays ({},{})", reply.0, reply.1)?;
+        board = game::apply_move(board, reply.0, reply.1);
+    }
+    writeln!(out, "session: {}", score)?;
+    Ok(score)
+}
+
+fn explain(
+    out: &mut impl Write,
+    board: [u8; 5],
+    guess: (u8, u8),
+    verdict: Verdict,
+) -> io::Result<()> {
+    let best = game::choose_move(board).expect("an open square");
+    let plies = game::depth(board);
+    match verdict {
+        Verdict::Winning => writeln!(
+            out,
+            "Correct: ({},{}) leaves your opponent a losing position.",
+            guess.0, guess.1
+        )?,
+        Verdict::Missed => writeln!(
+            out,
+            "Not quite: after ({},{}) your opponent has a winning reply.",
+            guess.0, guess.1
+        )?,
+        Verdict::NoWin => writeln!(
+            out,
+            "This position is {:?} for you: every move loses against perfect play.",
+            Outcome::Losing
+        )?,
+    }
+    let winners = game::winning_moves(board);
+    if winners.is_empty() {
+        writeln!(
+            out,
+            "The solver's best try is ({},{}), which drags the game out to {} plies.",
+            best.0, best.1, plies
+        )
+    } else {
+        writeln!(
+            out,
+            "The solver plays ({},{}) and wins within {} plies; all winning moves: {:?}",
+            best.0, best.1, plies, winners
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grades_against_winning_moves() {
+        let empty = [0u8; 5];
+        assert_eq!(grade(empty, (1, 2)), Verdict::Winning);
+        assert_eq!(grade(empty, (5, 1)), Verdict::Missed);
+        let lost = [0xFF, 0xFF, 0xFF, 0xFE, 0xFC];
+        assert_eq!(grade(lost, (4, 8)), Verdict::NoWin);
+    }
+
+    #[test]
+    fn scripted_session_tracks_accuracy() {
+        let mut out = Vec::new();
+        let score = run([0u8; 5], b"nonsense\n9,9\n1,2\nq\n" as &[u8], &mut out).unwrap();
+        assert_eq!(score, Score { asked: 1, correct: 1 });
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("try again"));
+        assert!(text.contains("solver plays"));
+        assert!(text.contains("session: 1/1"));
+
+        let score = run([0u8; 5], b"5,1\n" as &[u8], Vec::new()).unwrap();
+        assert_eq!(score, Score { asked: 1, correct: 0 });
+    }
+}
@@ -0,0 +1,256 @@
+//! `--tui` (`--features tui`): a full-screen dashboard for single-wallet
+//! autoplay, in place of the scrolling console. It shows the board with the
+//! chosen move, the move count, the elapsed time, the last signature and the
+//! tail of the log. The autoplay loop keeps running on the main thread and
+//! only updates `STATE`; a second thread redraws it every `TICK` and reads the
+//! keyboard. `q` or Ctrl-C stops autoplay the way SIGINT does.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::render::format_board;
+use crate::shutdown;
+
+/// How often the dashboard is redrawn and the keyboard checked.
+const TICK: Duration = Duration::from_millis(100);
+/// Log lines kept for the pane, and printed to stderr once the dashboard closes.
+const LOG_LINES: usize = 200;
+
+/// True while the dashboard owns the terminal.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<State> = Mutex::new(State {
+    started: None,
+    board: None,
+    chosen: None,
+    moves: 0,
+    last_signature: None,
+    log: VecDeque::new(),
+    partial: String::new(),
+});
+
+struct State {
+    started: Option<Instant>,
+    board: Option<[u8; 5]>,
+    chosen: Option<(u8, u8)>,
+    moves: u32,
+    last_signature: Option<String>,
+    log: VecDeque<String>,
+    /// A log line `LogPipe` has only been handed part of.
+    partial: String,
+}
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// The board autoplay just read, with the move it chose on it if any.
+pub fn show_board(board: &[u8; 5], highlight: Option<(u8, u8)>) {
+    let mut state = state();
+    state.board = Some(*board);
+    if highlight.is_some() {
+        state.chosen = highlight;
+    }
+}
+
+/// A move of ours confirmed as `signature`.
+pub fn record_move(signature: &str) {
+    let mut state = state();
+    state.moves += 1;
+    state.last_signature = Some(signature.to_string());
+}
+
+/// The console logger's output: kept for the log pane while the dashboard is
+/// up, and passed on to stderr otherwise.
+pub struct LogPipe;
+
+impl Write for LogPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !active() {
+            return io::stderr().write(buf);
+        }
+        let mut state = state();
+        let text = String::from_utf8_lossy(buf).into_owned();
+        state.partial.push_str(&text);
+        while let Some(end) = state.partial.find('\n') {
+            let line: String = state.partial.drain(..=end).collect();
+            if state.log.len() == LOG_LINES {
+                state.log.pop_front();
+            }
+            state.log.push_back(line.trim_end().to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// The running dashboard. Dropping it gives the terminal back and prints the
+/// kept log lines, so the end of the run stays readable after it closes.
+pub struct Dashboard {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Switches the terminal to the dashboard until the returned value is dropped.
+pub fn start() -> io::Result<Dashboard> {
+    let mut terminal = ratatui::try_init()?;
+    state().started = Some(Instant::now());
+    ACTIVE.store(true, Ordering::Relaxed);
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopping = Arc::clone(&stop);
+    let thread = thread::spawn(move || {
+        while !stopping.load(Ordering::Relaxed) {
+            // A failed draw only costs a frame; autoplay carries on.
+            let _ = terminal.draw(draw);
+            if let Ok(true) = event::poll(TICK) {
+                if let Ok(Event::Key(key)) = event::read() {
+                    let ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if key.kind == KeyEventKind::Press && (ctrl_c || key.code == KeyCode::Char('q'))
+                    {
+                        stop_autoplay();
+                    }
+                }
+            }
+        }
+    });
+    Ok(Dashboard {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+/// Raw mode swallows SIGINT, so `q` and Ctrl-C stand in for it: the first
+/// asks autoplay to stop after the move in flight, the second exits at once.
+fn stop_autoplay() {
+    if shutdown::requested() {
+        ratatui::restore();
+        log::logger().flush();
+        std::process::exit(130);
+    }
+    shutdown::request();
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        ratatui::restore();
+        ACTIVE.store(false, Ordering::Relaxed);
+        let mut state = state();
+        let mut err = io::stderr().lock();
+        for line in state.log.drain(..) {
+            let _ = writeln!(err, "{}", line);
+        }
+    }
+}
+
+fn draw(frame: &mut Frame) {
+    let state = state();
+    let [top, log] =
+        Layout::vertical([Constraint::Length(9), Constraint::Min(3)]).areas(frame.area());
+    let [board, status] =
+        Layout::horizontal([Constraint::Length(22), Constraint::Min(20)]).areas(top);
+
+    let grid = match &state.board {
+        Some(rows) => format_board(rows, state.chosen),
+        None => "waiting for the\nfirst board".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(grid).block(Block::bordered().title(" board ")),
+        board,
+    );
+
+    let elapsed = state.started.map(|at| at.elapsed()).unwrap_or_default();
+    let lines = [
+        format!(
+            "chosen:   {}",
+            state
+                .chosen
+                .map(|(r, c)| format!("({},{})", r, c))
+                .unwrap_or_else(|| "-".into())
+        ),
+        format!("moves:    {}", state.moves),
+        format!(
+            "elapsed:  {}",
+            humantime::format_duration(Duration::from_secs(elapsed.as_secs()))
+        ),
+        format!(
+            "last sig: {}",
+            state.last_signature.as_deref().unwrap_or("-")
+        ),
+        String::new(),
+        if shutdown::requested() {
+            "stopping after the move in flight (again to quit now)".to_string()
+        } else {
+            "q or Ctrl-C stops after the move in flight".to_string()
+        },
+    ];
+    frame.render_widget(
+        Paragraph::new(lines.join("\n")).block(Block::bordered().title(" autoplay ")),
+        status,
+    );
+
+    let shown = usize::from(log.height.saturating_sub(2));
+    let tail: Vec<&str> = state
+        .log
+        .iter()
+        .skip(state.log.len().saturating_sub(shown))
+        .map(String::as_str)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(tail.join("\n")).block(Block::bordered().title(" log ")),
+        log,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn draws_the_board_status_and_log_tail() {
+        show_board(&[0xC0, 0, 0, 0, 0], Some((2, 3)));
+        record_move("5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW");
+        {
+            let mut state = state();
+            state
+                .log
+                .extend(["first line".to_string(), "newest line".to_string()]);
+        }
+        let mut terminal = Terminal::new(TestBackend::new(100, 14)).unwrap();
+        terminal.draw(draw).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("chosen:   (2,3)"), "{}", screen);
+        assert!(screen.contains("moves:    1"), "{}", screen);
+        assert!(screen.contains("last sig: 5VERv8"), "{}", screen);
+        assert!(screen.contains("newest line"), "{}", screen);
+        assert!(screen.contains("1 . . # # # # # #"), "{}", screen);
+        assert!(screen.contains("2 # # * # # # # #"), "{}", screen);
+    }
+}